@@ -0,0 +1,7 @@
+-- Migration: Add HTTP cache validators to feeds
+-- Stores the ETag / Last-Modified headers from the last successful fetch so the
+-- fetcher can send conditional requests and skip unchanged feeds.
+
+ALTER TABLE feeds
+    ADD COLUMN etag VARCHAR(1000) NULL,
+    ADD COLUMN last_modified VARCHAR(100) NULL;
@@ -0,0 +1,12 @@
+-- Migration: Add feed metadata populated from the parsed feed
+--
+-- title_locked marks feeds whose title was set by hand (e.g. by an admin); the
+-- fetcher leaves those titles alone. The curated seed feeds have hand-picked
+-- titles, so they start out locked.
+
+ALTER TABLE feeds
+    ADD COLUMN language VARCHAR(50) NULL,
+    ADD COLUMN icon_url VARCHAR(2000) NULL,
+    ADD COLUMN title_locked BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE feeds SET title_locked = TRUE WHERE is_curated = TRUE;
@@ -0,0 +1,11 @@
+-- Migration: Add adaptive per-feed fetch scheduling
+--
+-- fetch_interval_minutes is recomputed on every fetch from the feed's posting
+-- frequency and publisher hints; next_fetch_at is when the scheduler should
+-- pick the feed up again. NULL next_fetch_at means "due now".
+
+ALTER TABLE feeds
+    ADD COLUMN fetch_interval_minutes INT NULL,
+    ADD COLUMN next_fetch_at TIMESTAMPTZ NULL;
+
+CREATE INDEX idx_feeds_next_fetch_at ON feeds (next_fetch_at);
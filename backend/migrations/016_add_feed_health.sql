@@ -0,0 +1,15 @@
+-- Migration: Add feed health tracking
+--
+-- consecutive_failures drives exponential backoff in the scheduler; once it
+-- reaches the configured threshold the feed is marked disabled and no longer
+-- fetched. last_fetched_at keeps meaning "last attempt", last_success_at is the
+-- last fetch that completed.
+
+ALTER TABLE feeds
+    ADD COLUMN consecutive_failures INT NOT NULL DEFAULT 0,
+    ADD COLUMN last_error TEXT NULL,
+    ADD COLUMN last_error_at TIMESTAMPTZ NULL,
+    ADD COLUMN last_success_at TIMESTAMPTZ NULL,
+    ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE feeds SET last_success_at = last_fetched_at;
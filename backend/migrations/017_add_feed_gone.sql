@@ -0,0 +1,8 @@
+-- Migration: Track feeds that no longer exist
+--
+-- A feed answering 410 Gone is marked with gone_at and is no longer fetched.
+-- Permanent redirects are handled in place by rewriting feeds.url (or merging
+-- into the feed already stored at the new URL), so they need no column.
+
+ALTER TABLE feeds
+    ADD COLUMN gone_at TIMESTAMPTZ NULL;
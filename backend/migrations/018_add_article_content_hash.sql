@@ -0,0 +1,9 @@
+-- Migration: Add article content hashes
+--
+-- content_hash is a SHA-256 over the fields an entry is ingested with. Re-fetched
+-- entries whose hash matches the stored one are left untouched instead of being
+-- rewritten on every fetch. Existing rows start NULL and are hashed the next
+-- time their feed is fetched.
+
+ALTER TABLE articles
+    ADD COLUMN content_hash VARCHAR(64) NULL;
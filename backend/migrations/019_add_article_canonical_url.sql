@@ -0,0 +1,12 @@
+-- Migration: Add canonical article URLs
+--
+-- canonical_url is the article link normalized (tracking params, fragment and
+-- www. stripped, https, sorted query). Articles from different feeds sharing a
+-- canonical_url are copies of the same story: listings can collapse them and
+-- read state is shared between them.
+
+ALTER TABLE articles
+    ADD COLUMN canonical_url VARCHAR(2000) NULL;
+
+CREATE INDEX idx_articles_canonical_url ON articles (canonical_url)
+    WHERE canonical_url IS NOT NULL;
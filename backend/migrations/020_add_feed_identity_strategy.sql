@@ -0,0 +1,12 @@
+-- Migration: Add per-feed article identity strategy
+--
+-- Decides what is stored in articles.guid (the per-feed unique key):
+--   auto - the publisher's guid, or the link when the entry has none
+--          (switches to 'link' once the feed is seen rotating guids)
+--   guid - always the publisher's guid
+--   link - the entry's canonical link
+--   hash - a hash of link + title + publication time
+
+ALTER TABLE feeds
+    ADD COLUMN identity_strategy VARCHAR(20) NOT NULL DEFAULT 'auto'
+        CHECK (identity_strategy IN ('auto', 'guid', 'link', 'hash'));
@@ -0,0 +1,14 @@
+-- Migration: Keep raw article HTML next to the sanitized copy
+--
+-- summary and content now hold HTML sanitized at ingestion (allowlisted tags,
+-- no scripts/handlers/tracking pixels, absolute URLs). raw_summary and
+-- raw_content keep the publisher's original so the sanitizer can be re-run
+-- (`herald-backend resanitize`) whenever its rules change. Existing rows were
+-- stored unsanitized, so they start with raw = current value; run the
+-- resanitize command once after migrating.
+
+ALTER TABLE articles
+    ADD COLUMN raw_summary TEXT NULL,
+    ADD COLUMN raw_content TEXT NULL;
+
+UPDATE articles SET raw_summary = summary, raw_content = content;
@@ -0,0 +1,22 @@
+-- Migration: Full-text extraction for truncated feeds
+--
+-- Feeds with fetch_full_content have each new article's page downloaded and
+-- its main content extracted. The extracted (sanitized) HTML and plain text
+-- are stored next to the feed-provided content. extracted_at records the
+-- attempt, so failed extractions are not retried on every request.
+
+ALTER TABLE feeds
+    ADD COLUMN fetch_full_content BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE articles
+    ADD COLUMN extracted_content TEXT NULL,
+    ADD COLUMN extracted_text TEXT NULL,
+    ADD COLUMN extracted_at TIMESTAMPTZ NULL;
+
+-- Curated feeds known to ship summaries only
+UPDATE feeds SET fetch_full_content = TRUE
+WHERE url IN (
+    'https://www.theverge.com/rss/index.xml',
+    'https://techcrunch.com/feed/',
+    'https://feeds.reuters.com/reuters/topNews'
+);
@@ -0,0 +1,23 @@
+-- Migration: Article media (enclosures, thumbnails, images)
+--
+-- Attachments of an article: RSS enclosures and Atom enclosure links,
+-- media:content and media:thumbnail, and the first inline image of the
+-- content as a fallback when the feed carries no image. Replaced whenever
+-- the article's content changes.
+
+CREATE TABLE article_media (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    article_id UUID NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('enclosure', 'thumbnail', 'inline_image')),
+    url TEXT NOT NULL,
+    mime_type VARCHAR(255) NULL,
+    length_bytes BIGINT NULL,
+    duration_seconds INTEGER NULL,
+    width INTEGER NULL,
+    height INTEGER NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (article_id, kind, url)
+);
+
+CREATE INDEX idx_article_media_article ON article_media(article_id, position);
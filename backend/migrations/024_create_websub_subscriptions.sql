@@ -0,0 +1,26 @@
+-- Migration: WebSub (PubSubHubbub) push subscriptions
+--
+-- Feeds that advertise a rel="hub" link are subscribed to their hub, which
+-- then pushes new content to /api/websub/:feed_id. The secret signs pushes
+-- (X-Hub-Signature). A subscription is pending until the hub verifies it,
+-- active until its lease expires (leases are renewed ahead of expiry) and
+-- denied if the hub refused it. Feeds with an active subscription are polled
+-- much less often.
+
+CREATE TABLE websub_subscriptions (
+    feed_id UUID PRIMARY KEY REFERENCES feeds(id) ON DELETE CASCADE,
+    hub_url TEXT NOT NULL,
+    topic_url TEXT NOT NULL,
+    secret VARCHAR(64) NOT NULL,
+    state VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (state IN ('pending', 'active', 'denied')),
+    lease_seconds INTEGER NULL,
+    expires_at TIMESTAMPTZ NULL,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    verified_at TIMESTAMPTZ NULL,
+    last_push_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_websub_subscriptions_expires ON websub_subscriptions(expires_at)
+    WHERE state = 'active';
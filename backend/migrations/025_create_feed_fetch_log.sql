@@ -0,0 +1,21 @@
+-- Migration: Feed fetch history
+--
+-- One row per fetch attempt (success, 304 or failure) so "my feed isn't
+-- updating" can be answered from data. Rows older than
+-- FETCH_LOG_RETENTION_DAYS are pruned by the scheduler.
+
+CREATE TABLE feed_fetch_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    feed_id UUID NOT NULL REFERENCES feeds(id) ON DELETE CASCADE,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    http_status INTEGER NULL,
+    duration_ms INTEGER NOT NULL,
+    bytes BIGINT NULL,
+    new_articles INTEGER NOT NULL DEFAULT 0,
+    updated_articles INTEGER NOT NULL DEFAULT 0,
+    error_kind VARCHAR(50) NULL,
+    error_message TEXT NULL
+);
+
+CREATE INDEX idx_feed_fetch_log_feed ON feed_fetch_log(feed_id, fetched_at DESC);
+CREATE INDEX idx_feed_fetch_log_fetched_at ON feed_fetch_log(fetched_at);
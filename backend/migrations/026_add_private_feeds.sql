@@ -0,0 +1,16 @@
+-- Migration: Private feeds with credentials
+--
+-- Feeds behind HTTP Basic auth, a bearer token or custom headers belong to the
+-- user who added them: they are never shared with (or deduplicated against)
+-- other users' subscriptions to the same URL. `credentials` holds the
+-- AES-256-GCM encrypted request credentials (nonce followed by ciphertext).
+
+ALTER TABLE feeds
+    ADD COLUMN owner_user_id UUID NULL REFERENCES users(id) ON DELETE CASCADE,
+    ADD COLUMN credentials BYTEA NULL;
+
+-- URLs are unique among public feeds, and per owner among private ones
+ALTER TABLE feeds DROP CONSTRAINT feeds_url_key;
+CREATE UNIQUE INDEX idx_feeds_public_url ON feeds (url) WHERE owner_user_id IS NULL;
+CREATE UNIQUE INDEX idx_feeds_private_url ON feeds (owner_user_id, url)
+    WHERE owner_user_id IS NOT NULL;
@@ -0,0 +1,16 @@
+-- Migration: Scraped sources for sites without a feed
+--
+-- A feed's `kind` says how it is fetched:
+--   feed   - an RSS/Atom/JSON feed document
+--   scrape - an HTML page turned into articles by the CSS selectors in
+--            `scrape_rules` (item container, title, link, date, summary)
+--
+-- Scraped sources belong to the user who defined their selectors, like
+-- private feeds (see 026).
+
+ALTER TABLE feeds
+    ADD COLUMN kind VARCHAR(20) NOT NULL DEFAULT 'feed'
+        CHECK (kind IN ('feed', 'scrape')),
+    ADD COLUMN scrape_rules JSONB NULL,
+    ADD CONSTRAINT feeds_scrape_rules_check
+        CHECK (kind <> 'scrape' OR (scrape_rules IS NOT NULL AND owner_user_id IS NOT NULL));
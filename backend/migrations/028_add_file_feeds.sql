@@ -0,0 +1,7 @@
+-- Migration: Feeds read from files on the server
+--
+-- `file` feeds have a file:// URL inside the configured FEED_FILE_ROOT and
+-- are read from disk instead of over HTTP.
+
+ALTER TABLE feeds DROP CONSTRAINT feeds_kind_check;
+ALTER TABLE feeds ADD CONSTRAINT feeds_kind_check CHECK (kind IN ('feed', 'scrape', 'file'));
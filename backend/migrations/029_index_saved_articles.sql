@@ -0,0 +1,6 @@
+-- Migration: Look up saved articles by article
+--
+-- Article retention pruning skips articles any user has saved; the primary
+-- key (user_id, article_id) can't answer "is this article saved by anyone".
+
+CREATE INDEX idx_user_articles_saved_article ON user_articles (article_id) WHERE is_saved = TRUE;
@@ -0,0 +1,14 @@
+-- Migration: Per-user quotas and usage
+--
+-- `max_feeds` overrides MAX_FEEDS_PER_USER for one user (NULL uses the
+-- default). `requested_by` records whose request produced an AI analysis, so
+-- analyses can be counted against the user who asked for them.
+
+ALTER TABLE users
+    ADD COLUMN max_feeds INTEGER NULL CHECK (max_feeds >= 0);
+
+ALTER TABLE article_analysis
+    ADD COLUMN requested_by UUID NULL REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_article_analysis_requested_by ON article_analysis (requested_by)
+    WHERE requested_by IS NOT NULL;
@@ -0,0 +1,14 @@
+-- Migration: Article language
+--
+-- `language` is the article's ISO 639-1 code ("en", "de"), detected from its
+-- text at ingestion or taken from the feed's declared language; NULL when
+-- neither says. `preferred_languages` limits a user's timeline to articles in
+-- those languages (empty shows every language).
+
+ALTER TABLE articles
+    ADD COLUMN language VARCHAR(8) NULL;
+
+CREATE INDEX idx_articles_language ON articles (language);
+
+ALTER TABLE users
+    ADD COLUMN preferred_languages TEXT[] NOT NULL DEFAULT '{}';
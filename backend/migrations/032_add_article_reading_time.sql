@@ -0,0 +1,11 @@
+-- Migration: Article word count and reading time
+--
+-- Counted over the article's plain text: the extracted full text once there
+-- is one, otherwise the feed's content (or summary). `reading_time_minutes`
+-- assumes 230 words a minute. NULL for articles not counted yet.
+
+ALTER TABLE articles
+    ADD COLUMN word_count INTEGER NULL,
+    ADD COLUMN reading_time_minutes INTEGER NULL;
+
+CREATE INDEX idx_articles_reading_time ON articles (reading_time_minutes);
@@ -0,0 +1,6 @@
+-- Migration: Look up opposing-article links by the opposing article
+--
+-- Article retention pruning keeps articles on either side of an opposing
+-- link; UNIQUE(source_article_id, opposing_article_id) only covers the source.
+
+CREATE INDEX idx_opposing_articles_opposing ON opposing_articles (opposing_article_id);
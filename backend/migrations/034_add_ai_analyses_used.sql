@@ -0,0 +1,28 @@
+-- Migration: Durable AI analysis usage
+--
+-- Counting article_analysis rows under-reports usage once analyses are
+-- deleted (with their article, by retention pruning). `ai_analyses_used` only
+-- ever goes up: every analysis written for a user's request adds one,
+-- whatever later happens to the row.
+
+ALTER TABLE users
+    ADD COLUMN ai_analyses_used BIGINT NOT NULL DEFAULT 0;
+
+-- Analyses recorded before this migration
+UPDATE users u
+SET ai_analyses_used = (SELECT COUNT(*) FROM article_analysis aa WHERE aa.requested_by = u.id);
+
+CREATE FUNCTION count_ai_analysis_use() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE users SET ai_analyses_used = ai_analyses_used + 1 WHERE id = NEW.requested_by;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- A re-analysis overwrites the article's row (with a new analyzed_at), so
+-- those updates count too
+CREATE TRIGGER article_analysis_count_use
+    AFTER INSERT OR UPDATE OF analyzed_at, requested_by ON article_analysis
+    FOR EACH ROW
+    WHEN (NEW.requested_by IS NOT NULL)
+    EXECUTE FUNCTION count_ai_analysis_use();
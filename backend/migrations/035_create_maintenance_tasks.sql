@@ -0,0 +1,15 @@
+-- Migration: One-off maintenance tasks
+--
+-- Data fixes that need application code (so can't be written in SQL) are
+-- listed here and run once at startup, before any feed is fetched.
+--
+-- rekey_generated_guids: articles stored without a publisher guid used to be
+-- keyed by the id feed-rs made up from their link and title; they are now
+-- keyed by their identity strategy (the link, for `auto` feeds).
+
+CREATE TABLE maintenance_tasks (
+    name VARCHAR(100) PRIMARY KEY,
+    completed_at TIMESTAMPTZ NULL
+);
+
+INSERT INTO maintenance_tasks (name) VALUES ('rekey_generated_guids');
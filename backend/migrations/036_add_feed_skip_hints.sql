@@ -0,0 +1,9 @@
+-- Migration: Store feeds' skipHours / skipDays
+--
+-- The hints are read from the feed document, which a 304 Not Modified
+-- response doesn't include; storing them lets those fetches be scheduled
+-- around the skipped hours and days too. Days are numbered from Monday (0).
+
+ALTER TABLE feeds
+    ADD COLUMN skip_hours SMALLINT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN skip_days SMALLINT[] NOT NULL DEFAULT '{}';
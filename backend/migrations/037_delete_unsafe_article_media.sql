@@ -0,0 +1,7 @@
+-- Migration: Drop article media with unsafe URLs
+--
+-- Media URLs used to be stored as feeds gave them, so javascript:, data: and
+-- relative URLs could be served back as thumbnails. New media only keeps
+-- absolute http(s) URLs; remove what was stored before.
+
+DELETE FROM article_media WHERE url !~* '^https?://';
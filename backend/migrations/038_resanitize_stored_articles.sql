@@ -0,0 +1,7 @@
+-- Migration: Sanitize articles stored before ingestion-time sanitization
+--
+-- Rows that predate 021 were copied into raw_summary / raw_content unchanged,
+-- so their summary and content are still the publisher's HTML. The sanitizer
+-- lives in application code, so it is run over them once at startup.
+
+INSERT INTO maintenance_tasks (name) VALUES ('resanitize_stored_html');
@@ -34,11 +34,13 @@ pub struct Claims {
 #[derive(Debug, Clone)]
 pub struct AuthUser {
     pub user_id: Uuid,
+    #[allow(dead_code)]
     pub email: String,
 }
 
 /// Error type for authentication failures.
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum AuthError {
     InvalidToken,
     MissingToken,
@@ -9,5 +9,5 @@ mod jwt;
 mod password;
 
 // Re-export key types and functions
-pub use jwt::{AuthUser, Claims, create_token, validate_token, AuthError};
+pub use jwt::{AuthUser, create_token};
 pub use password::{hash_password, verify_password};
@@ -0,0 +1,159 @@
+//! Maintenance commands.
+//!
+//! Running the binary with arguments executes one command against the
+//! database and exits instead of starting the server:
+//!
+//! ```text
+//! herald-backend merge-duplicates [FEED_ID]
+//! herald-backend set-identity FEED_ID auto|guid|link|hash
+//! herald-backend resanitize
+//! herald-backend set-full-content FEED_ID on|off
+//! herald-backend prune-articles [--dry-run]
+//! herald-backend set-feed-limit EMAIL N|default
+//! herald-backend detect-languages
+//! herald-backend count-words
+//! ```
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::{feeds, users};
+use crate::models::feed::Feed;
+use crate::services::identity::{self, IdentityStrategy};
+use crate::services::language;
+use crate::services::reading_time;
+use crate::services::retention;
+use crate::services::sanitize;
+
+const USAGE: &str = "\
+Usage:
+  herald-backend                                   Start the server
+  herald-backend merge-duplicates [FEED_ID]        Merge duplicate articles (all feeds by default)
+  herald-backend set-identity FEED_ID STRATEGY     Set a feed's identity strategy (auto, guid, link, hash)
+  herald-backend resanitize                        Re-run HTML sanitization over stored articles
+  herald-backend set-full-content FEED_ID on|off   Toggle full-text extraction for a feed
+  herald-backend prune-articles [--dry-run]        Delete unsaved articles past ARTICLE_RETENTION_DAYS
+  herald-backend set-feed-limit EMAIL N|default    Set how many feeds a user may subscribe to
+  herald-backend detect-languages                  Detect the language of articles that have none
+  herald-backend count-words                       Count words and reading time of articles that have none";
+
+/// Run the command in `args` (the process arguments without the binary name).
+pub async fn run(pool: &PgPool, config: &Config, args: &[String]) -> Result<(), String> {
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["merge-duplicates"] => {
+            let all = feeds::list_all_feeds(pool).await.map_err(|e| e.to_string())?;
+            merge_duplicates(pool, &all).await
+        }
+        ["merge-duplicates", feed_id] => {
+            let feed = find_feed(pool, feed_id).await?;
+            merge_duplicates(pool, &[feed]).await
+        }
+        ["set-identity", feed_id, strategy] => {
+            let feed = find_feed(pool, feed_id).await?;
+            let strategy: IdentityStrategy = strategy.parse()?;
+            let removed = identity::set_strategy(pool, feed.id, strategy)
+                .await
+                .map_err(|e| e.to_string())?;
+            println!(
+                "{}: identity strategy set to {}, {} duplicate article(s) merged",
+                feed.title,
+                strategy.as_str(),
+                removed
+            );
+            Ok(())
+        }
+        ["resanitize"] => {
+            let updated = sanitize::resanitize_all(pool).await.map_err(|e| e.to_string())?;
+            println!("Re-sanitized {} article(s)", updated);
+            Ok(())
+        }
+        ["set-full-content", feed_id, setting @ ("on" | "off")] => {
+            let feed = find_feed(pool, feed_id).await?;
+            feeds::update_fetch_full_content(pool, feed.id, *setting == "on")
+                .await
+                .map_err(|e| e.to_string())?;
+            println!("{}: full-content extraction {}", feed.title, setting);
+            Ok(())
+        }
+        ["prune-articles", flags @ ..] if matches!(flags, [] | ["--dry-run"]) => {
+            if config.article_retention_days <= 0 {
+                return Err("Article retention is off (ARTICLE_RETENTION_DAYS is 0)".to_string());
+            }
+            let report = retention::prune_articles(
+                pool,
+                config.article_retention_days,
+                config.article_prune_batch_size,
+                !flags.is_empty() || config.article_prune_dry_run,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            println!("{}", report);
+            Ok(())
+        }
+        ["set-feed-limit", email, limit] => {
+            let max_feeds = match *limit {
+                "default" => None,
+                limit => Some(
+                    limit
+                        .parse::<u16>()
+                        .map_err(|_| format!("Invalid feed limit: {}", limit))?
+                        .into(),
+                ),
+            };
+            if !users::set_max_feeds(pool, email, max_feeds)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                return Err(format!("User {} not found", email));
+            }
+            match max_feeds {
+                Some(max_feeds) => println!("{}: feed limit set to {}", email, max_feeds),
+                None => println!(
+                    "{}: feed limit set to the default ({})",
+                    email, config.max_feeds_per_user
+                ),
+            }
+            Ok(())
+        }
+        ["detect-languages"] => {
+            let updated = language::detect_missing(pool).await.map_err(|e| e.to_string())?;
+            println!("Detected the language of {} article(s)", updated);
+            Ok(())
+        }
+        ["count-words"] => {
+            let updated = reading_time::count_missing(pool).await.map_err(|e| e.to_string())?;
+            println!("Counted the words of {} article(s)", updated);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+async fn find_feed(pool: &PgPool, feed_id: &str) -> Result<Feed, String> {
+    let id: Uuid = feed_id
+        .parse()
+        .map_err(|_| format!("Invalid feed id: {}", feed_id))?;
+
+    feeds::get_feed_by_id(pool, id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Feed {} not found", id))
+}
+
+async fn merge_duplicates(pool: &PgPool, feeds: &[Feed]) -> Result<(), String> {
+    let mut total = 0;
+
+    for feed in feeds {
+        let removed = identity::merge_duplicates(pool, feed)
+            .await
+            .map_err(|e| format!("{}: {}", feed.title, e))?;
+        if removed > 0 {
+            println!("{}: merged {} duplicate article(s)", feed.title, removed);
+        }
+        total += removed;
+    }
+
+    println!("Merged {} duplicate article(s) across {} feed(s)", total, feeds.len());
+    Ok(())
+}
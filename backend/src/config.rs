@@ -30,7 +30,42 @@ pub struct Config {
 
     //Feed Settings
     pub max_feeds_per_user: i32,
+    /// Unsaved articles older than this are deleted; 0 keeps articles forever.
     pub article_retention_days: i32,
+    /// Most articles deleted per statement while pruning.
+    pub article_prune_batch_size: i64,
+    /// Only report what pruning would delete.
+    pub article_prune_dry_run: bool,
+
+    //Fetch Scheduling
+    pub fetch_concurrency: usize,
+    pub fetch_per_host_concurrency: usize,
+    pub fetch_per_host_delay_ms: u64,
+    pub feed_min_interval_minutes: i64,
+    pub feed_max_interval_minutes: i64,
+    pub feed_failure_threshold: i32,
+    pub fetch_log_retention_days: i32,
+    /// How long subscribe and refresh requests wait for their fetches.
+    pub feed_refresh_timeout_seconds: u64,
+    /// Minimum time between a user's manual refreshes (of one feed, or all).
+    pub feed_refresh_cooldown_seconds: u64,
+
+    //Fetch Safety
+    /// Hosts, IPs and CIDR ranges that may be fetched even though they are
+    /// private (comma-separated); everything non-public is blocked otherwise.
+    pub fetch_allowlist: String,
+    pub fetch_max_response_bytes: usize,
+    /// Base64-encoded 32-byte key private feed credentials are encrypted with;
+    /// feeds with credentials can't be added without it.
+    pub feed_credentials_key: Option<String>,
+    /// Directory `file://` feeds may be read from; `file://` feeds can't be
+    /// added without it.
+    pub feed_file_root: Option<String>,
+
+    //WebSub
+    /// Externally reachable base URL (e.g. `https://herald.example.com`) that
+    /// WebSub hubs push to; push subscriptions are disabled when unset.
+    pub public_base_url: Option<String>,
 }
 
 impl Config { 
@@ -61,6 +96,17 @@ impl Config {
             .unwrap_or_else(|_| "7".to_string())
             .parse()
             .expect("ARTICLE_RETENTION_DAYS must be a valid number");
+
+        let article_prune_batch_size: i64 = env::var("ARTICLE_PRUNE_BATCH_SIZE")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse()
+            .expect("ARTICLE_PRUNE_BATCH_SIZE must be a valid number");
+
+        let article_prune_dry_run: bool = env::var("ARTICLE_PRUNE_DRY_RUN")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .expect("ARTICLE_PRUNE_DRY_RUN must be true or false");
+
         let ollama_url = env::var("OLLAMA_URL")
             .unwrap_or_else(|_| "http://localhost:11434".to_string());
             
@@ -90,6 +136,70 @@ impl Config {
             .parse()
             .expect("AI_ANALYSIS_ENABLED must be true or false");
 
+        let fetch_concurrency: usize = env::var("FETCH_CONCURRENCY")
+            .unwrap_or_else(|_| "16".to_string())
+            .parse()
+            .expect("FETCH_CONCURRENCY must be a valid number");
+
+        let fetch_per_host_concurrency: usize = env::var("FETCH_PER_HOST_CONCURRENCY")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse()
+            .expect("FETCH_PER_HOST_CONCURRENCY must be a valid number");
+
+        let fetch_per_host_delay_ms: u64 = env::var("FETCH_PER_HOST_DELAY_MS")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse()
+            .expect("FETCH_PER_HOST_DELAY_MS must be a valid number");
+
+        let feed_min_interval_minutes: i64 = env::var("FEED_MIN_INTERVAL_MINUTES")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .expect("FEED_MIN_INTERVAL_MINUTES must be a valid number");
+
+        let feed_max_interval_minutes: i64 = env::var("FEED_MAX_INTERVAL_MINUTES")
+            .unwrap_or_else(|_| "1440".to_string())
+            .parse()
+            .expect("FEED_MAX_INTERVAL_MINUTES must be a valid number");
+
+        let feed_failure_threshold: i32 = env::var("FEED_FAILURE_THRESHOLD")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .expect("FEED_FAILURE_THRESHOLD must be a valid number");
+
+        let fetch_log_retention_days: i32 = env::var("FETCH_LOG_RETENTION_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .expect("FETCH_LOG_RETENTION_DAYS must be a valid number");
+
+        let feed_refresh_timeout_seconds: u64 = env::var("FEED_REFRESH_TIMEOUT_SECONDS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .expect("FEED_REFRESH_TIMEOUT_SECONDS must be a valid number");
+
+        let feed_refresh_cooldown_seconds: u64 = env::var("FEED_REFRESH_COOLDOWN_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .expect("FEED_REFRESH_COOLDOWN_SECONDS must be a valid number");
+
+        let fetch_allowlist = env::var("FETCH_ALLOWLIST").unwrap_or_default();
+
+        let fetch_max_response_bytes: usize = env::var("FETCH_MAX_RESPONSE_BYTES")
+            .unwrap_or_else(|_| "10485760".to_string())
+            .parse()
+            .expect("FETCH_MAX_RESPONSE_BYTES must be a valid number");
+
+        let feed_credentials_key: Option<String> = env::var("FEED_CREDENTIALS_KEY")
+            .ok()
+            .filter(|k| !k.trim().is_empty());
+
+        let feed_file_root: Option<String> = env::var("FEED_FILE_ROOT")
+            .ok()
+            .filter(|root| !root.trim().is_empty());
+
+        let public_base_url: Option<String> = env::var("PUBLIC_BASE_URL")
+            .ok()
+            .filter(|u| !u.trim().is_empty());
+
         Self { 
             database_url,
             host,
@@ -98,6 +208,8 @@ impl Config {
             jwt_expiration_hours,
             max_feeds_per_user,
             article_retention_days,
+            article_prune_batch_size,
+            article_prune_dry_run,
             ollama_url,
             ollama_model,
             anthropic_api_key,
@@ -107,6 +219,20 @@ impl Config {
             ai_default_provider,
             ai_analysis_batch_size,
             ai_analysis_enabled,
+            fetch_concurrency,
+            fetch_per_host_concurrency,
+            fetch_per_host_delay_ms,
+            feed_min_interval_minutes,
+            feed_max_interval_minutes,
+            feed_failure_threshold,
+            fetch_log_retention_days,
+            feed_refresh_timeout_seconds,
+            feed_refresh_cooldown_seconds,
+            fetch_allowlist,
+            fetch_max_response_bytes,
+            feed_credentials_key,
+            feed_file_root,
+            public_base_url,
         }
     }
 }
\ No newline at end of file
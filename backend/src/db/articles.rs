@@ -2,9 +2,12 @@ use sqlx::PgPool;
 use sqlx::types::chrono::{DateTime, Utc};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
 use sqlx::FromRow;
+use sqlx::types::Json;
 
-use crate::models::article::Article;
+use crate::models::article::{Article, ArticleMedia};
+use crate::services::reading_time::ReadingTime;
 
 /// Article with user-specific read/saved status
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -18,104 +21,66 @@ pub struct ArticleWithStatus {
     pub content: Option<String>,
     pub published_at: Option<DateTime<Utc>>,
     pub guid: Option<String>,
+    /// Normalized URL shared by copies of the same story across feeds.
+    pub canonical_url: Option<String>,
+    /// ISO 639-1 language code, detected or declared by the feed.
+    pub language: Option<String>,
+    /// Words in the article's text (the extracted text once there is one).
+    pub word_count: Option<i32>,
+    /// Estimated minutes to read `word_count` words.
+    pub reading_time_minutes: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub is_read: bool,
     pub is_saved: bool,
     pub feed_title: Option<String>,
+    /// How many of the user's feeds carry this story (1 when it is unique, or
+    /// when copies aren't collapsed).
+    pub duplicate_count: i64,
+    /// Image for the article card: a thumbnail, an image enclosure or the
+    /// first image of the content.
+    pub thumbnail_url: Option<String>,
+    /// Enclosures, thumbnails and the inline image fallback, in feed order.
+    pub media: Json<Vec<ArticleMedia>>,
+}
+
+/// Filters for [`list_articles_for_user`].
+#[derive(Debug, Default, Clone)]
+pub struct ArticleFilters<'a> {
+    /// Only articles from feeds in this topic.
+    pub topic_slug: Option<&'a str>,
+    /// Only articles the user saved.
+    pub saved_only: bool,
+    /// Show one article per canonical URL (the first copy stored) instead of
+    /// every feed's copy of the same story.
+    pub collapse_duplicates: bool,
+    /// Only articles in these languages (ISO 639-1 codes).
+    pub languages: Option<&'a [String]>,
+    /// With `languages`, also keep articles whose language is unknown.
+    pub include_unknown_language: bool,
+    /// Only articles taking at least this many minutes to read.
+    pub min_minutes: Option<i32>,
+    /// Only articles taking at most this many minutes to read.
+    pub max_minutes: Option<i32>,
 }
 
 /// List articles from user's subscribed feeds with read/saved status
-/// Optionally filter by topic slug and/or saved-only articles
+/// Optionally filter by topic slug and/or saved-only articles, and collapse
+/// copies of the same story from different feeds
 pub async fn list_articles_for_user(
     pool: &PgPool,
     user_id: Uuid,
-    topic_slug: Option<&str>,
-    saved_only: bool,
+    filters: &ArticleFilters<'_>,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<ArticleWithStatus>, sqlx::Error> {
-    // Build the query based on filters
     // We need to join through: articles -> feeds -> topics (optional) and user_articles
-    // User must be subscribed to the feed via user_feeds
-
-    if let Some(slug) = topic_slug {
-        if saved_only {
-            // Filter by topic AND saved only
-            sqlx::query_as!(
-                ArticleWithStatus,
-                r#"
-                SELECT
-                    a.id,
-                    a.feed_id,
-                    a.title,
-                    a.url,
-                    a.author,
-                    a.summary,
-                    a.content,
-                    a.published_at,
-                    a.guid,
-                    a.created_at,
-                    COALESCE(ua.is_read, FALSE) as "is_read!",
-                    COALESCE(ua.is_saved, FALSE) as "is_saved!",
-                    f.title as feed_title
-                FROM articles a
-                INNER JOIN feeds f ON a.feed_id = f.id
-                INNER JOIN topics t ON f.topic_id = t.id
-                INNER JOIN user_feeds uf ON f.id = uf.feed_id AND uf.user_id = $1
-                LEFT JOIN user_articles ua ON a.id = ua.article_id AND ua.user_id = $1
-                WHERE t.slug = $2
-                  AND ua.is_saved = TRUE
-                ORDER BY a.published_at DESC NULLS LAST, a.created_at DESC
-                LIMIT $3 OFFSET $4
-                "#,
-                user_id,
-                slug,
-                limit,
-                offset
-            )
-            .fetch_all(pool)
-            .await
-        } else {
-            // Filter by topic only
-            sqlx::query_as!(
-                ArticleWithStatus,
-                r#"
-                SELECT
-                    a.id,
-                    a.feed_id,
-                    a.title,
-                    a.url,
-                    a.author,
-                    a.summary,
-                    a.content,
-                    a.published_at,
-                    a.guid,
-                    a.created_at,
-                    COALESCE(ua.is_read, FALSE) as "is_read!",
-                    COALESCE(ua.is_saved, FALSE) as "is_saved!",
-                    f.title as feed_title
-                FROM articles a
-                INNER JOIN feeds f ON a.feed_id = f.id
-                INNER JOIN topics t ON f.topic_id = t.id
-                INNER JOIN user_feeds uf ON f.id = uf.feed_id AND uf.user_id = $1
-                LEFT JOIN user_articles ua ON a.id = ua.article_id AND ua.user_id = $1
-                WHERE t.slug = $2
-                ORDER BY a.published_at DESC NULLS LAST, a.created_at DESC
-                LIMIT $3 OFFSET $4
-                "#,
-                user_id,
-                slug,
-                limit,
-                offset
-            )
-            .fetch_all(pool)
-            .await
-        }
-    } else if saved_only {
-        // Saved only, no topic filter
-        sqlx::query_as!(
-            ArticleWithStatus,
-            r#"
+    // User must be subscribed to the feed via user_feeds. When collapsing,
+    // copies are ranked per canonical URL (articles without one form their own
+    // group); the other branch is skipped by its constant WHERE
+    sqlx::query_as!(
+        ArticleWithStatus,
+        r#"
+        WITH visible AS (
             SELECT
                 a.id,
                 a.feed_id,
@@ -126,69 +91,126 @@ pub async fn list_articles_for_user(
                 a.content,
                 a.published_at,
                 a.guid,
+                a.canonical_url,
+                a.language,
+                a.word_count,
+                a.reading_time_minutes,
                 a.created_at,
-                COALESCE(ua.is_read, FALSE) as "is_read!",
-                COALESCE(ua.is_saved, FALSE) as "is_saved!",
+                COALESCE(ua.is_read, FALSE) as is_read,
+                COALESCE(ua.is_saved, FALSE) as is_saved,
                 f.title as feed_title
             FROM articles a
             INNER JOIN feeds f ON a.feed_id = f.id
             INNER JOIN user_feeds uf ON f.id = uf.feed_id AND uf.user_id = $1
+            LEFT JOIN topics t ON f.topic_id = t.id
             LEFT JOIN user_articles ua ON a.id = ua.article_id AND ua.user_id = $1
-            WHERE ua.is_saved = TRUE
-            ORDER BY a.published_at DESC NULLS LAST, a.created_at DESC
-            LIMIT $2 OFFSET $3
-            "#,
-            user_id,
-            limit,
-            offset
-        )
-        .fetch_all(pool)
-        .await
-    } else {
-        // No filters - all articles from user's feeds
-        sqlx::query_as!(
-            ArticleWithStatus,
-            r#"
+            WHERE ($2::text IS NULL OR t.slug = $2)
+              AND (NOT $3 OR ua.is_saved = TRUE)
+              AND ($7::text[] IS NULL OR a.language = ANY($7) OR ($8 AND a.language IS NULL))
+              AND ($9::int IS NULL OR a.reading_time_minutes >= $9)
+              AND ($10::int IS NULL OR a.reading_time_minutes <= $10)
+        ),
+        ranked AS (
+            SELECT v.*, 1::bigint as copy_rank, 1::bigint as copy_count
+            FROM visible v
+            WHERE NOT $4
+            UNION ALL
             SELECT
-                a.id,
-                a.feed_id,
-                a.title,
-                a.url,
-                a.author,
-                a.summary,
-                a.content,
-                a.published_at,
-                a.guid,
-                a.created_at,
-                COALESCE(ua.is_read, FALSE) as "is_read!",
-                COALESCE(ua.is_saved, FALSE) as "is_saved!",
-                f.title as feed_title
-            FROM articles a
-            INNER JOIN feeds f ON a.feed_id = f.id
-            INNER JOIN user_feeds uf ON f.id = uf.feed_id AND uf.user_id = $1
-            LEFT JOIN user_articles ua ON a.id = ua.article_id AND ua.user_id = $1
-            ORDER BY a.published_at DESC NULLS LAST, a.created_at DESC
-            LIMIT $2 OFFSET $3
-            "#,
-            user_id,
-            limit,
-            offset
+                v.*,
+                ROW_NUMBER() OVER (
+                    PARTITION BY COALESCE(v.canonical_url, v.id::text)
+                    ORDER BY v.created_at ASC, v.id ASC
+                ),
+                COUNT(*) OVER (PARTITION BY COALESCE(v.canonical_url, v.id::text))
+            FROM visible v
+            WHERE $4
+        ),
+        page AS (
+            SELECT *
+            FROM ranked
+            WHERE copy_rank = 1
+            ORDER BY published_at DESC NULLS LAST, created_at DESC
+            LIMIT $5 OFFSET $6
         )
-        .fetch_all(pool)
-        .await
-    }
+        SELECT
+            p.id as "id!",
+            p.feed_id as "feed_id!",
+            p.title as "title!",
+            p.url as "url!",
+            p.author,
+            p.summary,
+            p.content,
+            p.published_at,
+            p.guid,
+            p.canonical_url,
+            p.language,
+            p.word_count,
+            p.reading_time_minutes,
+            p.created_at as "created_at!",
+            p.is_read as "is_read!",
+            p.is_saved as "is_saved!",
+            p.feed_title,
+            p.copy_count as "duplicate_count!",
+            thumb.url as "thumbnail_url?",
+            COALESCE(m.media, '[]') as "media!: Json<Vec<ArticleMedia>>"
+        FROM page p
+        -- Thumbnails first, then image enclosures, then the inline image
+        LEFT JOIN LATERAL (
+            SELECT am.url
+            FROM article_media am
+            WHERE am.article_id = p.id
+              AND (am.kind <> 'enclosure' OR am.mime_type LIKE 'image/%')
+            ORDER BY CASE am.kind WHEN 'thumbnail' THEN 0 WHEN 'enclosure' THEN 1 ELSE 2 END,
+                     am.position
+            LIMIT 1
+        ) thumb ON TRUE
+        LEFT JOIN LATERAL (
+            SELECT json_agg(json_build_object(
+                'kind', am.kind,
+                'url', am.url,
+                'mime_type', am.mime_type,
+                'length_bytes', am.length_bytes,
+                'duration_seconds', am.duration_seconds,
+                'width', am.width,
+                'height', am.height
+            ) ORDER BY am.position) as media
+            FROM article_media am
+            WHERE am.article_id = p.id
+        ) m ON TRUE
+        ORDER BY p.published_at DESC NULLS LAST, p.created_at DESC
+        "#,
+        user_id,
+        filters.topic_slug,
+        filters.saved_only,
+        filters.collapse_duplicates,
+        limit,
+        offset,
+        filters.languages,
+        filters.include_unknown_language,
+        filters.min_minutes,
+        filters.max_minutes
+    )
+    .fetch_all(pool)
+    .await
 }
 
-/// Get a single article by ID
-pub async fn get_article(pool: &PgPool, id: Uuid) -> Result<Option<Article>, sqlx::Error> {
+/// Get a single article by ID, if `user_id` is subscribed to its feed
+pub async fn get_article(
+    pool: &PgPool,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<Option<Article>, sqlx::Error> {
     sqlx::query_as!(
         Article,
         r#"
-        SELECT id, feed_id, title, url, author, summary, content, published_at, guid, created_at
+        SELECT id, feed_id, title, url, author, summary, content, published_at, guid, language,
+               word_count, reading_time_minutes, extracted_content, extracted_text, extracted_at, created_at
         FROM articles
         WHERE id = $1
+          AND feed_id IN (SELECT feed_id FROM user_feeds WHERE user_id = $2)
         "#,
-        id
+        id,
+        user_id
     )
     .fetch_optional(pool)
     .await
@@ -196,6 +218,8 @@ pub async fn get_article(pool: &PgPool, id: Uuid) -> Result<Option<Article>, sql
 
 /// Mark an article as read or unread for a user
 /// Uses upsert to create user_articles record if it doesn't exist
+/// Copies of the same story (same canonical URL) in the user's other feeds
+/// get the same read state
 pub async fn mark_read(
     pool: &PgPool,
     user_id: Uuid,
@@ -207,7 +231,13 @@ pub async fn mark_read(
     sqlx::query!(
         r#"
         INSERT INTO user_articles (user_id, article_id, is_read, read_at)
-        VALUES ($1, $2, $3, $4)
+        SELECT $1, a.id, $3, $4
+        FROM articles a
+        WHERE a.id = $2
+           OR (
+               a.canonical_url = (SELECT canonical_url FROM articles WHERE id = $2)
+               AND a.feed_id IN (SELECT feed_id FROM user_feeds WHERE user_id = $1)
+           )
         ON CONFLICT (user_id, article_id)
         DO UPDATE SET is_read = $3, read_at = $4
         "#,
@@ -254,24 +284,93 @@ pub async fn toggle_save(
     Ok(result.is_saved)
 }
 
-/// Create a new article (used by RSS fetcher)
-/// Returns the created article, or the existing article if guid conflicts
+/// An entry to be stored by [`create_article`].
+#[derive(Debug, Clone)]
+pub struct NewArticle<'a> {
+    pub feed_id: Uuid,
+    pub title: &'a str,
+    pub url: &'a str,
+    pub author: Option<&'a str>,
+    /// Sanitized summary HTML.
+    pub summary: Option<&'a str>,
+    /// Sanitized content HTML.
+    pub content: Option<&'a str>,
+    /// Summary HTML as published.
+    pub raw_summary: Option<&'a str>,
+    /// Content HTML as published.
+    pub raw_content: Option<&'a str>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub guid: Option<&'a str>,
+    pub canonical_url: Option<&'a str>,
+    /// ISO 639-1 language code. Derived from the other fields, so not part of
+    /// the content hash (nor is the reading time).
+    pub language: Option<&'a str>,
+    /// Length of the content (or summary).
+    pub reading_time: ReadingTime,
+}
+
+impl NewArticle<'_> {
+    /// SHA-256 (hex) over every stored field, used to detect changed entries.
+    pub fn content_hash(&self) -> String {
+        let published_at = self.published_at.map(|p| p.to_rfc3339());
+        let fields = [
+            Some(self.title),
+            Some(self.url),
+            self.author,
+            self.summary,
+            self.content,
+            self.raw_summary,
+            self.raw_content,
+            published_at.as_deref(),
+            self.canonical_url,
+        ];
+
+        let mut hasher = Sha256::new();
+        for field in fields {
+            // Tag each field so None, "" and shifted boundaries hash differently
+            match field {
+                Some(value) => {
+                    hasher.update([1u8]);
+                    hasher.update((value.len() as u64).to_le_bytes());
+                    hasher.update(value.as_bytes());
+                }
+                None => hasher.update([0u8]),
+            }
+        }
+
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// What [`create_article`] did with an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertStatus {
+    /// The entry was new; carries the new article's ID.
+    Inserted(Uuid),
+    /// The entry existed and its content changed; carries the article's ID.
+    Updated(Uuid),
+    /// The entry existed with identical content; nothing was written.
+    Unchanged,
+}
+
+/// Create or update an article (used by RSS fetcher)
+/// An entry already stored under the same guid is only rewritten when its
+/// content hash changed. Returns whether it was inserted, updated or unchanged.
 pub async fn create_article(
     pool: &PgPool,
-    feed_id: Uuid,
-    title: &str,
-    url: &str,
-    author: Option<&str>,
-    summary: Option<&str>,
-    content: Option<&str>,
-    published_at: Option<DateTime<Utc>>,
-    guid: Option<&str>,
-) -> Result<Article, sqlx::Error> {
-    sqlx::query_as!(
-        Article,
+    article: &NewArticle<'_>,
+) -> Result<UpsertStatus, sqlx::Error> {
+    let content_hash = article.content_hash();
+
+    // xmax is 0 for a freshly inserted row and set for one updated on conflict;
+    // no row at all means the conflict's WHERE skipped an unchanged entry
+    let written = sqlx::query!(
         r#"
-        INSERT INTO articles (feed_id, title, url, author, summary, content, published_at, guid)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        INSERT INTO articles
+            (feed_id, title, url, author, summary, content, raw_summary, raw_content,
+             published_at, guid, canonical_url, content_hash, language,
+             word_count, reading_time_minutes)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
         ON CONFLICT (feed_id, guid)
         DO UPDATE SET
             title = EXCLUDED.title,
@@ -279,18 +378,674 @@ pub async fn create_article(
             author = EXCLUDED.author,
             summary = EXCLUDED.summary,
             content = EXCLUDED.content,
-            published_at = EXCLUDED.published_at
-        RETURNING id, feed_id, title, url, author, summary, content, published_at, guid, created_at
+            raw_summary = EXCLUDED.raw_summary,
+            raw_content = EXCLUDED.raw_content,
+            published_at = EXCLUDED.published_at,
+            -- Once the page has been extracted its own canonical link is kept
+            canonical_url = CASE WHEN articles.extracted_at IS NULL
+                THEN EXCLUDED.canonical_url ELSE articles.canonical_url END,
+            content_hash = EXCLUDED.content_hash,
+            language = EXCLUDED.language,
+            -- Counts over the extracted full text are kept
+            word_count = CASE WHEN articles.extracted_text IS NULL
+                THEN EXCLUDED.word_count ELSE articles.word_count END,
+            reading_time_minutes = CASE WHEN articles.extracted_text IS NULL
+                THEN EXCLUDED.reading_time_minutes ELSE articles.reading_time_minutes END
+        WHERE articles.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+        RETURNING id, (xmax = 0) AS "inserted!"
         "#,
-        feed_id,
-        title,
-        url,
-        author,
-        summary,
+        article.feed_id,
+        article.title,
+        article.url,
+        article.author,
+        article.summary,
+        article.content,
+        article.raw_summary,
+        article.raw_content,
+        article.published_at,
+        article.guid,
+        article.canonical_url,
+        content_hash,
+        article.language,
+        article.reading_time.word_count,
+        article.reading_time.minutes
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(match written {
+        Some(row) if row.inserted => UpsertStatus::Inserted(row.id),
+        Some(row) => UpsertStatus::Updated(row.id),
+        None => UpsertStatus::Unchanged,
+    })
+}
+
+/// Store the result of a full-content extraction attempt.
+/// `None` records that extraction found nothing, so it isn't retried.
+/// Extracted text replaces the feed's content in the article's word count, and
+/// the page's canonical URL (if it declares one) replaces the feed's.
+pub async fn store_extracted(
+    pool: &PgPool,
+    id: Uuid,
+    content: Option<&str>,
+    text: Option<&str>,
+    canonical_url: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let reading_time = text.map(ReadingTime::of_text);
+
+    sqlx::query!(
+        r#"
+        UPDATE articles
+        SET extracted_content = $2, extracted_text = $3, extracted_at = NOW(),
+            word_count = COALESCE($4, word_count),
+            reading_time_minutes = COALESCE($5, reading_time_minutes),
+            canonical_url = COALESCE($6, canonical_url)
+        WHERE id = $1
+        "#,
+        id,
         content,
-        published_at,
-        guid
+        text,
+        reading_time.map(|r| r.word_count),
+        reading_time.map(|r| r.minutes),
+        canonical_url
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Replace an article's media with `media` (kept in the given order).
+pub async fn replace_media(
+    pool: &PgPool,
+    article_id: Uuid,
+    media: &[ArticleMedia],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM article_media WHERE article_id = $1", article_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if !media.is_empty() {
+        let kinds: Vec<String> = media.iter().map(|m| m.kind.clone()).collect();
+        let urls: Vec<String> = media.iter().map(|m| m.url.clone()).collect();
+        let mime_types: Vec<Option<String>> = media.iter().map(|m| m.mime_type.clone()).collect();
+        let lengths: Vec<Option<i64>> = media.iter().map(|m| m.length_bytes).collect();
+        let durations: Vec<Option<i32>> = media.iter().map(|m| m.duration_seconds).collect();
+        let widths: Vec<Option<i32>> = media.iter().map(|m| m.width).collect();
+        let heights: Vec<Option<i32>> = media.iter().map(|m| m.height).collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO article_media
+                (article_id, kind, url, mime_type, length_bytes, duration_seconds, width, height, position)
+            SELECT $1, m.kind, m.url, m.mime_type, m.length_bytes, m.duration_seconds, m.width, m.height,
+                   m.position::int
+            FROM UNNEST($2::text[], $3::text[], $4::text[], $5::bigint[], $6::int[], $7::int[], $8::int[])
+                WITH ORDINALITY AS m(kind, url, mime_type, length_bytes, duration_seconds, width, height, position)
+            ON CONFLICT (article_id, kind, url) DO NOTHING
+            "#,
+            article_id,
+            &kinds,
+            &urls,
+            &mime_types as &[Option<String>],
+            &lengths as &[Option<i64>],
+            &durations as &[Option<i32>],
+            &widths as &[Option<i32>],
+            &heights as &[Option<i32>]
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+/// An article's raw HTML with what it is currently sanitized to.
+#[derive(Debug, Clone)]
+pub struct RawHtmlRow {
+    pub id: Uuid,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    pub raw_summary: Option<String>,
+    pub raw_content: Option<String>,
+    /// The feed's site URL and feed URL, for resolving relative links.
+    pub site_url: Option<String>,
+    pub feed_url: String,
+}
+
+/// List articles' raw HTML in id order, `limit` at a time after `after`.
+pub async fn list_raw_html(
+    pool: &PgPool,
+    after: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<RawHtmlRow>, sqlx::Error> {
+    sqlx::query_as!(
+        RawHtmlRow,
+        r#"
+        SELECT a.id, a.summary, a.content, a.raw_summary, a.raw_content,
+               f.site_url, f.url as feed_url
+        FROM articles a
+        INNER JOIN feeds f ON a.feed_id = f.id
+        WHERE $1::uuid IS NULL OR a.id > $1
+        ORDER BY a.id ASC
+        LIMIT $2
+        "#,
+        after,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Store re-sanitized summary and content for an article.
+pub async fn update_sanitized_html(
+    pool: &PgPool,
+    id: Uuid,
+    summary: Option<&str>,
+    content: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE articles
+        SET summary = $2, content = $3
+        WHERE id = $1
+        "#,
+        id,
+        summary,
+        content
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// An article's text, for detecting its language.
+#[derive(Debug, Clone)]
+pub struct LanguageRow {
+    pub id: Uuid,
+    pub title: String,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    /// The language the article's feed declares.
+    pub feed_language: Option<String>,
+}
+
+/// List articles without a language in id order, `limit` at a time after `after`.
+pub async fn list_missing_language(
+    pool: &PgPool,
+    after: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<LanguageRow>, sqlx::Error> {
+    sqlx::query_as!(
+        LanguageRow,
+        r#"
+        SELECT a.id, a.title, a.summary, a.content, f.language as feed_language
+        FROM articles a
+        INNER JOIN feeds f ON a.feed_id = f.id
+        WHERE a.language IS NULL
+          AND ($1::uuid IS NULL OR a.id > $1)
+        ORDER BY a.id ASC
+        LIMIT $2
+        "#,
+        after,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Store an article's language.
+pub async fn update_language(pool: &PgPool, id: Uuid, language: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE articles
+        SET language = $2
+        WHERE id = $1
+        "#,
+        id,
+        language
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// An article's text, for counting its words.
+#[derive(Debug, Clone)]
+pub struct WordCountRow {
+    pub id: Uuid,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    pub extracted_text: Option<String>,
+}
+
+/// List articles without a word count in id order, `limit` at a time after `after`.
+pub async fn list_missing_word_count(
+    pool: &PgPool,
+    after: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<WordCountRow>, sqlx::Error> {
+    sqlx::query_as!(
+        WordCountRow,
+        r#"
+        SELECT id, summary, content, extracted_text
+        FROM articles
+        WHERE word_count IS NULL
+          AND ($1::uuid IS NULL OR id > $1)
+        ORDER BY id ASC
+        LIMIT $2
+        "#,
+        after,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Store an article's word count and reading time.
+pub async fn update_reading_time(
+    pool: &PgPool,
+    id: Uuid,
+    reading_time: ReadingTime,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE articles
+        SET word_count = $2, reading_time_minutes = $3
+        WHERE id = $1
+        "#,
+        id,
+        reading_time.word_count,
+        reading_time.minutes
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Count entries stored under a different guid for the same link, i.e. entries
+/// whose guid changed since we last saw them.
+///
+/// `guids` and `links` are parallel: one guid and link (canonical if known)
+/// per entry.
+pub async fn count_rotated_guids(
+    pool: &PgPool,
+    feed_id: Uuid,
+    guids: &[String],
+    links: &[String],
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(DISTINCT e.link) as "count!"
+        FROM UNNEST($2::text[], $3::text[]) AS e(guid, link)
+        INNER JOIN articles a
+            ON a.feed_id = $1
+           AND COALESCE(a.canonical_url, a.url) = e.link
+           AND a.guid <> e.guid
+        WHERE NOT EXISTS (
+            SELECT 1 FROM articles known WHERE known.feed_id = $1 AND known.guid = e.guid
+        )
+        "#,
+        feed_id,
+        guids,
+        links
     )
     .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// The fields article identity is derived from.
+#[derive(Debug, Clone)]
+pub struct IdentityRow {
+    pub id: Uuid,
+    pub guid: Option<String>,
+    pub url: String,
+    pub canonical_url: Option<String>,
+    pub title: String,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+/// List a feed's articles for re-keying, oldest first.
+pub async fn list_identity_rows(
+    pool: &PgPool,
+    feed_id: Uuid,
+) -> Result<Vec<IdentityRow>, sqlx::Error> {
+    sqlx::query_as!(
+        IdentityRow,
+        r#"
+        SELECT id, guid, url, canonical_url, title, published_at
+        FROM articles
+        WHERE feed_id = $1
+        ORDER BY created_at ASC, id ASC
+        "#,
+        feed_id
+    )
+    .fetch_all(pool)
     .await
 }
+
+/// An article whose guid may have been made up by feed-rs (see
+/// [`crate::services::identity::rekey_generated_guids`]).
+#[derive(Debug, Clone)]
+pub struct GeneratedGuidRow {
+    pub id: Uuid,
+    pub feed_id: Uuid,
+    pub identity_strategy: String,
+    pub guid: String,
+    pub url: String,
+    pub canonical_url: Option<String>,
+    pub title: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// List articles of `auto` and `guid` feeds whose guid looks like a feed-rs
+/// generated id (up to 32 hex digits), oldest first.
+pub async fn list_generated_guid_candidates(pool: &PgPool) -> Result<Vec<GeneratedGuidRow>, sqlx::Error> {
+    sqlx::query_as!(
+        GeneratedGuidRow,
+        r#"
+        SELECT a.id, a.feed_id, f.identity_strategy, a.guid as "guid!", a.url, a.canonical_url,
+               a.title, a.published_at, a.created_at
+        FROM articles a
+        INNER JOIN feeds f ON a.feed_id = f.id
+        WHERE f.identity_strategy IN ('auto', 'guid')
+          AND a.guid ~ '^[0-9a-f]{1,32}$'
+        ORDER BY a.created_at ASC, a.id ASC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Find a feed's article stored under `guid`.
+///
+/// # Returns
+/// The article's ID and when it was stored.
+pub async fn find_by_guid(
+    pool: &PgPool,
+    feed_id: Uuid,
+    guid: &str,
+) -> Result<Option<(Uuid, DateTime<Utc>)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, created_at
+        FROM articles
+        WHERE feed_id = $1 AND guid = $2
+        "#,
+        feed_id,
+        guid
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| (row.id, row.created_at)))
+}
+
+/// Merge duplicate articles into `keep`.
+///
+/// Users' read/saved state on the duplicates is carried over to `keep` (read
+/// or saved on any copy counts), the duplicates are deleted and, if given,
+/// `keep` gets `new_guid`.
+pub async fn merge_articles(
+    pool: &PgPool,
+    keep: Uuid,
+    duplicates: &[Uuid],
+    new_guid: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    if !duplicates.is_empty() {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_articles (user_id, article_id, is_read, is_saved, read_at, saved_at)
+            SELECT user_id, $1, BOOL_OR(is_read), BOOL_OR(is_saved), MIN(read_at), MIN(saved_at)
+            FROM user_articles
+            WHERE article_id = ANY($2)
+            GROUP BY user_id
+            ON CONFLICT (user_id, article_id) DO UPDATE
+            SET is_read = user_articles.is_read OR EXCLUDED.is_read,
+                is_saved = user_articles.is_saved OR EXCLUDED.is_saved,
+                read_at = COALESCE(user_articles.read_at, EXCLUDED.read_at),
+                saved_at = COALESCE(user_articles.saved_at, EXCLUDED.saved_at)
+            "#,
+            keep,
+            duplicates
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM articles WHERE id = ANY($1)", duplicates)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    if let Some(guid) = new_guid {
+        sqlx::query!("UPDATE articles SET guid = $2 WHERE id = $1", keep, guid)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Expired articles, split by whether retention keeps them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExpiredCounts {
+    /// Expired articles nobody saved or analysed (deleted by pruning).
+    pub prunable: i64,
+    /// Expired articles kept because a user saved them, or they have an AI
+    /// analysis or opposing-article links (which deleting would cascade to).
+    pub kept: i64,
+}
+
+/// Count articles stored (and, when dated, published) before `cutoff`.
+pub async fn count_expired(
+    pool: &PgPool,
+    cutoff: DateTime<Utc>,
+) -> Result<ExpiredCounts, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) FILTER (WHERE NOT kept) as "prunable!",
+               COUNT(*) FILTER (WHERE kept) as "kept!"
+        FROM (
+            SELECT (
+                EXISTS (SELECT 1 FROM user_articles ua WHERE ua.article_id = a.id AND ua.is_saved)
+                OR EXISTS (SELECT 1 FROM article_analysis aa WHERE aa.article_id = a.id)
+                OR EXISTS (
+                    SELECT 1 FROM opposing_articles oa
+                    WHERE oa.source_article_id = a.id OR oa.opposing_article_id = a.id
+                )
+            ) AS kept
+            FROM articles a
+            WHERE a.created_at < $1 AND COALESCE(a.published_at, a.created_at) < $1
+        ) expired
+        "#,
+        cutoff
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(ExpiredCounts {
+        prunable: row.prunable,
+        kept: row.kept,
+    })
+}
+
+/// Delete up to `limit` expired articles (see [`count_expired`]) that no user
+/// has saved and that have no analysis or opposing-article links, oldest
+/// first. Rows locked by other transactions are skipped.
+///
+/// # Returns
+/// The number of articles deleted.
+pub async fn delete_expired_batch(
+    pool: &PgPool,
+    cutoff: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM articles
+        WHERE id IN (
+            SELECT a.id
+            FROM articles a
+            WHERE a.created_at < $1
+              AND COALESCE(a.published_at, a.created_at) < $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM user_articles ua WHERE ua.article_id = a.id AND ua.is_saved
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM article_analysis aa WHERE aa.article_id = a.id
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM opposing_articles oa
+                  WHERE oa.source_article_id = a.id OR oa.opposing_article_id = a.id
+              )
+            ORDER BY a.created_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        "#,
+        cutoff,
+        limit
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    fn sample() -> NewArticle<'static> {
+        NewArticle {
+            feed_id: Uuid::nil(),
+            title: "Title",
+            url: "https://example.com/a",
+            author: None,
+            summary: Some("Summary"),
+            content: None,
+            raw_summary: Some("Summary"),
+            raw_content: None,
+            published_at: None,
+            guid: Some("a"),
+            canonical_url: Some("https://example.com/a"),
+            language: Some("en"),
+            reading_time: ReadingTime::of_text("Summary"),
+        }
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(sample().content_hash(), sample().content_hash());
+        assert_eq!(sample().content_hash().len(), 64);
+    }
+
+    #[test]
+    fn test_content_hash_changes_with_content() {
+        let edited = NewArticle {
+            summary: Some("Summary, corrected"),
+            ..sample()
+        };
+        assert_ne!(sample().content_hash(), edited.content_hash());
+
+        // Moving text between fields is a change too
+        let shifted = NewArticle {
+            author: Some(""),
+            ..sample()
+        };
+        assert_ne!(sample().content_hash(), shifted.content_hash());
+    }
+
+    #[sqlx::test]
+    async fn test_extraction_sets_canonical_url(pool: PgPool) {
+        let feed_id = test_support::insert_feed(&pool, "https://example.com/feed").await;
+        let article = NewArticle { feed_id, ..sample() };
+        let UpsertStatus::Inserted(id) = create_article(&pool, &article).await.unwrap() else {
+            panic!("article should be inserted");
+        };
+        let canonical_url = || async {
+            sqlx::query_scalar::<_, Option<String>>("SELECT canonical_url FROM articles WHERE id = $1")
+                .bind(id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        store_extracted(&pool, id, None, None, Some("https://example.com/story")).await.unwrap();
+        assert_eq!(canonical_url().await.as_deref(), Some("https://example.com/story"));
+
+        // Neither a page without one nor a later feed update replaces it
+        store_extracted(&pool, id, None, None, None).await.unwrap();
+        let edited = NewArticle { summary: Some("Summary, corrected"), ..article };
+        assert_eq!(create_article(&pool, &edited).await.unwrap(), UpsertStatus::Updated(id));
+        assert_eq!(canonical_url().await.as_deref(), Some("https://example.com/story"));
+    }
+
+    #[sqlx::test]
+    async fn test_article_only_visible_to_subscribers(pool: PgPool) {
+        let owner = test_support::insert_user(&pool, "owner@example.com").await;
+        let other = test_support::insert_user(&pool, "other@example.com").await;
+        let feed_id = test_support::insert_feed(&pool, "https://example.com/private").await;
+        sqlx::query("UPDATE feeds SET owner_user_id = $2 WHERE id = $1")
+            .bind(feed_id)
+            .bind(owner)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO user_feeds (user_id, feed_id) VALUES ($1, $2)")
+            .bind(owner)
+            .bind(feed_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let id = test_support::insert_article(&pool, feed_id, "a").await;
+
+        assert!(get_article(&pool, owner, id).await.unwrap().is_some());
+        // GET /api/articles/:id answers 404 for everyone else
+        assert!(get_article(&pool, other, id).await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    async fn test_collapse_duplicates(pool: PgPool) {
+        let user = test_support::insert_user(&pool, "a@example.com").await;
+        for url in ["https://one.example/feed", "https://two.example/feed"] {
+            let feed_id = test_support::insert_feed(&pool, url).await;
+            sqlx::query("INSERT INTO user_feeds (user_id, feed_id) VALUES ($1, $2)")
+                .bind(user)
+                .bind(feed_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            let id = test_support::insert_article(&pool, feed_id, "story").await;
+            sqlx::query("UPDATE articles SET canonical_url = 'https://example.com/story' WHERE id = $1")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let pool = &pool;
+        let list = |collapse_duplicates| async move {
+            let filters = ArticleFilters { collapse_duplicates, ..Default::default() };
+            list_articles_for_user(pool, user, &filters, 10, 0).await.unwrap()
+        };
+        let all = list(false).await;
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|a| a.duplicate_count == 1));
+
+        let collapsed = list(true).await;
+        assert_eq!(collapsed.len(), 1);
+        assert_eq!(collapsed[0].duplicate_count, 2);
+    }
+}
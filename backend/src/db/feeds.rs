@@ -1,15 +1,23 @@
+use chrono::Weekday;
 use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::models::feed::Feed;
+use crate::services::schedule::ScheduleHints;
 
 /// Get all feeds a user is subscribed to.
 pub async fn list_user_feeds(pool: &PgPool, user_id: Uuid) -> Result<Vec<Feed>, sqlx::Error> {
     sqlx::query_as!(
         Feed,
         r#"
-        SELECT f.id, f.title, f.url, f.site_url, f.description, f.topic_id,
-               f.is_curated, f.last_fetched_at, f.created_at, f.updated_at
+        SELECT f.id, f.title, f.url, f.site_url, f.description, f.topic_id, f.is_curated,
+               f.language, f.icon_url, f.title_locked, f.last_fetched_at, f.etag,
+               f.last_modified, f.fetch_interval_minutes, f.next_fetch_at,
+               f.consecutive_failures, f.last_error, f.last_error_at, f.last_success_at,
+               f.disabled, f.gone_at, f.identity_strategy, f.fetch_full_content,
+               f.owner_user_id, f.credentials, f.kind, f.scrape_rules, f.created_at,
+               f.updated_at
         FROM feeds f
         INNER JOIN user_feeds uf ON f.id = uf.feed_id
         WHERE uf.user_id = $1
@@ -21,15 +29,21 @@ pub async fn list_user_feeds(pool: &PgPool, user_id: Uuid) -> Result<Vec<Feed>,
     .await
 }
 
-/// Find a feed by its URL (for checking if it already exists).
+/// Find a shared feed by its URL (for checking if it already exists).
+///
+/// Private feeds are never returned: they aren't shared with other users.
 pub async fn get_feed_by_url(pool: &PgPool, url: &str) -> Result<Option<Feed>, sqlx::Error> {
     sqlx::query_as!(
         Feed,
         r#"
-        SELECT id, title, url, site_url, description, topic_id,
-               is_curated, last_fetched_at, created_at, updated_at
+        SELECT id, title, url, site_url, description, topic_id, is_curated, language,
+               icon_url, title_locked, last_fetched_at, etag, last_modified,
+               fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+               last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+               fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+               created_at, updated_at
         FROM feeds
-        WHERE url = $1
+        WHERE url = $1 AND owner_user_id IS NULL
         "#,
         url
     )
@@ -42,8 +56,12 @@ pub async fn get_feed_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Feed>, sql
     sqlx::query_as!(
         Feed,
         r#"
-        SELECT id, title, url, site_url, description, topic_id,
-               is_curated, last_fetched_at, created_at, updated_at
+        SELECT id, title, url, site_url, description, topic_id, is_curated, language,
+               icon_url, title_locked, last_fetched_at, etag, last_modified,
+               fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+               last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+               fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+               created_at, updated_at
         FROM feeds
         WHERE id = $1
         "#,
@@ -67,8 +85,12 @@ pub async fn create_feed(
         r#"
         INSERT INTO feeds (title, url, site_url, description, topic_id)
         VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, title, url, site_url, description, topic_id,
-                  is_curated, last_fetched_at, created_at, updated_at
+        RETURNING id, title, url, site_url, description, topic_id, is_curated, language,
+                  icon_url, title_locked, last_fetched_at, etag, last_modified,
+                  fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+                  last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+                  fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+                  created_at, updated_at
         "#,
         title,
         url,
@@ -80,21 +102,207 @@ pub async fn create_feed(
     .await
 }
 
-/// Subscribe a user to a feed (insert into user_feeds junction table).
+/// Create a shared feed read from a `file://` URL.
+pub async fn create_file_feed(pool: &PgPool, url: &str) -> Result<Feed, sqlx::Error> {
+    sqlx::query_as!(
+        Feed,
+        r#"
+        INSERT INTO feeds (title, url, kind)
+        VALUES ($1, $1, 'file')
+        RETURNING id, title, url, site_url, description, topic_id, is_curated, language,
+                  icon_url, title_locked, last_fetched_at, etag, last_modified,
+                  fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+                  last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+                  fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+                  created_at, updated_at
+        "#,
+        url
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Find a user's private feed by its URL.
+pub async fn get_private_feed(
+    pool: &PgPool,
+    owner_user_id: Uuid,
+    url: &str,
+) -> Result<Option<Feed>, sqlx::Error> {
+    sqlx::query_as!(
+        Feed,
+        r#"
+        SELECT id, title, url, site_url, description, topic_id, is_curated, language,
+               icon_url, title_locked, last_fetched_at, etag, last_modified,
+               fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+               last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+               fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+               created_at, updated_at
+        FROM feeds
+        WHERE owner_user_id = $1 AND url = $2
+        "#,
+        owner_user_id,
+        url
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Create a private feed for `owner_user_id` with encrypted credentials, or
+/// replace the credentials of the owner's existing private feed at `url`.
+pub async fn upsert_private_feed(
+    pool: &PgPool,
+    owner_user_id: Uuid,
+    url: &str,
+    credentials: &[u8],
+) -> Result<Feed, sqlx::Error> {
+    sqlx::query_as!(
+        Feed,
+        r#"
+        INSERT INTO feeds (title, url, owner_user_id, credentials)
+        VALUES ($2, $2, $1, $3)
+        ON CONFLICT (owner_user_id, url) WHERE owner_user_id IS NOT NULL DO UPDATE
+        SET credentials = EXCLUDED.credentials,
+            kind = 'feed',
+            scrape_rules = NULL,
+            etag = NULL,
+            last_modified = NULL,
+            updated_at = NOW()
+        RETURNING id, title, url, site_url, description, topic_id, is_curated, language,
+                  icon_url, title_locked, last_fetched_at, etag, last_modified,
+                  fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+                  last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+                  fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+                  created_at, updated_at
+        "#,
+        owner_user_id,
+        url,
+        credentials
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Create a scraped source for `owner_user_id`, or replace the selectors of
+/// the one they already have at `url`.
+pub async fn upsert_scraped_feed(
+    pool: &PgPool,
+    owner_user_id: Uuid,
+    url: &str,
+    scrape_rules: &serde_json::Value,
+) -> Result<Feed, sqlx::Error> {
+    sqlx::query_as!(
+        Feed,
+        r#"
+        INSERT INTO feeds (title, url, owner_user_id, kind, scrape_rules)
+        VALUES ($2, $2, $1, 'scrape', $3)
+        ON CONFLICT (owner_user_id, url) WHERE owner_user_id IS NOT NULL DO UPDATE
+        SET kind = 'scrape',
+            scrape_rules = EXCLUDED.scrape_rules,
+            credentials = NULL,
+            etag = NULL,
+            last_modified = NULL,
+            updated_at = NOW()
+        RETURNING id, title, url, site_url, description, topic_id, is_curated, language,
+                  icon_url, title_locked, last_fetched_at, etag, last_modified,
+                  fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+                  last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+                  fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+                  created_at, updated_at
+        "#,
+        owner_user_id,
+        url,
+        scrape_rules
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Result of [`subscribe_user_to_feed`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subscription {
+    /// The user is now subscribed.
+    Added,
+    /// The user was already subscribed.
+    Existing,
+    /// The user already has `limit` feeds; nothing was changed.
+    LimitReached { limit: i32 },
+}
+
+/// Subscribe a user to a feed (insert into user_feeds junction table), unless
+/// that would take them past their feed limit.
+///
+/// The limit is the user's own `max_feeds`, or `default_limit` if they have
+/// none. The user's row is locked while counting, so concurrent subscribes by
+/// the same user can't both slip under the limit.
 pub async fn subscribe_user_to_feed(
     pool: &PgPool,
     user_id: Uuid,
     feed_id: Uuid,
-) -> Result<(), sqlx::Error> {
+    default_limit: i32,
+) -> Result<Subscription, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let limit = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(max_feeds, $2) as "limit!"
+        FROM users
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        user_id,
+        default_limit
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let current = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) as "count!",
+            COALESCE(bool_or(feed_id = $2), FALSE) as "subscribed!"
+        FROM user_feeds
+        WHERE user_id = $1
+        "#,
+        user_id,
+        feed_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if current.subscribed {
+        return Ok(Subscription::Existing);
+    }
+    if current.count >= i64::from(limit) {
+        return Ok(Subscription::LimitReached { limit });
+    }
+
     sqlx::query!(
         r#"
         INSERT INTO user_feeds (user_id, feed_id)
         VALUES ($1, $2)
-        ON CONFLICT (user_id, feed_id) DO NOTHING
         "#,
         user_id,
         feed_id
     )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Subscription::Added)
+}
+
+/// Delete a feed nobody is subscribed to (one created for a subscription
+/// that was then refused).
+pub async fn delete_unsubscribed_feed(pool: &PgPool, feed_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM feeds
+        WHERE id = $1
+          AND NOT EXISTS (SELECT 1 FROM user_feeds WHERE feed_id = $1)
+        "#,
+        feed_id
+    )
     .execute(pool)
     .await?;
 
@@ -102,11 +310,16 @@ pub async fn subscribe_user_to_feed(
 }
 
 /// Unsubscribe a user from a feed (delete from user_feeds junction table).
+///
+/// A private feed is deleted along with its credentials and articles when its
+/// owner unsubscribes.
 pub async fn unsubscribe_user_from_feed(
     pool: &PgPool,
     user_id: Uuid,
     feed_id: Uuid,
 ) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
     sqlx::query!(
         r#"
         DELETE FROM user_feeds
@@ -115,18 +328,257 @@ pub async fn unsubscribe_user_from_feed(
         user_id,
         feed_id
     )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM feeds
+        WHERE id = $2 AND owner_user_id = $1
+        "#,
+        user_id,
+        feed_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Whether a user is subscribed to a feed.
+pub async fn is_user_subscribed(
+    pool: &PgPool,
+    user_id: Uuid,
+    feed_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let subscribed = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM user_feeds WHERE user_id = $1 AND feed_id = $2
+        ) as "subscribed!"
+        "#,
+        user_id,
+        feed_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(subscribed)
+}
+
+/// Record a successful fetch: bumps last_fetched_at / last_success_at and
+/// resets the failure streak (re-enabling the feed if it had been disabled).
+pub async fn record_fetch_success(pool: &PgPool, feed_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE feeds
+        SET last_fetched_at = NOW(),
+            last_success_at = NOW(),
+            consecutive_failures = 0,
+            disabled = FALSE,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        feed_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Feed health after recording a failed fetch.
+#[derive(Debug)]
+pub struct FailureRecord {
+    pub consecutive_failures: i32,
+    pub disabled: bool,
+}
+
+/// Record a failed fetch: extends the failure streak, stores the error and
+/// schedules the retry. The feed is disabled once the streak reaches
+/// `failure_threshold`.
+pub async fn record_fetch_failure(
+    pool: &PgPool,
+    feed_id: Uuid,
+    error: &str,
+    next_fetch_at: DateTime<Utc>,
+    failure_threshold: i32,
+) -> Result<FailureRecord, sqlx::Error> {
+    sqlx::query_as!(
+        FailureRecord,
+        r#"
+        UPDATE feeds
+        SET last_fetched_at = NOW(),
+            consecutive_failures = consecutive_failures + 1,
+            last_error = $2,
+            last_error_at = NOW(),
+            next_fetch_at = $3,
+            disabled = disabled OR consecutive_failures + 1 >= $4,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING consecutive_failures, disabled
+        "#,
+        feed_id,
+        error,
+        next_fetch_at,
+        failure_threshold
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Update a feed's descriptive metadata from its parsed contents.
+///
+/// `None` values keep whatever is currently stored. The title is left alone
+/// for feeds with `title_locked` set.
+pub async fn update_feed_metadata(
+    pool: &PgPool,
+    feed_id: Uuid,
+    title: Option<&str>,
+    site_url: Option<&str>,
+    description: Option<&str>,
+    language: Option<&str>,
+    icon_url: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE feeds
+        SET title = CASE WHEN title_locked THEN title ELSE COALESCE($2, title) END,
+            site_url = COALESCE($3, site_url),
+            description = COALESCE($4, description),
+            language = COALESCE($5, language),
+            icon_url = COALESCE($6, icon_url),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        feed_id,
+        title,
+        site_url,
+        description,
+        language,
+        icon_url
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Store the HTTP cache validators returned by the last successful fetch.
+/// Passing `None` clears a validator the server no longer sends.
+pub async fn update_http_validators(
+    pool: &PgPool,
+    feed_id: Uuid,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE feeds
+        SET etag = $2, last_modified = $3, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        feed_id,
+        etag,
+        last_modified
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a feed's computed fetch interval, when it is next due and the hours
+/// and days its publisher asks not to be fetched in.
+pub async fn update_schedule(
+    pool: &PgPool,
+    feed_id: Uuid,
+    fetch_interval_minutes: i32,
+    next_fetch_at: DateTime<Utc>,
+    hints: &ScheduleHints,
+) -> Result<(), sqlx::Error> {
+    let skip_hours: Vec<i16> = hints.skip_hours.iter().map(|&hour| hour as i16).collect();
+    let skip_days: Vec<i16> = hints
+        .skip_days
+        .iter()
+        .map(|day| day.num_days_from_monday() as i16)
+        .collect();
+
+    sqlx::query!(
+        r#"
+        UPDATE feeds
+        SET fetch_interval_minutes = $2, next_fetch_at = $3, skip_hours = $4, skip_days = $5,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        feed_id,
+        fetch_interval_minutes,
+        next_fetch_at,
+        &skip_hours,
+        &skip_days
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The `skipHours` / `skipDays` stored for a feed by its last full fetch (the
+/// other hints are only used to compute the interval, so aren't stored).
+pub async fn get_skip_hints(pool: &PgPool, feed_id: Uuid) -> Result<ScheduleHints, sqlx::Error> {
+    let row = sqlx::query!(
+        "SELECT skip_hours, skip_days FROM feeds WHERE id = $1",
+        feed_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row
+        .map(|row| ScheduleHints {
+            skip_hours: row.skip_hours.into_iter().map(|hour| hour as u32).collect(),
+            skip_days: row
+                .skip_days
+                .into_iter()
+                .filter_map(|day| Weekday::try_from(day as u8).ok())
+                .collect(),
+            ..Default::default()
+        })
+        .unwrap_or_default())
+}
+
+/// Push back a feed's next fetch without changing its interval.
+pub async fn update_next_fetch_at(
+    pool: &PgPool,
+    feed_id: Uuid,
+    next_fetch_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE feeds
+        SET next_fetch_at = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        feed_id,
+        next_fetch_at
+    )
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
-/// Update the last_fetched_at timestamp for a feed.
-pub async fn update_last_fetched(pool: &PgPool, feed_id: Uuid) -> Result<(), sqlx::Error> {
+/// Mark a feed as gone (the server answered `410 Gone`) so it is no longer fetched.
+pub async fn mark_feed_gone(pool: &PgPool, feed_id: Uuid) -> Result<(), sqlx::Error> {
     sqlx::query!(
         r#"
         UPDATE feeds
-        SET last_fetched_at = NOW(), updated_at = NOW()
+        SET gone_at = NOW(),
+            last_fetched_at = NOW(),
+            last_error = '410 Gone',
+            last_error_at = NOW(),
+            updated_at = NOW()
         WHERE id = $1
         "#,
         feed_id
@@ -137,16 +589,271 @@ pub async fn update_last_fetched(pool: &PgPool, feed_id: Uuid) -> Result<(), sql
     Ok(())
 }
 
-/// Get all feeds that have at least one subscriber (active feeds).
+/// Move a feed to the URL it permanently redirected to.
+///
+/// If no other feed uses `new_url` the feed's URL is simply rewritten. If one
+/// does (among shared feeds, or the same owner's private feeds), this feed is
+/// merged into it: subscriptions, curated topics and articles
+/// move to the existing feed (read/saved state, AI analyses and
+/// opposing-article links are carried over for articles both feeds already
+/// had), then this feed is deleted.
+///
+/// # Returns
+/// The ID of the feed that now lives at `new_url`.
+pub async fn relocate_feed(
+    pool: &PgPool,
+    feed_id: Uuid,
+    new_url: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let existing = sqlx::query_scalar!(
+        r#"
+        SELECT target.id FROM feeds target
+        INNER JOIN feeds moved ON moved.id = $2
+        WHERE target.url = $1
+          AND target.id <> $2
+          AND target.owner_user_id IS NOT DISTINCT FROM moved.owner_user_id
+        FOR UPDATE OF target
+        "#,
+        new_url,
+        feed_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(target_id) = existing else {
+        sqlx::query!(
+            r#"
+            UPDATE feeds
+            SET url = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            feed_id,
+            new_url
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        return Ok(feed_id);
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_feeds (user_id, feed_id, added_at)
+        SELECT user_id, $2, added_at FROM user_feeds WHERE feed_id = $1
+        ON CONFLICT (user_id, feed_id) DO NOTHING
+        "#,
+        feed_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO curated_feeds (topic_id, feed_id)
+        SELECT topic_id, $2 FROM curated_feeds WHERE feed_id = $1
+        ON CONFLICT (topic_id, feed_id) DO NOTHING
+        "#,
+        feed_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Articles both feeds have: keep the target's copy, carrying read/saved state over
+    sqlx::query!(
+        r#"
+        INSERT INTO user_articles (user_id, article_id, is_read, is_saved, read_at, saved_at)
+        SELECT ua.user_id, target.id, ua.is_read, ua.is_saved, ua.read_at, ua.saved_at
+        FROM user_articles ua
+        INNER JOIN articles old ON old.id = ua.article_id
+        INNER JOIN articles target ON target.feed_id = $2 AND target.guid = old.guid
+        WHERE old.feed_id = $1
+        ON CONFLICT (user_id, article_id) DO UPDATE
+        SET is_read = user_articles.is_read OR EXCLUDED.is_read,
+            is_saved = user_articles.is_saved OR EXCLUDED.is_saved,
+            read_at = COALESCE(user_articles.read_at, EXCLUDED.read_at),
+            saved_at = COALESCE(user_articles.saved_at, EXCLUDED.saved_at)
+        "#,
+        feed_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Analyses and opposing-article links would cascade with the old copy
+    sqlx::query!(
+        r#"
+        UPDATE article_analysis aa
+        SET article_id = target.id
+        FROM articles old
+        INNER JOIN articles target ON target.feed_id = $2 AND target.guid = old.guid
+        WHERE aa.article_id = old.id
+          AND old.feed_id = $1
+          AND NOT EXISTS (SELECT 1 FROM article_analysis t WHERE t.article_id = target.id)
+        "#,
+        feed_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        WITH copies AS (
+            SELECT old.id AS old_id, target.id AS target_id
+            FROM articles old
+            INNER JOIN articles target ON target.feed_id = $2 AND target.guid = old.guid
+            WHERE old.feed_id = $1
+        )
+        INSERT INTO opposing_articles
+            (source_article_id, opposing_article_id, relevance_score, created_at)
+        SELECT COALESCE(src.target_id, oa.source_article_id),
+               COALESCE(opp.target_id, oa.opposing_article_id),
+               oa.relevance_score, oa.created_at
+        FROM opposing_articles oa
+        LEFT JOIN copies src ON src.old_id = oa.source_article_id
+        LEFT JOIN copies opp ON opp.old_id = oa.opposing_article_id
+        WHERE (src.old_id IS NOT NULL OR opp.old_id IS NOT NULL)
+          AND COALESCE(src.target_id, oa.source_article_id)
+              <> COALESCE(opp.target_id, oa.opposing_article_id)
+        ON CONFLICT (source_article_id, opposing_article_id) DO NOTHING
+        "#,
+        feed_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Articles only this feed has move over as-is
+    sqlx::query!(
+        r#"
+        UPDATE articles old
+        SET feed_id = $2
+        WHERE old.feed_id = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM articles target
+              WHERE target.feed_id = $2 AND target.guid = old.guid
+          )
+        "#,
+        feed_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE feeds target
+        SET is_curated = target.is_curated OR old.is_curated,
+            updated_at = NOW()
+        FROM feeds old
+        WHERE target.id = $2 AND old.id = $1
+        "#,
+        feed_id,
+        target_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Remaining duplicate articles and subscriptions go with the old feed
+    sqlx::query!("DELETE FROM feeds WHERE id = $1", feed_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(target_id)
+}
+
+/// Get all feeds that have at least one subscriber and are due for a fetch.
 /// Used by the scheduler to determine which feeds need to be fetched.
-pub async fn get_all_active_feeds(pool: &PgPool) -> Result<Vec<Feed>, sqlx::Error> {
+pub async fn get_due_feeds(pool: &PgPool) -> Result<Vec<Feed>, sqlx::Error> {
     sqlx::query_as!(
         Feed,
         r#"
-        SELECT DISTINCT f.id, f.title, f.url, f.site_url, f.description, f.topic_id,
-               f.is_curated, f.last_fetched_at, f.created_at, f.updated_at
+        SELECT f.id, f.title, f.url, f.site_url, f.description, f.topic_id, f.is_curated,
+               f.language, f.icon_url, f.title_locked, f.last_fetched_at, f.etag,
+               f.last_modified, f.fetch_interval_minutes, f.next_fetch_at,
+               f.consecutive_failures, f.last_error, f.last_error_at, f.last_success_at,
+               f.disabled, f.gone_at, f.identity_strategy, f.fetch_full_content,
+               f.owner_user_id, f.credentials, f.kind, f.scrape_rules, f.created_at,
+               f.updated_at
         FROM feeds f
-        INNER JOIN user_feeds uf ON f.id = uf.feed_id
+        WHERE EXISTS (SELECT 1 FROM user_feeds uf WHERE uf.feed_id = f.id)
+          AND NOT f.disabled
+          AND f.gone_at IS NULL
+          AND (f.next_fetch_at IS NULL OR f.next_fetch_at <= NOW())
+        ORDER BY f.next_fetch_at ASC NULLS FIRST
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Change how a feed's articles are identified (see `services::identity`).
+pub async fn update_identity_strategy(
+    pool: &PgPool,
+    feed_id: Uuid,
+    identity_strategy: &str,
+) -> Result<Feed, sqlx::Error> {
+    sqlx::query_as!(
+        Feed,
+        r#"
+        UPDATE feeds
+        SET identity_strategy = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, title, url, site_url, description, topic_id, is_curated, language,
+                  icon_url, title_locked, last_fetched_at, etag, last_modified,
+                  fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+                  last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+                  fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+                  created_at, updated_at
+        "#,
+        feed_id,
+        identity_strategy
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Turn full-content extraction on or off for a feed.
+pub async fn update_fetch_full_content(
+    pool: &PgPool,
+    feed_id: Uuid,
+    fetch_full_content: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE feeds
+        SET fetch_full_content = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        feed_id,
+        fetch_full_content
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Get every feed, for maintenance commands.
+pub async fn list_all_feeds(pool: &PgPool) -> Result<Vec<Feed>, sqlx::Error> {
+    sqlx::query_as!(
+        Feed,
+        r#"
+        SELECT id, title, url, site_url, description, topic_id, is_curated, language,
+               icon_url, title_locked, last_fetched_at, etag, last_modified,
+               fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+               last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+               fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+               created_at, updated_at
+        FROM feeds
+        ORDER BY title ASC
         "#
     )
     .fetch_all(pool)
@@ -154,6 +861,7 @@ pub async fn get_all_active_feeds(pool: &PgPool) -> Result<Vec<Feed>, sqlx::Erro
 }
 
 /// Get curated feeds for given topics (useful for onboarding).
+#[allow(dead_code)]
 pub async fn get_curated_feeds_for_topics(
     pool: &PgPool,
     topic_ids: &[Uuid],
@@ -161,8 +869,12 @@ pub async fn get_curated_feeds_for_topics(
     sqlx::query_as!(
         Feed,
         r#"
-        SELECT id, title, url, site_url, description, topic_id,
-               is_curated, last_fetched_at, created_at, updated_at
+        SELECT id, title, url, site_url, description, topic_id, is_curated, language,
+               icon_url, title_locked, last_fetched_at, etag, last_modified,
+               fetch_interval_minutes, next_fetch_at, consecutive_failures, last_error,
+               last_error_at, last_success_at, disabled, gone_at, identity_strategy,
+               fetch_full_content, owner_user_id, credentials, kind, scrape_rules,
+               created_at, updated_at
         FROM feeds
         WHERE is_curated = TRUE AND topic_id = ANY($1)
         ORDER BY title ASC
@@ -172,3 +884,163 @@ pub async fn get_curated_feeds_for_topics(
     .fetch_all(pool)
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{test_support, users};
+
+    #[sqlx::test]
+    async fn test_skip_hints_are_stored(pool: PgPool) {
+        let feed_id = test_support::insert_feed(&pool, "https://example.com/feed").await;
+        assert_eq!(get_skip_hints(&pool, feed_id).await.unwrap(), ScheduleHints::default());
+
+        let hints = ScheduleHints {
+            ttl_minutes: Some(60),
+            skip_hours: vec![23, 0, 1],
+            skip_days: vec![Weekday::Sat, Weekday::Sun],
+            update_period_minutes: None,
+        };
+        update_schedule(&pool, feed_id, 60, Utc::now(), &hints).await.unwrap();
+
+        // Only the skip hints are needed to schedule a 304
+        let stored = get_skip_hints(&pool, feed_id).await.unwrap();
+        assert_eq!(stored, ScheduleHints { ttl_minutes: None, ..hints });
+    }
+
+    #[sqlx::test]
+    async fn test_subscribe_user_to_feed_respects_limit(pool: PgPool) {
+        let user = test_support::insert_user(&pool, "a@example.com").await;
+        let first = test_support::insert_feed(&pool, "https://example.com/1").await;
+        let second = test_support::insert_feed(&pool, "https://example.com/2").await;
+        let third = test_support::insert_feed(&pool, "https://example.com/3").await;
+        let count = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM user_feeds WHERE user_id = $1")
+                .bind(user)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        let subscribe = |feed_id| subscribe_user_to_feed(&pool, user, feed_id, 2);
+        assert_eq!(subscribe(first).await.unwrap(), Subscription::Added);
+        assert_eq!(subscribe(first).await.unwrap(), Subscription::Existing);
+        assert_eq!(count().await, 1);
+
+        assert_eq!(subscribe(second).await.unwrap(), Subscription::Added);
+        assert_eq!(subscribe(third).await.unwrap(), Subscription::LimitReached { limit: 2 });
+        // At the limit, feeds already subscribed to are still reported as such
+        assert_eq!(subscribe(second).await.unwrap(), Subscription::Existing);
+        assert_eq!(count().await, 2);
+
+        // The user's own limit wins over the default
+        assert!(users::set_max_feeds(&pool, "a@example.com", Some(3)).await.unwrap());
+        assert_eq!(subscribe(third).await.unwrap(), Subscription::Added);
+        assert!(users::set_max_feeds(&pool, "a@example.com", Some(1)).await.unwrap());
+        let fourth = test_support::insert_feed(&pool, "https://example.com/4").await;
+        assert_eq!(subscribe(fourth).await.unwrap(), Subscription::LimitReached { limit: 1 });
+        assert_eq!(count().await, 3);
+    }
+
+    #[sqlx::test]
+    async fn test_relocate_feed_merges_into_existing_feed(pool: PgPool) {
+        let old_feed = test_support::insert_feed(&pool, "http://example.com/feed").await;
+        let target = test_support::insert_feed(&pool, "https://example.com/feed").await;
+        let user = test_support::insert_user(&pool, "a@example.com").await;
+        let other = test_support::insert_user(&pool, "b@example.com").await;
+        for (user_id, feed) in [(user, old_feed), (other, target)] {
+            sqlx::query("INSERT INTO user_feeds (user_id, feed_id) VALUES ($1, $2)")
+                .bind(user_id)
+                .bind(feed)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // "shared" is in both feeds, "only-old" just in the one being moved
+        let old_shared = test_support::insert_article(&pool, old_feed, "shared").await;
+        let only_old = test_support::insert_article(&pool, old_feed, "only-old").await;
+        let target_shared = test_support::insert_article(&pool, target, "shared").await;
+        let elsewhere = test_support::insert_article(&pool, target, "elsewhere").await;
+
+        sqlx::query(
+            "INSERT INTO user_articles (user_id, article_id, is_read, is_saved, read_at, saved_at)
+             VALUES ($1, $2, TRUE, TRUE, NOW(), NOW())",
+        )
+        .bind(user)
+        .bind(old_shared)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO article_analysis (article_id, provider) VALUES ($1, 'ollama')")
+            .bind(old_shared)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO opposing_articles (source_article_id, opposing_article_id, relevance_score)
+             VALUES ($1, $2, 0.9), ($3, $1, 0.8)",
+        )
+        .bind(old_shared)
+        .bind(elsewhere)
+        .bind(only_old)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let moved = relocate_feed(&pool, old_feed, "https://example.com/feed").await.unwrap();
+        assert_eq!(moved, target);
+
+        let feed_left: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM feeds WHERE id = $1)")
+            .bind(old_feed)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(!feed_left);
+
+        let mut subscribers: Vec<Uuid> =
+            sqlx::query_scalar("SELECT user_id FROM user_feeds WHERE feed_id = $1")
+                .bind(target)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        subscribers.sort();
+        let mut expected = vec![user, other];
+        expected.sort();
+        assert_eq!(subscribers, expected);
+
+        let (is_read, is_saved): (bool, bool) = sqlx::query_as(
+            "SELECT is_read, is_saved FROM user_articles WHERE user_id = $1 AND article_id = $2",
+        )
+        .bind(user)
+        .bind(target_shared)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert!(is_read && is_saved);
+
+        let only_old_feed: Uuid = sqlx::query_scalar("SELECT feed_id FROM articles WHERE id = $1")
+            .bind(only_old)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(only_old_feed, target);
+
+        let analysed: Vec<Uuid> = sqlx::query_scalar("SELECT article_id FROM article_analysis")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(analysed, vec![target_shared]);
+
+        let mut links: Vec<(Uuid, Uuid)> = sqlx::query_as(
+            "SELECT source_article_id, opposing_article_id FROM opposing_articles",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        links.sort();
+        let mut expected = vec![(target_shared, elsewhere), (only_old, target_shared)];
+        expected.sort();
+        assert_eq!(links, expected);
+    }
+}
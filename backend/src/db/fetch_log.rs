@@ -0,0 +1,82 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::feed::FetchLogEntry;
+
+/// A fetch attempt to record (see [`record`]).
+#[derive(Debug)]
+pub struct NewFetchLogEntry<'a> {
+    pub feed_id: Uuid,
+    pub http_status: Option<i32>,
+    pub duration_ms: i32,
+    pub bytes: Option<i64>,
+    pub new_articles: i32,
+    pub updated_articles: i32,
+    pub error_kind: Option<&'a str>,
+    pub error_message: Option<&'a str>,
+}
+
+/// Record one fetch attempt in a feed's history.
+pub async fn record(pool: &PgPool, entry: &NewFetchLogEntry<'_>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO feed_fetch_log
+            (feed_id, http_status, duration_ms, bytes, new_articles, updated_articles,
+             error_kind, error_message)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+        entry.feed_id,
+        entry.http_status,
+        entry.duration_ms,
+        entry.bytes,
+        entry.new_articles,
+        entry.updated_articles,
+        entry.error_kind,
+        entry.error_message
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A feed's most recent fetch attempts, newest first.
+pub async fn list_for_feed(
+    pool: &PgPool,
+    feed_id: Uuid,
+    limit: i64,
+) -> Result<Vec<FetchLogEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        FetchLogEntry,
+        r#"
+        SELECT fetched_at, http_status, duration_ms, bytes, new_articles, updated_articles,
+               error_kind, error_message
+        FROM feed_fetch_log
+        WHERE feed_id = $1
+        ORDER BY fetched_at DESC
+        LIMIT $2
+        "#,
+        feed_id,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete fetch history older than `retention_days`.
+///
+/// # Returns
+/// The number of rows deleted.
+pub async fn prune(pool: &PgPool, retention_days: i32) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM feed_fetch_log
+        WHERE fetched_at < NOW() - $1::int * INTERVAL '1 day'
+        "#,
+        retention_days
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,33 @@
+use sqlx::PgPool;
+
+/// Whether the one-off maintenance task `name` still has to run.
+pub async fn is_pending(pool: &PgPool, name: &str) -> Result<bool, sqlx::Error> {
+    let pending = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM maintenance_tasks WHERE name = $1 AND completed_at IS NULL
+        ) as "pending!"
+        "#,
+        name
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(pending)
+}
+
+/// Record that the maintenance task `name` has run.
+pub async fn mark_completed(pool: &PgPool, name: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE maintenance_tasks
+        SET completed_at = NOW()
+        WHERE name = $1
+        "#,
+        name
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -1,4 +1,9 @@
 pub mod articles;
 pub mod feeds;
+pub mod fetch_log;
+pub mod maintenance;
+#[cfg(test)]
+pub mod test_support;
 pub mod topics;
 pub mod users;
+pub mod websub;
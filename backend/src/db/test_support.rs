@@ -0,0 +1,36 @@
+//! Fixtures for database tests (`#[sqlx::test]`).
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Insert a public feed at `url`.
+pub async fn insert_feed(pool: &PgPool, url: &str) -> Uuid {
+    sqlx::query_scalar("INSERT INTO feeds (title, url) VALUES ('Feed', $1) RETURNING id")
+        .bind(url)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Insert a user without a password.
+pub async fn insert_user(pool: &PgPool, email: &str) -> Uuid {
+    sqlx::query_scalar("INSERT INTO users (email, display_name) VALUES ($1, $1) RETURNING id")
+        .bind(email)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Insert an article keyed by `guid`, linking to `https://example.com/<guid>`.
+pub async fn insert_article(pool: &PgPool, feed_id: Uuid, guid: &str) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO articles (feed_id, title, url, guid)
+         VALUES ($1, $2, 'https://example.com/' || $2, $2)
+         RETURNING id",
+    )
+    .bind(feed_id)
+    .bind(guid)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
@@ -26,6 +26,7 @@ pub async fn create_user(
 }
 
 /// Create a new user with OAuth authentication.
+#[allow(dead_code)]
 pub async fn create_oauth_user(
     pool: &PgPool,
     email: &str,
@@ -86,6 +87,7 @@ pub async fn find_by_id(
 }
 
 /// Find a user by their OAuth provider and OAuth ID.
+#[allow(dead_code)]
 pub async fn find_by_oauth(
     pool: &PgPool,
     provider: &str,
@@ -104,3 +106,141 @@ pub async fn find_by_oauth(
     .fetch_optional(pool)
     .await
 }
+
+/// What a user has used of their account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub feeds: i64,
+    /// The user's feed limit (their own, or the default).
+    pub max_feeds: i32,
+    pub saved_articles: i64,
+    /// AI analyses run at the user's request (counted when written, so
+    /// deleting an analysis doesn't give the use back).
+    pub analyses: i64,
+}
+
+/// Count what a user has used; `None` if there is no such user.
+pub async fn get_usage(
+    pool: &PgPool,
+    id: Uuid,
+    default_max_feeds: i32,
+) -> Result<Option<Usage>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            COALESCE(u.max_feeds, $2) as "max_feeds!",
+            (SELECT COUNT(*) FROM user_feeds WHERE user_id = u.id) as "feeds!",
+            (SELECT COUNT(*) FROM user_articles WHERE user_id = u.id AND is_saved = TRUE) as "saved_articles!",
+            u.ai_analyses_used as "analyses!"
+        FROM users u
+        WHERE u.id = $1
+        "#,
+        id,
+        default_max_feeds
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| Usage {
+        feeds: row.feeds,
+        max_feeds: row.max_feeds,
+        saved_articles: row.saved_articles,
+        analyses: row.analyses,
+    }))
+}
+
+/// Set a user's own feed limit (`None` goes back to the default).
+///
+/// # Returns
+/// Whether a user with that email exists.
+pub async fn set_max_feeds(
+    pool: &PgPool,
+    email: &str,
+    max_feeds: Option<i32>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET max_feeds = $2, updated_at = NOW()
+        WHERE email = $1
+        "#,
+        email,
+        max_feeds
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// A user's preferred article languages (ISO 639-1 codes; empty for all).
+pub async fn get_preferred_languages(pool: &PgPool, id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    let languages = sqlx::query_scalar!(
+        r#"
+        SELECT preferred_languages
+        FROM users
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(languages.unwrap_or_default())
+}
+
+/// Set a user's preferred article languages.
+pub async fn set_preferred_languages(
+    pool: &PgPool,
+    id: Uuid,
+    languages: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET preferred_languages = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        languages
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    #[sqlx::test]
+    async fn test_analysis_usage_survives_deleted_analyses(pool: PgPool) {
+        let user = create_user(&pool, "a@example.com", "hash", "A").await.unwrap();
+        let feed_id = test_support::insert_feed(&pool, "https://example.com/feed").await;
+        let article_id = test_support::insert_article(&pool, feed_id, "a").await;
+
+        sqlx::query("INSERT INTO article_analysis (article_id, provider, requested_by) VALUES ($1, 'ollama', $2)")
+            .bind(article_id)
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        // Re-analysed later
+        sqlx::query("UPDATE article_analysis SET analyzed_at = NOW() WHERE article_id = $1")
+            .bind(article_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        // Pruned along with its article
+        sqlx::query("DELETE FROM articles WHERE id = $1")
+            .bind(article_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let usage = get_usage(&pool, user.id, 50).await.unwrap().unwrap();
+        assert_eq!(usage.analyses, 2);
+    }
+}
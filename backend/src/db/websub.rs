@@ -0,0 +1,193 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A feed's subscription to its WebSub hub.
+#[derive(Debug, Clone)]
+pub struct WebSubSubscription {
+    pub feed_id: Uuid,
+    pub hub_url: String,
+    /// The URL the hub knows the feed by (its `rel="self"` link).
+    pub topic_url: String,
+    /// Shared secret the hub signs pushes with.
+    pub secret: String,
+    /// `pending`, `active` or `denied`.
+    pub state: String,
+}
+
+/// Get a feed's subscription, if it has one.
+pub async fn get_subscription(
+    pool: &PgPool,
+    feed_id: Uuid,
+) -> Result<Option<WebSubSubscription>, sqlx::Error> {
+    sqlx::query_as!(
+        WebSubSubscription,
+        r#"
+        SELECT feed_id, hub_url, topic_url, secret, state
+        FROM websub_subscriptions
+        WHERE feed_id = $1
+        "#,
+        feed_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Start a new subscription (replacing any previous one for the feed) in the
+/// `pending` state, waiting for the hub to verify it.
+pub async fn create_pending(
+    pool: &PgPool,
+    feed_id: Uuid,
+    hub_url: &str,
+    topic_url: &str,
+    secret: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO websub_subscriptions (feed_id, hub_url, topic_url, secret)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (feed_id) DO UPDATE SET
+            hub_url = EXCLUDED.hub_url,
+            topic_url = EXCLUDED.topic_url,
+            secret = EXCLUDED.secret,
+            state = 'pending',
+            lease_seconds = NULL,
+            expires_at = NULL,
+            requested_at = NOW(),
+            verified_at = NULL,
+            updated_at = NOW()
+        "#,
+        feed_id,
+        hub_url,
+        topic_url,
+        secret
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record that a subscription (or renewal) was requested from the hub again.
+/// Active subscriptions stay active until the hub verifies the renewal.
+pub async fn mark_requested(pool: &PgPool, feed_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE websub_subscriptions
+        SET requested_at = NOW(), updated_at = NOW()
+        WHERE feed_id = $1
+        "#,
+        feed_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Mark a subscription verified by the hub, with the lease it granted.
+pub async fn activate(pool: &PgPool, feed_id: Uuid, lease_seconds: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE websub_subscriptions
+        SET state = 'active',
+            lease_seconds = $2::int,
+            expires_at = NOW() + $2::int * INTERVAL '1 second',
+            verified_at = NOW(),
+            updated_at = NOW()
+        WHERE feed_id = $1
+        "#,
+        feed_id,
+        lease_seconds
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Mark a subscription refused by the hub.
+pub async fn mark_denied(pool: &PgPool, feed_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE websub_subscriptions
+        SET state = 'denied', expires_at = NULL, updated_at = NOW()
+        WHERE feed_id = $1
+        "#,
+        feed_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record that the hub pushed content for a feed.
+pub async fn record_push(pool: &PgPool, feed_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE websub_subscriptions
+        SET last_push_at = NOW(), updated_at = NOW()
+        WHERE feed_id = $1
+        "#,
+        feed_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether a feed currently receives pushes (verified, lease not expired).
+pub async fn is_active(pool: &PgPool, feed_id: Uuid) -> Result<bool, sqlx::Error> {
+    let active = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM websub_subscriptions
+            WHERE feed_id = $1 AND state = 'active' AND expires_at > NOW()
+        ) as "active!"
+        "#,
+        feed_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(active)
+}
+
+/// Subscriptions that need (re)requesting from their hub:
+/// - active ones expiring within a day (or half their lease, if shorter)
+/// - pending ones the hub never verified within an hour
+/// - denied ones, once a day
+///
+/// Feeds that are disabled, gone or no longer subscribed to are skipped.
+pub async fn list_due_for_renewal(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<WebSubSubscription>, sqlx::Error> {
+    sqlx::query_as!(
+        WebSubSubscription,
+        r#"
+        SELECT s.feed_id, s.hub_url, s.topic_url, s.secret, s.state
+        FROM websub_subscriptions s
+        INNER JOIN feeds f ON f.id = s.feed_id
+        WHERE NOT f.disabled
+          AND f.gone_at IS NULL
+          AND EXISTS (SELECT 1 FROM user_feeds uf WHERE uf.feed_id = f.id)
+          AND (
+              (s.state = 'active'
+                  AND s.expires_at < NOW() + LEAST(
+                      INTERVAL '1 day',
+                      COALESCE(s.lease_seconds, 0) * INTERVAL '1 second' / 2
+                  )
+                  AND s.requested_at < NOW() - INTERVAL '10 minutes')
+              OR (s.state = 'pending' AND s.requested_at < NOW() - INTERVAL '1 hour')
+              OR (s.state = 'denied' AND s.requested_at < NOW() - INTERVAL '1 day')
+          )
+        ORDER BY s.expires_at ASC NULLS FIRST
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -7,6 +7,7 @@ use serde::Serialize;
 
 /// Application error types
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum AppError {
     // Auth errors
     Unauthorized,
@@ -33,6 +34,9 @@ pub enum AppError {
     // Rate limiting
     RateLimited,
 
+    // Quota errors
+    LimitExceeded(String),
+
     // Generic internal error
     InternalError(String),
 }
@@ -107,6 +111,14 @@ impl IntoResponse for AppError {
                 None,
             ),
 
+            // 403 Forbidden (quota used up)
+            AppError::LimitExceeded(msg) => (
+                StatusCode::FORBIDDEN,
+                "limit_exceeded",
+                "Account limit reached",
+                Some(msg),
+            ),
+
             // 502 Bad Gateway (external services)
             AppError::ExternalServiceError(msg) => (
                 StatusCode::BAD_GATEWAY,
@@ -160,10 +172,10 @@ impl From<sqlx::Error> for AppError {
             sqlx::Error::RowNotFound => AppError::NotFound("Record not found".to_string()),
             sqlx::Error::Database(db_err) => {
                 // Check for unique constraint violations
-                if let Some(code) = db_err.code() {
-                    if code == "23505" {
-                        return AppError::AlreadyExists("Record already exists".to_string());
-                    }
+                if let Some(code) = db_err.code()
+                    && code == "23505"
+                {
+                    return AppError::AlreadyExists("Record already exists".to_string());
                 }
                 AppError::DatabaseError(db_err.to_string())
             }
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 use axum::Router;
 use sqlx::postgres::PgPoolOptions;
 use tokio::net::TcpListener;
@@ -9,6 +10,7 @@ use tower_http::trace::TraceLayer;
 
 
 mod auth;
+mod cli;
 mod config;
 mod db;
 mod routes;
@@ -17,11 +19,19 @@ mod errors;
 mod services;
 
 use config::Config;
+use services::fetcher::FeedFetcher;
+use services::host_limiter::HostLimiter;
+use services::identity;
+use services::sanitize;
+use services::refresh::RefreshThrottle;
 use services::scheduler::FeedScheduler;
 
 pub struct AppState {
    pub db: PgPool,
    pub config: Config,
+   pub fetcher: Arc<FeedFetcher>,
+   pub host_limiter: Arc<HostLimiter>,
+   pub refresh_throttle: RefreshThrottle,
 }
 
 
@@ -48,16 +58,47 @@ async fn main() {
     // 4. Run Migrations (Optional)
     sqlx::migrate!().run(&pool).await.unwrap();
 
+    // One-off data fixes, finished before anything fetches feeds
+    if let Some(rekeyed) = identity::rekey_generated_guids(&pool)
+        .await
+        .expect("Failed to re-key articles stored under generated guids")
+    {
+        tracing::info!("Re-keyed {} article(s) stored under generated guids", rekeyed);
+    }
+    if let Some(updated) = sanitize::resanitize_stored_html(&pool)
+        .await
+        .expect("Failed to sanitize stored articles")
+    {
+        tracing::info!("Sanitized {} stored article(s)", updated);
+    }
+
+    // Maintenance commands run and exit instead of starting the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&pool, &config, &args).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // 5. Start background feed scheduler
-    let scheduler = FeedScheduler::new(pool.clone());
+    let host_limiter = Arc::new(HostLimiter::new(
+        config.fetch_per_host_concurrency,
+        Duration::from_millis(config.fetch_per_host_delay_ms),
+    ));
+    let fetcher = Arc::new(FeedFetcher::new(pool.clone(), host_limiter.clone(), &config));
+    let scheduler = FeedScheduler::new(pool.clone(), fetcher.clone(), host_limiter.clone(), &config);
     tokio::spawn(async move {
         scheduler.run().await;
     });
-    tracing::info!("Feed scheduler started (15 min interval)");
+    tracing::info!("Feed scheduler started");
 
     let addr = format!("{}:{}", config.host, config.port);
     // 6. Create App State
-    let state = Arc::new(AppState { db: pool, config });
+    let refresh_throttle =
+        RefreshThrottle::new(Duration::from_secs(config.feed_refresh_cooldown_seconds));
+    let state = Arc::new(AppState { db: pool, config, fetcher, host_limiter, refresh_throttle });
     
     // 7. Build Application Router with CORS + TraceLayer + state
     let app = Router::new()
@@ -16,5 +16,31 @@ pub struct Article {
     pub content: Option<String>,
     pub published_at: Option<DateTime<Utc>>,
     pub guid: Option<String>,
+    /// ISO 639-1 language code, detected or declared by the feed.
+    pub language: Option<String>,
+    /// Words in the article's text (the extracted text once there is one).
+    pub word_count: Option<i32>,
+    /// Estimated minutes to read `word_count` words.
+    pub reading_time_minutes: Option<i32>,
+    /// Main content extracted from the article page (sanitized HTML).
+    pub extracted_content: Option<String>,
+    /// Plain text of `extracted_content`.
+    pub extracted_text: Option<String>,
+    /// When extraction was last attempted (set even if it found nothing).
+    pub extracted_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+/// A file attached to an article: an enclosure (podcast episode, video,
+/// image), a thumbnail, or the first image of the content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct ArticleMedia {
+    /// `enclosure`, `thumbnail` or `inline_image`.
+    pub kind: String,
+    pub url: String,
+    pub mime_type: Option<String>,
+    pub length_bytes: Option<i64>,
+    pub duration_seconds: Option<i32>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
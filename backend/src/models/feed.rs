@@ -14,7 +14,60 @@ pub struct Feed {
     pub description: Option<String>,
     pub topic_id: Option<Uuid>,
     pub is_curated: bool,
+    pub language: Option<String>,
+    pub icon_url: Option<String>,
+    /// When true the title was set by hand and is not overwritten by fetches.
+    pub title_locked: bool,
     pub last_fetched_at: Option<DateTime<Utc>>,
+    /// `ETag` header from the last successful fetch (sent as `If-None-Match`).
+    #[serde(skip_serializing)]
+    pub etag: Option<String>,
+    /// `Last-Modified` header from the last successful fetch (sent as `If-Modified-Since`).
+    #[serde(skip_serializing)]
+    pub last_modified: Option<String>,
+    /// Adaptive fetch interval computed from the feed's posting frequency.
+    pub fetch_interval_minutes: Option<i32>,
+    /// When the scheduler will next fetch this feed (`None` = due now).
+    pub next_fetch_at: Option<DateTime<Utc>>,
+    /// Failed fetches in a row; drives retry backoff and auto-disable.
+    pub consecutive_failures: i32,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Set after too many consecutive failures; disabled feeds are not fetched.
+    pub disabled: bool,
+    /// Set when the feed answered `410 Gone`; gone feeds are never fetched again.
+    pub gone_at: Option<DateTime<Utc>>,
+    /// How articles are identified: `auto`, `guid`, `link` or `hash`.
+    pub identity_strategy: String,
+    /// Download each new article's page and extract its full text.
+    pub fetch_full_content: bool,
+    /// The user a private (credentialed) feed belongs to; `None` for shared feeds.
+    pub owner_user_id: Option<Uuid>,
+    /// Encrypted request credentials (see `services::credentials`).
+    #[serde(skip)]
+    pub credentials: Option<Vec<u8>>,
+    /// How the feed is fetched: `feed` (a feed document) or `scrape` (an HTML
+    /// page read with `scrape_rules`).
+    pub kind: String,
+    /// CSS selectors for a scraped source (see `services::scrape`).
+    pub scrape_rules: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+/// One fetch attempt from a feed's fetch history.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct FetchLogEntry {
+    pub fetched_at: DateTime<Utc>,
+    /// Status of the final response (`None` when no response was received).
+    pub http_status: Option<i32>,
+    pub duration_ms: i32,
+    /// Size of the downloaded body (`None` for 304s and failed downloads).
+    pub bytes: Option<i64>,
+    pub new_articles: i32,
+    pub updated_articles: i32,
+    /// Kind of failure (`http`, `timeout`, `parse`, ...), `None` on success.
+    pub error_kind: Option<String>,
+    pub error_message: Option<String>,
+}
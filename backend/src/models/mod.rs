@@ -4,6 +4,4 @@ pub mod feed;
 pub mod article;
 
 pub use topic::Topic;
-pub use user::User;
-pub use feed::Feed;
-pub use article::Article;
\ No newline at end of file
+pub use feed::Feed;
\ No newline at end of file
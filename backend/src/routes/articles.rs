@@ -8,9 +8,11 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::auth::AuthUser;
-use crate::db::articles::{self, ArticleWithStatus};
+use crate::db::articles::{self, ArticleFilters, ArticleWithStatus};
+use crate::db::users;
 use crate::errors::{AppError, AppResult};
 use crate::models::article::Article;
+use crate::services::language;
 use crate::AppState;
 
 /// Query parameters for listing articles
@@ -20,12 +22,28 @@ pub struct ArticleQuery {
     pub topic: Option<String>,
     /// Filter to only saved articles
     pub saved: Option<bool>,
+    /// Show each story once even if several feeds carry it (default false)
+    pub collapse: Option<bool>,
+    /// Only articles in these languages (comma-separated ISO 639-1 codes), or
+    /// `all`; defaults to the user's preferred languages
+    pub lang: Option<String>,
+    /// Only articles taking at least this many minutes to read
+    pub min_minutes: Option<i32>,
+    /// Only articles taking at most this many minutes to read
+    pub max_minutes: Option<i32>,
     /// Page number (1-indexed, default 1)
     pub page: Option<i64>,
     /// Number of articles per page (default 20)
     pub per_page: Option<i64>,
 }
 
+/// Query parameters for article detail
+#[derive(Debug, Deserialize)]
+pub struct ArticleDetailQuery {
+    /// Extract the full text from the article's page if it hasn't been yet (default false)
+    pub full: Option<bool>,
+}
+
 /// Request body for marking an article as read/unread
 #[derive(Debug, Deserialize)]
 pub struct MarkReadRequest {
@@ -68,7 +86,10 @@ pub fn routes() -> Router<Arc<AppState>> {
 }
 
 /// GET /api/articles - List articles with optional filters
-/// Query params: ?topic=tech, ?saved=true, ?page=1&per_page=20
+/// Query params: ?topic=tech, ?saved=true, ?collapse=true, ?lang=en,de,
+/// ?min_minutes=10&max_minutes=30 (reading time), ?page=1&per_page=20
+/// Without `lang`, only articles in the user's preferred languages (and those
+/// whose language is unknown) are listed, if they have set any
 async fn list_articles(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -77,7 +98,24 @@ async fn list_articles(
     let page = query.page.unwrap_or(1).max(1);
     let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
     let offset = (page - 1) * per_page;
-    let saved_only = query.saved.unwrap_or(false);
+
+    let (languages, include_unknown_language) = match query.lang.as_deref().map(str::trim) {
+        Some("all") => (Vec::new(), false),
+        Some(lang) => (
+            language::normalize_all(lang.split(',')).map_err(AppError::ValidationError)?,
+            false,
+        ),
+        None => (users::get_preferred_languages(&state.db, auth_user.user_id).await?, true),
+    };
+    let filters = ArticleFilters {
+        topic_slug: query.topic.as_deref(),
+        saved_only: query.saved.unwrap_or(false),
+        collapse_duplicates: query.collapse.unwrap_or(false),
+        languages: (!languages.is_empty()).then_some(languages.as_slice()),
+        include_unknown_language,
+        min_minutes: query.min_minutes,
+        max_minutes: query.max_minutes,
+    };
 
     // Fetch one extra to determine if there are more pages
     let limit = per_page + 1;
@@ -85,8 +123,7 @@ async fn list_articles(
     let mut fetched_articles = articles::list_articles_for_user(
         &state.db,
         auth_user.user_id,
-        query.topic.as_deref(),
-        saved_only,
+        &filters,
         limit,
         offset,
     )
@@ -108,20 +145,37 @@ async fn list_articles(
 }
 
 /// GET /api/articles/:id - Get full article detail
+/// Only articles of feeds the user is subscribed to (private feeds' articles
+/// must not leak to other users); anything else is 404.
+/// With `?full=true`, the article page is downloaded and its main content
+/// extracted on first request; a failed extraction still returns the article
 async fn get_article(
     State(state): State<Arc<AppState>>,
-    _auth_user: AuthUser,
+    auth_user: AuthUser,
     Path(id): Path<Uuid>,
+    Query(query): Query<ArticleDetailQuery>,
 ) -> AppResult<Json<Article>> {
-    let article = articles::get_article(&state.db, id)
+    let mut article = articles::get_article(&state.db, auth_user.user_id, id)
         .await
         .map_err(AppError::from)?
         .ok_or_else(|| AppError::NotFound(format!("Article with id {} not found", id)))?;
 
+    if query.full.unwrap_or(false) && article.extracted_at.is_none() {
+        match state.fetcher.extract_article(article.id, &article.url).await {
+            Ok(_) => {
+                if let Some(updated) = articles::get_article(&state.db, auth_user.user_id, id).await.map_err(AppError::from)? {
+                    article = updated;
+                }
+            }
+            Err(e) => tracing::warn!("Full-content extraction failed for {}: {}", article.url, e),
+        }
+    }
+
     Ok(Json(article))
 }
 
 /// PATCH /api/articles/:id/read - Mark article as read/unread
+/// Also applies to copies of the same story in the user's other feeds
 async fn mark_read(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -1,22 +1,42 @@
 use axum::{
-    extract::{Path, State},
-    routing::{delete, get},
+    extract::{Path, Query, State},
+    routing::{delete, get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
 use uuid::Uuid;
 
 use crate::auth::AuthUser;
-use crate::db::feeds;
+use crate::db::feeds::{self, Subscription};
+use crate::db::fetch_log;
 use crate::errors::{AppError, AppResult};
+use crate::models::feed::FetchLogEntry;
 use crate::models::Feed;
+use crate::services::credentials::{CredentialError, FeedCredentials};
+use crate::services::discovery::{self, Discovery, DiscoveryError, FeedCandidate};
+use crate::services::fetcher::FetchError;
+use crate::services::refresh::{self, RefreshOutcome, RefreshScope};
+use crate::services::scrape::{ScrapeError, ScrapeRules, ScrapedPage};
 use crate::AppState;
 
 /// Request body for subscribing to a new feed
 #[derive(Debug, Deserialize)]
 pub struct SubscribeFeedRequest {
     pub url: String,
+    /// Credentials for a feed behind authentication; makes the feed private
+    pub credentials: Option<FeedCredentials>,
+    /// Selectors for scraping a page that has no feed; makes it a scraped source
+    pub scrape: Option<ScrapeRules>,
+}
+
+/// Request body for previewing a scraped source
+#[derive(Debug, Deserialize)]
+pub struct ScrapePreviewRequest {
+    pub url: String,
+    pub rules: ScrapeRules,
 }
 
 /// Response for subscribe endpoint
@@ -26,6 +46,30 @@ pub struct SubscribeResponse {
     pub is_new: bool, // true if we created the feed, false if it already existed
 }
 
+/// Response when a website advertises several feeds and the user must pick one
+#[derive(Debug, Serialize)]
+pub struct CandidatesResponse {
+    pub candidates: Vec<FeedCandidate>,
+}
+
+/// Result of a subscribe request: either a subscription, or feeds to choose from
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum SubscribeOutcome {
+    Subscribed(Box<SubscribeResponse>),
+    Candidates(CandidatesResponse),
+}
+
+/// Query parameters for a feed's fetch history
+#[derive(Debug, Deserialize)]
+pub struct FetchLogQuery {
+    pub limit: Option<i64>,
+}
+
+/// Default and maximum number of fetch log entries returned
+const DEFAULT_FETCH_LOG_LIMIT: i64 = 50;
+const MAX_FETCH_LOG_LIMIT: i64 = 200;
+
 /// Response for successful operations
 #[derive(Debug, Serialize)]
 pub struct SuccessResponse {
@@ -36,12 +80,19 @@ pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/feeds", get(list_feeds).post(subscribe_feed))
         .route("/feeds/:id", delete(unsubscribe_feed))
+        .route("/feeds/refresh", post(refresh_all_feeds))
+        .route("/feeds/scrape/preview", post(preview_scrape))
+        .route("/feeds/:id/refresh", post(refresh_feed))
+        .route("/feeds/:id/fetch-log", get(get_fetch_log))
 }
 
 /// GET /api/feeds - List user's subscribed feeds
 ///
 /// Requires authentication.
 /// Returns all feeds the authenticated user is subscribed to, ordered by title.
+/// Each feed includes its health (`consecutive_failures`, `last_error`,
+/// `last_error_at`, `last_success_at`, `disabled`, `gone_at`) so broken
+/// subscriptions show up.
 async fn list_feeds(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -55,26 +106,104 @@ async fn list_feeds(
 /// POST /api/feeds - Subscribe to a new RSS feed
 ///
 /// Requires authentication.
-/// Accepts a JSON body with the feed URL.
+/// Accepts a JSON body with the feed URL, which may also be a website URL.
+/// Unknown URLs go through feed autodiscovery: if the page advertises exactly one
+/// feed, the user is subscribed to it; if it advertises several, the candidates are
+/// returned instead and the client should resubmit with the chosen feed URL.
 /// If the feed already exists in the system, subscribes the user to it.
 /// If the feed is new, creates it (using URL as title initially) and subscribes the user.
+/// A feed that has never been fetched is fetched right away, waiting up to
+/// `FEED_REFRESH_TIMEOUT_SECONDS` so the user sees articles immediately.
 /// Returns the feed and whether it was newly created.
+///
+/// With `credentials` (`username`/`password`, `bearer_token` and/or `headers`)
+/// the URL must be the feed itself. The feed is private to the user: it is
+/// never shared with or found by other users subscribing to the same URL, and
+/// its credentials are stored encrypted. Subscribing again with new
+/// credentials replaces them.
+///
+/// With `scrape` (CSS selectors `item`, `title`, `link`, and optionally `date`
+/// and `summary`) the URL is an HTML page without a feed, and its articles are
+/// read with the selectors on every fetch. Like a feed with credentials, a
+/// scraped source belongs to the user who defined it; subscribing again with
+/// new selectors replaces them.
+///
+/// A `file://` URL subscribes to a feed file on the server, which must be
+/// inside `FEED_FILE_ROOT`.
+///
+/// Fails with `limit_exceeded` (403) if the user already has as many feeds as
+/// they are allowed (`MAX_FEEDS_PER_USER`, unless set for them individually).
 async fn subscribe_feed(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
     Json(payload): Json<SubscribeFeedRequest>,
-) -> AppResult<Json<SubscribeResponse>> {
+) -> AppResult<Json<SubscribeOutcome>> {
     // Validate URL is not empty
     let url = payload.url.trim();
     if url.is_empty() {
         return Err(AppError::ValidationError("URL cannot be empty".to_string()));
     }
 
+    let private_feed = match (&payload.credentials, &payload.scrape) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::ValidationError(
+                "Scraped sources can't have credentials".to_string(),
+            ));
+        }
+        (Some(credentials), None) => {
+            Some(add_private_feed(&state, auth_user.user_id, url, credentials).await?)
+        }
+        (None, Some(rules)) => Some(add_scraped_source(&state, auth_user.user_id, url, rules).await?),
+        (None, None) => None,
+    };
+    if let Some((feed, is_new)) = private_feed {
+        return subscribe_and_fetch(&state, auth_user.user_id, feed, is_new, true).await;
+    }
+
+    if url.starts_with("file:") {
+        let (feed, is_new) = add_file_feed(&state, url).await?;
+        let fetch_now = feed.last_fetched_at.is_none();
+        return subscribe_and_fetch(&state, auth_user.user_id, feed, is_new, fetch_now).await;
+    }
+
     // Check if feed with this URL already exists
-    let existing_feed = feeds::get_feed_by_url(&state.db, url)
+    let mut existing_feed = feeds::get_feed_by_url(&state.db, url)
         .await
         .map_err(AppError::from)?;
 
+    // Unknown URL: resolve it to a feed (it may be a website homepage)
+    let mut feed_url = url.to_string();
+    if existing_feed.is_none() {
+        match discovery::discover_feed(state.fetcher.client(), state.fetcher.url_guard(), url).await {
+            Ok(Discovery::Feed(resolved)) => feed_url = resolved,
+            Ok(Discovery::Candidates(candidates)) => {
+                return Ok(Json(SubscribeOutcome::Candidates(CandidatesResponse {
+                    candidates,
+                })));
+            }
+            Err(
+                e @ (DiscoveryError::InvalidUrl(_)
+                | DiscoveryError::Blocked(_)
+                | DiscoveryError::NoFeedFound),
+            ) => {
+                return Err(AppError::ValidationError(e.to_string()));
+            }
+            Err(
+                e @ (DiscoveryError::HttpError(_)
+                | DiscoveryError::TooManyRedirects
+                | DiscoveryError::ResponseTooLarge),
+            ) => {
+                return Err(AppError::ExternalServiceError(e.to_string()));
+            }
+        }
+
+        if feed_url != url {
+            existing_feed = feeds::get_feed_by_url(&state.db, &feed_url)
+                .await
+                .map_err(AppError::from)?;
+        }
+    }
+
     let (feed, is_new) = match existing_feed {
         Some(feed) => {
             // Feed already exists, just subscribe the user
@@ -84,11 +213,11 @@ async fn subscribe_feed(
             // Create a new feed (use URL as title initially, will be updated when fetched)
             let new_feed = feeds::create_feed(
                 &state.db,
-                url,    // Use URL as initial title
-                url,    // The actual URL
-                None,   // site_url - will be populated on fetch
-                None,   // description - will be populated on fetch
-                None,   // topic_id - user can categorize later
+                &feed_url, // Use URL as initial title
+                &feed_url, // The resolved feed URL
+                None,      // site_url - will be populated on fetch
+                None,      // description - will be populated on fetch
+                None,      // topic_id - user can categorize later
             )
             .await
             .map_err(AppError::from)?;
@@ -96,19 +225,148 @@ async fn subscribe_feed(
         }
     };
 
-    // Subscribe the user to the feed
-    feeds::subscribe_user_to_feed(&state.db, auth_user.user_id, feed.id)
+    let fetch_now = feed.last_fetched_at.is_none();
+    subscribe_and_fetch(&state, auth_user.user_id, feed, is_new, fetch_now).await
+}
+
+/// Create the user's private feed at `url`, or update its credentials.
+///
+/// # Returns
+/// The feed and whether it was newly created.
+async fn add_private_feed(
+    state: &AppState,
+    user_id: Uuid,
+    url: &str,
+    credentials: &FeedCredentials,
+) -> AppResult<(Feed, bool)> {
+    let url = check_url(state, url).await?;
+    credentials.to_headers().map_err(AppError::ValidationError)?;
+
+    let cipher = state
+        .fetcher
+        .credential_cipher()
+        .ok_or_else(|| AppError::ValidationError(CredentialError::NotConfigured.to_string()))?;
+
+    let is_new = feeds::get_private_feed(&state.db, user_id, url.as_str()).await?.is_none();
+    let feed =
+        feeds::upsert_private_feed(&state.db, user_id, url.as_str(), &cipher.encrypt(credentials))
+            .await?;
+
+    Ok((feed, is_new))
+}
+
+/// Create the user's scraped source at `url`, or update its selectors.
+///
+/// # Returns
+/// The feed and whether it was newly created.
+async fn add_scraped_source(
+    state: &AppState,
+    user_id: Uuid,
+    url: &str,
+    rules: &ScrapeRules,
+) -> AppResult<(Feed, bool)> {
+    let url = check_url(state, url).await?;
+    rules.validate().map_err(|e| AppError::ValidationError(e.to_string()))?;
+    let rules = serde_json::to_value(rules).expect("scrape rules always serialize");
+
+    let is_new = feeds::get_private_feed(&state.db, user_id, url.as_str()).await?.is_none();
+    let feed = feeds::upsert_scraped_feed(&state.db, user_id, url.as_str(), &rules).await?;
+
+    Ok((feed, is_new))
+}
+
+/// Find or create the feed for a `file://` URL.
+///
+/// # Returns
+/// The feed and whether it was newly created.
+async fn add_file_feed(state: &AppState, url: &str) -> AppResult<(Feed, bool)> {
+    let source = state
+        .fetcher
+        .file_source()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    // One answer for every failure, so it can't be used to probe the filesystem
+    let path = source.resolve(url).await.map_err(|_| {
+        AppError::ValidationError("Not a readable feed file inside FEED_FILE_ROOT".to_string())
+    })?;
+    let url = Url::from_file_path(&path)
+        .map_err(|_| AppError::ValidationError(format!("Invalid URL: {}", url)))?;
+
+    if let Some(feed) = feeds::get_feed_by_url(&state.db, url.as_str()).await? {
+        return Ok((feed, false));
+    }
+    let feed = feeds::create_file_feed(&state.db, url.as_str()).await?;
+
+    Ok((feed, true))
+}
+
+/// Parse a URL given by the user and check it's one we fetch from.
+async fn check_url(state: &AppState, url: &str) -> AppResult<Url> {
+    let url = Url::parse(url).map_err(|_| AppError::ValidationError(format!("Invalid URL: {}", url)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::ValidationError(format!("Invalid URL: {}", url)));
+    }
+    state
+        .fetcher
+        .url_guard()
+        .check_url_resolved(&url)
         .await
-        .map_err(AppError::from)?;
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    Ok(url)
+}
+
+/// Subscribe the user to a feed and, if `fetch_now`, fetch it rather than
+/// waiting for the next scheduler tick.
+async fn subscribe_and_fetch(
+    state: &Arc<AppState>,
+    user_id: Uuid,
+    feed: Feed,
+    is_new: bool,
+    fetch_now: bool,
+) -> AppResult<Json<SubscribeOutcome>> {
+    // Subscribe the user to the feed, within their feed limit
+    let subscription =
+        feeds::subscribe_user_to_feed(&state.db, user_id, feed.id, state.config.max_feeds_per_user)
+            .await
+            .map_err(AppError::from)?;
+    if let Subscription::LimitReached { limit } = subscription {
+        if is_new {
+            feeds::delete_unsubscribed_feed(&state.db, feed.id).await?;
+        }
+        return Err(AppError::LimitExceeded(format!(
+            "You can subscribe to at most {} feeds; unsubscribe from one first",
+            limit
+        )));
+    }
 
-    Ok(Json(SubscribeResponse { feed, is_new }))
+    let feed = if fetch_now {
+        let outcomes = refresh::refresh_feeds(
+            state.fetcher.clone(),
+            state.host_limiter.clone(),
+            vec![feed],
+            Duration::from_secs(state.config.feed_refresh_timeout_seconds),
+        )
+        .await;
+        let feed_id = outcomes[0].feed_id;
+        feeds::get_feed_by_id(&state.db, feed_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Feed with id {} not found", feed_id)))?
+    } else {
+        feed
+    };
+
+    Ok(Json(SubscribeOutcome::Subscribed(Box::new(SubscribeResponse {
+        feed,
+        is_new,
+    }))))
 }
 
 /// DELETE /api/feeds/:id - Unsubscribe from a feed
 ///
 /// Requires authentication.
 /// Removes the subscription link between the user and the feed.
-/// Does not delete the feed itself (other users may be subscribed).
+/// Does not delete the feed itself (other users may be subscribed), unless it is
+/// the user's private feed, which is deleted along with its credentials.
 async fn unsubscribe_feed(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -120,3 +378,120 @@ async fn unsubscribe_feed(
 
     Ok(Json(SuccessResponse { success: true }))
 }
+
+/// POST /api/feeds/scrape/preview - Preview a scraped source
+///
+/// Requires authentication.
+/// Fetches the page at `url` and returns the items `rules` would extract from
+/// it (and the items that would be skipped), without saving anything.
+async fn preview_scrape(
+    State(state): State<Arc<AppState>>,
+    _auth_user: AuthUser,
+    Json(payload): Json<ScrapePreviewRequest>,
+) -> AppResult<Json<ScrapedPage>> {
+    let url = check_url(&state, payload.url.trim()).await?;
+    payload
+        .rules
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    match state.fetcher.preview_scrape(url.as_str(), &payload.rules).await {
+        Ok(page) => Ok(Json(page)),
+        Err(e @ (FetchError::Blocked(_) | FetchError::Scrape(ScrapeError::NoItems))) => {
+            Err(AppError::ValidationError(e.to_string()))
+        }
+        Err(e) => Err(AppError::ExternalServiceError(e.to_string())),
+    }
+}
+
+/// POST /api/feeds/:id/refresh - Fetch one of the user's feeds now
+///
+/// Requires authentication; only feeds the user is subscribed to.
+/// Waits up to `FEED_REFRESH_TIMEOUT_SECONDS` for the fetch (status `pending`
+/// if it is still running or the scheduler is fetching it). Feeds fetched in the
+/// last minute are `skipped`; disabled feeds are not fetched (`disabled`).
+/// Each user may refresh a feed once per `FEED_REFRESH_COOLDOWN_SECONDS`.
+async fn refresh_feed(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<RefreshOutcome>> {
+    if !feeds::is_user_subscribed(&state.db, auth_user.user_id, id).await? {
+        return Err(AppError::NotFound(format!("Feed with id {} not found", id)));
+    }
+    let feed = feeds::get_feed_by_id(&state.db, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Feed with id {} not found", id)))?;
+
+    if !state
+        .refresh_throttle
+        .try_acquire(auth_user.user_id, RefreshScope::Feed(id))
+    {
+        return Err(AppError::RateLimited);
+    }
+
+    let mut outcomes = refresh::refresh_feeds(
+        state.fetcher.clone(),
+        state.host_limiter.clone(),
+        vec![feed],
+        Duration::from_secs(state.config.feed_refresh_timeout_seconds),
+    )
+    .await;
+
+    Ok(Json(outcomes.remove(0)))
+}
+
+/// POST /api/feeds/refresh - Fetch all of the user's feeds now
+///
+/// Requires authentication.
+/// Returns one outcome per subscribed feed, as for `POST /api/feeds/:id/refresh`,
+/// within the same overall wait. Each user may refresh all feeds once per
+/// `FEED_REFRESH_COOLDOWN_SECONDS`.
+async fn refresh_all_feeds(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> AppResult<Json<Vec<RefreshOutcome>>> {
+    if !state
+        .refresh_throttle
+        .try_acquire(auth_user.user_id, RefreshScope::All)
+    {
+        return Err(AppError::RateLimited);
+    }
+
+    let user_feeds = feeds::list_user_feeds(&state.db, auth_user.user_id).await?;
+    let outcomes = refresh::refresh_feeds(
+        state.fetcher.clone(),
+        state.host_limiter.clone(),
+        user_feeds,
+        Duration::from_secs(state.config.feed_refresh_timeout_seconds),
+    )
+    .await;
+
+    Ok(Json(outcomes))
+}
+
+/// GET /api/feeds/:id/fetch-log - Recent fetch history of a feed
+///
+/// Requires authentication; only feeds the user is subscribed to.
+/// Returns the most recent fetch attempts, newest first, with the HTTP status,
+/// duration, body size, new/updated article counts and, for failures, the
+/// error kind and message.
+/// Query params: ?limit=50 (max 200)
+async fn get_fetch_log(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<FetchLogQuery>,
+) -> AppResult<Json<Vec<FetchLogEntry>>> {
+    if !feeds::is_user_subscribed(&state.db, auth_user.user_id, id).await? {
+        return Err(AppError::NotFound(format!("Feed with id {} not found", id)));
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_FETCH_LOG_LIMIT)
+        .clamp(1, MAX_FETCH_LOG_LIMIT);
+    let entries = fetch_log::list_for_feed(&state.db, id, limit).await?;
+
+    Ok(Json(entries))
+}
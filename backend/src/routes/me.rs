@@ -0,0 +1,91 @@
+use axum::{extract::State, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::AuthUser;
+use crate::db::users;
+use crate::errors::{AppError, AppResult};
+use crate::services::language;
+use crate::AppState;
+
+/// Response for the usage endpoint
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    pub feeds: FeedUsage,
+    pub saved_articles: i64,
+    pub ai_analyses: i64,
+}
+
+/// Feeds subscribed to, out of the user's limit
+#[derive(Debug, Serialize)]
+pub struct FeedUsage {
+    pub used: i64,
+    pub allowed: i32,
+}
+
+/// The user's preferred article languages (request and response body)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LanguagesBody {
+    /// ISO 639-1 codes; empty shows articles in every language
+    pub languages: Vec<String>,
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/me/usage", get(get_usage))
+        .route("/me/languages", get(get_languages).put(set_languages))
+}
+
+/// GET /api/me/usage - What the user has used of their account
+///
+/// Requires authentication.
+/// Returns the feeds subscribed to against the user's limit
+/// (`MAX_FEEDS_PER_USER`, unless set for them individually), the number of
+/// saved articles, and the number of AI analyses run at their request.
+async fn get_usage(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> AppResult<Json<UsageResponse>> {
+    let usage = users::get_usage(&state.db, auth_user.user_id, state.config.max_feeds_per_user)
+        .await?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    Ok(Json(UsageResponse {
+        feeds: FeedUsage {
+            used: usage.feeds,
+            allowed: usage.max_feeds,
+        },
+        saved_articles: usage.saved_articles,
+        ai_analyses: usage.analyses,
+    }))
+}
+
+/// GET /api/me/languages - The user's preferred article languages
+///
+/// Requires authentication.
+async fn get_languages(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> AppResult<Json<LanguagesBody>> {
+    let languages = users::get_preferred_languages(&state.db, auth_user.user_id).await?;
+
+    Ok(Json(LanguagesBody { languages }))
+}
+
+/// PUT /api/me/languages - Set the user's preferred article languages
+///
+/// Requires authentication.
+/// Accepts language tags ("en", "pt-BR"), stored as ISO 639-1 codes. The
+/// article list then only shows articles in these languages, plus those whose
+/// language couldn't be detected; an empty list shows every language.
+async fn set_languages(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(payload): Json<LanguagesBody>,
+) -> AppResult<Json<LanguagesBody>> {
+    let languages = language::normalize_all(payload.languages.iter().map(String::as_str))
+        .map_err(AppError::ValidationError)?;
+    users::set_preferred_languages(&state.db, auth_user.user_id, &languages).await?;
+
+    Ok(Json(LanguagesBody { languages }))
+}
@@ -3,6 +3,8 @@ mod auth;
 mod topics;
 mod feeds;
 mod articles;
+mod websub;
+mod me;
 
 use axum::Router;
 use std::sync::Arc;
@@ -17,5 +19,7 @@ pub fn create_routes() -> Router<Arc<AppState>> {
                 .merge(topics::routes())
                 .merge(feeds::routes())
                 .merge(articles::routes())
+                .merge(websub::routes())
+                .merge(me::routes())
         )
 }
@@ -0,0 +1,93 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::{feeds, websub};
+use crate::errors::{AppError, AppResult};
+use crate::services::fetcher::FetchError;
+use crate::services::websub::{verify_intent, verify_signature, Verification, VerificationRequest};
+use crate::AppState;
+
+/// Create WebSub callback routes (called by hubs, so unauthenticated)
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/websub/:feed_id", get(verify).post(receive))
+}
+
+/// GET /api/websub/:feed_id - Hub intent verification
+/// Echoes the challenge for subscriptions Herald requested; anything else is 404
+async fn verify(
+    State(state): State<Arc<AppState>>,
+    Path(feed_id): Path<Uuid>,
+    Query(request): Query<VerificationRequest>,
+) -> AppResult<String> {
+    let subscription = websub::get_subscription(&state.db, feed_id).await?;
+
+    match verify_intent(subscription.as_ref(), &request) {
+        Verification::Confirm {
+            challenge,
+            lease_seconds,
+        } => {
+            websub::activate(&state.db, feed_id, lease_seconds).await?;
+            tracing::info!(feed_id = %feed_id, lease_seconds, "WebSub subscription verified");
+            Ok(challenge)
+        }
+        Verification::Denied => {
+            websub::mark_denied(&state.db, feed_id).await?;
+            tracing::warn!(feed_id = %feed_id, "WebSub hub denied subscription");
+            Ok(String::new())
+        }
+        Verification::Reject => Err(AppError::NotFound(format!(
+            "No matching WebSub subscription for feed {}",
+            feed_id
+        ))),
+    }
+}
+
+/// POST /api/websub/:feed_id - Feed content pushed by the hub
+/// Pushes with a missing or wrong signature are acknowledged but ignored (as
+/// the WebSub spec requires); valid ones are ingested like a fetched feed
+async fn receive(
+    State(state): State<Arc<AppState>>,
+    Path(feed_id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> AppResult<StatusCode> {
+    let subscription = websub::get_subscription(&state.db, feed_id)
+        .await?
+        .filter(|s| s.state != "denied")
+        .ok_or_else(|| AppError::NotFound(format!("No WebSub subscription for feed {}", feed_id)))?;
+
+    let signature = headers.get("x-hub-signature").and_then(|v| v.to_str().ok());
+    if !verify_signature(&subscription.secret, signature, &body) {
+        tracing::warn!(feed_id = %feed_id, "Ignoring WebSub push with invalid signature");
+        return Ok(StatusCode::ACCEPTED);
+    }
+
+    let feed = feeds::get_feed_by_id(&state.db, feed_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Feed with id {} not found", feed_id)))?;
+
+    websub::record_push(&state.db, feed_id).await?;
+
+    let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    match state.fetcher.ingest_push(&feed, content_type, &body).await {
+        Ok(result) => tracing::info!(
+            feed_id = %feed_id,
+            new_articles = result.new_articles,
+            updated_articles = result.updated_articles,
+            errors = result.errors.len(),
+            "WebSub push ingested"
+        ),
+        // Let the hub retry pushes we failed to store
+        Err(FetchError::DatabaseError(e)) => return Err(AppError::from(e)),
+        Err(e) => tracing::warn!(feed_id = %feed_id, error = %e, "Ignoring unusable WebSub push"),
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
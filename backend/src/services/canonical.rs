@@ -0,0 +1,202 @@
+//! Canonical article URLs.
+//!
+//! The same story often reaches us through several feeds with slightly
+//! different links (tracking parameters, `www.`, `http` vs `https`, fragments).
+//! Articles are grouped by a normalized form of their URL so copies can be
+//! collapsed in listings and read state shared between them.
+//!
+//! An entry's canonical URL comes from the feed first; when the article page
+//! is downloaded for extraction, the page's own `<link rel="canonical">`
+//! replaces it.
+
+use scraper::{Html, Selector};
+use url::Url;
+
+/// Query parameters that only track where a click came from.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "ref",
+    "ref_src",
+    "ref_url",
+    "cmpid",
+    "ocid",
+    "smid",
+    "taid",
+    "mbid",
+];
+
+/// Normalize an article URL into its canonical form.
+///
+/// - the scheme becomes `https` and the host is lowercased without `www.`
+/// - default ports, fragments and tracking parameters (`utm_*`, `fbclid`, ...) are dropped
+/// - remaining query parameters are sorted
+/// - a trailing slash is removed from non-root paths
+///
+/// Returns `None` for anything that isn't an absolute http(s) URL.
+pub fn canonicalize(raw: &str) -> Option<String> {
+    let mut url = Url::parse(raw.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+
+    url.set_scheme("https").ok()?;
+    url.set_host(Some(&host)).ok()?;
+    url.set_port(None).ok()?;
+    url.set_fragment(None);
+
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    params.sort();
+
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    let path = url.path();
+    if path.len() > 1 && path.ends_with('/') {
+        let trimmed = path.trim_end_matches('/').to_string();
+        url.set_path(&trimmed);
+    }
+
+    Some(url.to_string())
+}
+
+/// Pick the URL an entry should be grouped by: its `rel="canonical"` link if
+/// the feed provides one, otherwise its main link.
+pub fn canonical_for_entry(links: &[feed_rs::model::Link], link: &str) -> Option<String> {
+    links
+        .iter()
+        .find(|l| l.rel.as_deref() == Some("canonical"))
+        .and_then(|l| canonicalize(&l.href))
+        .or_else(|| canonicalize(link))
+}
+
+/// The canonical URL an article page declares in its `<link rel="canonical">`,
+/// resolved against the URL the page was served from.
+pub fn canonical_for_page(page: &str, page_url: &Url) -> Option<String> {
+    let document = Html::parse_document(page);
+    let links = Selector::parse("link[rel][href]").expect("valid selector");
+
+    document
+        .select(&links)
+        .find(|link| {
+            link.value()
+                .attr("rel")
+                .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("canonical")))
+        })
+        .and_then(|link| page_url.join(link.value().attr("href")?.trim()).ok())
+        .and_then(|url| canonicalize(url.as_str()))
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_tracking_params_and_fragment() {
+        assert_eq!(
+            canonicalize("https://apnews.com/article/abc?utm_source=rss&utm_medium=feed#comments")
+                .as_deref(),
+            Some("https://apnews.com/article/abc")
+        );
+        assert_eq!(
+            canonicalize("https://example.com/a?id=7&fbclid=xyz&page=2").as_deref(),
+            Some("https://example.com/a?id=7&page=2")
+        );
+    }
+
+    #[test]
+    fn test_normalizes_host_scheme_and_path() {
+        let expected = Some("https://arstechnica.com/science/2024/01/story");
+        assert_eq!(
+            canonicalize("http://WWW.ArsTechnica.com:80/science/2024/01/story/").as_deref(),
+            expected
+        );
+        assert_eq!(
+            canonicalize("https://arstechnica.com/science/2024/01/story").as_deref(),
+            expected
+        );
+        assert_eq!(
+            canonicalize("https://example.com/").as_deref(),
+            Some("https://example.com/")
+        );
+    }
+
+    #[test]
+    fn test_sorts_remaining_params() {
+        assert_eq!(
+            canonicalize("https://example.com/?b=2&a=1"),
+            canonicalize("https://example.com/?a=1&b=2")
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_http() {
+        assert_eq!(canonicalize("mailto:someone@example.com"), None);
+        assert_eq!(canonicalize("/relative/path"), None);
+    }
+
+    #[test]
+    fn test_entry_prefers_canonical_link() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>t</title><id>urn:t</id><updated>2024-01-01T00:00:00Z</updated>
+  <entry>
+    <title>e</title><id>urn:e</id><updated>2024-01-01T00:00:00Z</updated>
+    <link rel="alternate" href="https://feeds.example.com/click?u=1"/>
+    <link rel="canonical" href="https://www.example.com/story?utm_source=feed"/>
+  </entry>
+</feed>"#;
+        let parsed = feed_rs::parser::parse(xml.as_bytes()).unwrap();
+        let entry = &parsed.entries[0];
+
+        assert_eq!(
+            canonical_for_entry(&entry.links, "https://feeds.example.com/click?u=1").as_deref(),
+            Some("https://example.com/story")
+        );
+    }
+
+    #[test]
+    fn test_page_canonical_link() {
+        let page_url = Url::parse("https://feeds.example.com/click?u=1").unwrap();
+        let page = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="Canonical" href="https://www.example.com/story?utm_source=feed">
+            </head><body></body></html>"#;
+        assert_eq!(
+            canonical_for_page(page, &page_url).as_deref(),
+            Some("https://example.com/story")
+        );
+
+        let relative = r#"<html><head><link rel="canonical" href="/2024/story"></head></html>"#;
+        assert_eq!(
+            canonical_for_page(relative, &page_url).as_deref(),
+            Some("https://feeds.example.com/2024/story")
+        );
+
+        let without = r#"<html><head><title>Story</title></head></html>"#;
+        assert_eq!(canonical_for_page(without, &page_url), None);
+    }
+}
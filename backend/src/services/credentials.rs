@@ -0,0 +1,230 @@
+//! Credentials for private feeds (HTTP Basic auth, bearer tokens, custom
+//! headers), encrypted at rest.
+//!
+//! Credentials are stored as AES-256-GCM ciphertext under the key in
+//! `FEED_CREDENTIALS_KEY`; without a key, feeds with credentials can't be added
+//! or fetched.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Length of the AES-GCM nonce stored in front of the ciphertext.
+const NONCE_BYTES: usize = 12;
+
+/// Headers that can't be set as custom headers: the HTTP client and the
+/// fetcher manage them.
+const RESERVED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    "if-none-match",
+    "if-modified-since",
+];
+
+/// Request credentials for a private feed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeedCredentials {
+    /// HTTP Basic auth user name (with `password`).
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sent as `Authorization: Bearer <token>`.
+    pub bearer_token: Option<String>,
+    /// Extra request headers (e.g. `X-Api-Key`).
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl FeedCredentials {
+    /// The request headers for these credentials.
+    ///
+    /// # Errors
+    /// A user-facing message if the credentials are empty, mix Basic auth with
+    /// a bearer token, or contain a header that can't be sent.
+    pub fn to_headers(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+
+        let authorization = match (&self.username, &self.bearer_token) {
+            (Some(_), Some(_)) => {
+                return Err("Use either a username and password or a bearer token, not both".to_string());
+            }
+            (Some(username), None) => {
+                let password = self.password.as_deref().unwrap_or_default();
+                Some(format!("Basic {}", BASE64.encode(format!("{}:{}", username, password))))
+            }
+            (None, Some(token)) => Some(format!("Bearer {}", token.trim())),
+            (None, None) if self.password.is_some() => {
+                return Err("A password needs a username".to_string());
+            }
+            (None, None) => None,
+        };
+        if let Some(authorization) = authorization {
+            let mut value = HeaderValue::from_str(&authorization)
+                .map_err(|_| "Credentials contain invalid characters".to_string())?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| format!("Invalid header name '{}'", name))?;
+            if RESERVED_HEADERS.contains(&header_name.as_str()) {
+                return Err(format!("Header '{}' can't be set", name));
+            }
+            if header_name == AUTHORIZATION && headers.contains_key(AUTHORIZATION) {
+                return Err("Authorization is already set by the credentials".to_string());
+            }
+            let mut header_value = HeaderValue::from_str(value.trim())
+                .map_err(|_| format!("Invalid value for header '{}'", name))?;
+            header_value.set_sensitive(true);
+            headers.insert(header_name, header_value);
+        }
+
+        if headers.is_empty() {
+            return Err("Credentials are empty".to_string());
+        }
+
+        Ok(headers)
+    }
+}
+
+/// Errors from encrypting or decrypting credentials.
+#[derive(Debug)]
+pub enum CredentialError {
+    /// No `FEED_CREDENTIALS_KEY` is configured.
+    NotConfigured,
+    /// The stored ciphertext couldn't be decrypted (wrong key or corrupted).
+    Undecryptable,
+}
+
+impl std::fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialError::NotConfigured => {
+                write!(f, "Feed credentials are not enabled (FEED_CREDENTIALS_KEY is not set)")
+            }
+            CredentialError::Undecryptable => write!(f, "Stored feed credentials can't be decrypted"),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {}
+
+/// Encrypts and decrypts stored feed credentials.
+pub struct CredentialCipher {
+    cipher: Aes256Gcm,
+}
+
+impl CredentialCipher {
+    /// Build a cipher from a base64-encoded 32-byte key
+    /// (e.g. from `openssl rand -base64 32`).
+    pub fn from_base64_key(key: &str) -> Result<Self, String> {
+        let key = BASE64
+            .decode(key.trim())
+            .map_err(|_| "Credentials key must be base64".to_string())?;
+        if key.len() != 32 {
+            return Err(format!("Credentials key must be 32 bytes, got {}", key.len()));
+        }
+
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        })
+    }
+
+    /// Encrypt credentials for storage: a random nonce followed by the ciphertext.
+    pub fn encrypt(&self, credentials: &FeedCredentials) -> Vec<u8> {
+        let plaintext = serde_json::to_vec(credentials).expect("credentials always serialize");
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .expect("AES-GCM encryption of in-memory data can't fail");
+
+        let mut stored = nonce.to_vec();
+        stored.extend(ciphertext);
+        stored
+    }
+
+    /// Decrypt credentials stored by [`CredentialCipher::encrypt`].
+    pub fn decrypt(&self, stored: &[u8]) -> Result<FeedCredentials, CredentialError> {
+        if stored.len() < NONCE_BYTES {
+            return Err(CredentialError::Undecryptable);
+        }
+        let (nonce, ciphertext) = stored.split_at(NONCE_BYTES);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| CredentialError::Undecryptable)?;
+
+        serde_json::from_slice(&plaintext).map_err(|_| CredentialError::Undecryptable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let cipher = CredentialCipher::from_base64_key(KEY).unwrap();
+        let credentials = FeedCredentials {
+            bearer_token: Some("ghp_secret".to_string()),
+            headers: BTreeMap::from([("X-Api-Key".to_string(), "k".to_string())]),
+            ..Default::default()
+        };
+
+        let stored = cipher.encrypt(&credentials);
+        assert!(!String::from_utf8_lossy(&stored).contains("ghp_secret"));
+        assert_ne!(stored, cipher.encrypt(&credentials), "nonces must differ");
+        assert_eq!(cipher.decrypt(&stored).unwrap(), credentials);
+
+        let mut tampered = stored.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt(&tampered).is_err());
+
+        let other = CredentialCipher::from_base64_key(&BASE64.encode([7u8; 32])).unwrap();
+        assert!(other.decrypt(&stored).is_err());
+
+        assert!(CredentialCipher::from_base64_key("c2hvcnQ=").is_err());
+    }
+
+    #[test]
+    fn test_credential_headers() {
+        let basic = FeedCredentials {
+            username: Some("alice".to_string()),
+            password: Some("s3cret".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            basic.to_headers().unwrap()[AUTHORIZATION],
+            "Basic YWxpY2U6czNjcmV0"
+        );
+
+        let custom = FeedCredentials {
+            headers: BTreeMap::from([("X-Api-Key".to_string(), " abc ".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(custom.to_headers().unwrap()["x-api-key"], "abc");
+
+        let both = FeedCredentials {
+            username: Some("alice".to_string()),
+            bearer_token: Some("t".to_string()),
+            ..Default::default()
+        };
+        assert!(both.to_headers().is_err());
+
+        let reserved = FeedCredentials {
+            headers: BTreeMap::from([("Host".to_string(), "evil.example".to_string())]),
+            ..Default::default()
+        };
+        assert!(reserved.to_headers().is_err());
+        assert!(FeedCredentials::default().to_headers().is_err());
+    }
+}
@@ -0,0 +1,550 @@
+//! Character set detection and lenient parsing of malformed feeds.
+//!
+//! Feed documents are decoded to UTF-8 before parsing. The encoding comes from
+//! the byte order mark, then the HTTP `charset`, then the XML declaration,
+//! falling back to UTF-8 and finally windows-1252 (the usual culprit behind
+//! "UTF-8" feeds that aren't). Control characters are dropped on the way.
+//!
+//! Documents that still don't parse get one repair pass for the breakage seen
+//! most in the wild: unescaped `&` and `<`, and truncated documents. Every
+//! fix applied is reported so it can be recorded with the fetch.
+
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use std::ops::Range;
+
+/// How far into a document the XML declaration is looked for.
+const DECLARATION_SCAN_BYTES: usize = 1024;
+
+/// A feed document decoded to UTF-8.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded {
+    pub text: String,
+    /// Fixes applied while decoding (e.g. a wrong declared charset).
+    pub repairs: Vec<String>,
+}
+
+/// A successfully parsed document, with the text it was parsed from.
+#[derive(Debug)]
+pub struct Parsed<T> {
+    pub feed: T,
+    /// The decoded (and possibly repaired) document.
+    pub document: String,
+    /// Fixes applied while decoding and repairing.
+    pub repairs: Vec<String>,
+}
+
+/// Decode, parse and, if the document doesn't parse as served, repair and
+/// parse it again.
+///
+/// # Arguments
+/// * `bytes` - The raw document
+/// * `content_type` - The response's `Content-Type`, for its `charset`
+/// * `parse` - The parser, run on the UTF-8 document
+///
+/// # Returns
+/// The parsed document, or the parser's error for the unrepaired document
+/// if repairing didn't help.
+pub fn parse_lenient<T, E>(
+    bytes: &[u8],
+    content_type: Option<&str>,
+    parse: impl Fn(&[u8]) -> Result<T, E>,
+) -> Result<Parsed<T>, E> {
+    let Decoded { text, mut repairs } = decode(bytes, content_type);
+
+    let error = match parse(text.as_bytes()) {
+        Ok(feed) => {
+            return Ok(Parsed {
+                feed,
+                document: text,
+                repairs,
+            });
+        }
+        Err(e) => e,
+    };
+
+    let Some(repaired) = repair_xml(&text) else {
+        return Err(error);
+    };
+    match parse(repaired.text.as_bytes()) {
+        Ok(feed) => {
+            repairs.extend(repaired.repairs);
+            Ok(Parsed {
+                feed,
+                document: repaired.text,
+                repairs,
+            })
+        }
+        Err(_) => Err(error),
+    }
+}
+
+/// Decode a feed document to UTF-8.
+///
+/// The XML declaration of the result (if any) is rewritten to say UTF-8 so
+/// the parser doesn't decode it a second time, and characters XML doesn't
+/// allow are removed.
+pub fn decode(bytes: &[u8], content_type: Option<&str>) -> Decoded {
+    let mut repairs = Vec::new();
+
+    let (text, encoding) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => {
+            let (text, had_errors) =
+                encoding.decode_without_bom_handling(&bytes[bom_length..]);
+            if had_errors {
+                repairs.push(malformed_note(encoding));
+            }
+            (text.into_owned(), encoding)
+        }
+        None => decode_without_bom(bytes, content_type, &mut repairs),
+    };
+
+    // Parsers pass control characters through, and the database refuses NUL
+    let mut text = declare_utf8(text, encoding);
+    let invalid = text.chars().filter(|c| !is_xml_char(*c)).count();
+    if invalid > 0 {
+        text.retain(is_xml_char);
+        repairs.push(format!("Removed {} invalid characters", invalid));
+    }
+
+    Decoded { text, repairs }
+}
+
+/// Decode a document without a byte order mark using the first usable of the
+/// HTTP charset, the declared encoding, UTF-8 and windows-1252.
+fn decode_without_bom(
+    bytes: &[u8],
+    content_type: Option<&str>,
+    repairs: &mut Vec<String>,
+) -> (String, &'static Encoding) {
+    let mut candidates = Vec::new();
+
+    if let Some(label) = content_type.and_then(charset_param) {
+        match Encoding::for_label(label.as_bytes()) {
+            Some(encoding) => candidates.push(encoding),
+            None => repairs.push(format!("Ignored unknown HTTP charset '{}'", label)),
+        }
+    }
+    if let Some(range) = declared_encoding(bytes) {
+        let label = String::from_utf8_lossy(&bytes[range]);
+        match Encoding::for_label(label.as_bytes()) {
+            // A declaration readable as ASCII means the document isn't UTF-16
+            Some(encoding) if encoding == UTF_16LE || encoding == UTF_16BE => {}
+            Some(encoding) => candidates.push(encoding),
+            None => repairs.push(format!("Ignored unknown declared encoding '{}'", label)),
+        }
+    }
+
+    let declared = candidates.first().copied();
+    for encoding in candidates.into_iter().chain([UTF_8]) {
+        if encoding == UTF_8 {
+            // Only trust UTF-8 if the bytes actually are UTF-8
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                return (text.to_string(), UTF_8);
+            }
+            continue;
+        }
+
+        let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+        if had_errors {
+            repairs.push(malformed_note(encoding));
+        }
+        return (text.into_owned(), encoding);
+    }
+
+    let (text, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+    repairs.push(match declared {
+        Some(declared) => format!(
+            "Decoded as windows-1252: the document is not valid {}",
+            declared.name()
+        ),
+        None => "Decoded as windows-1252: the document is not valid UTF-8".to_string(),
+    });
+    (text.into_owned(), WINDOWS_1252)
+}
+
+fn malformed_note(encoding: &'static Encoding) -> String {
+    format!("Replaced malformed {} byte sequences", encoding.name())
+}
+
+/// The `charset` parameter of a `Content-Type`.
+fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+            .filter(|value| !value.is_empty())
+    })
+}
+
+/// The byte range of the `encoding` value in a document's XML declaration.
+///
+/// Works on raw bytes of any ASCII-compatible encoding as well as on decoded
+/// text.
+fn declared_encoding(bytes: &[u8]) -> Option<Range<usize>> {
+    let head = &bytes[..bytes.len().min(DECLARATION_SCAN_BYTES)];
+    let start = head.iter().position(|b| !b.is_ascii_whitespace())?;
+    if !head[start..].starts_with(b"<?xml") {
+        return None;
+    }
+    let end = start + find(&head[start..], b"?>")?;
+    let declaration = &head[start..end];
+
+    let attribute = find(declaration, b"encoding")?;
+    let mut i = attribute + b"encoding".len();
+    while declaration.get(i).is_some_and(u8::is_ascii_whitespace) {
+        i += 1;
+    }
+    if declaration.get(i) != Some(&b'=') {
+        return None;
+    }
+    i += 1;
+    while declaration.get(i).is_some_and(u8::is_ascii_whitespace) {
+        i += 1;
+    }
+    let quote = *declaration.get(i).filter(|q| matches!(q, b'"' | b'\''))?;
+    let value_start = i + 1;
+    let value_length = declaration[value_start..].iter().position(|b| *b == quote)?;
+
+    Some(start + value_start..start + value_start + value_length)
+}
+
+/// Point a decoded document's encoding declaration at UTF-8.
+fn declare_utf8(mut text: String, encoding: &'static Encoding) -> String {
+    if let Some(range) = declared_encoding(text.as_bytes())
+        && (encoding != UTF_8 || !text[range.clone()].eq_ignore_ascii_case("utf-8"))
+    {
+        text.replace_range(range, "UTF-8");
+    }
+    text
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Fix the common ways XML feeds are broken.
+///
+/// # Returns
+/// The repaired document and what was fixed, or `None` if the document isn't
+/// XML or nothing needed fixing.
+pub fn repair_xml(text: &str) -> Option<Decoded> {
+    if !text.trim_start().starts_with('<') {
+        return None;
+    }
+
+    let mut repairs = Vec::new();
+    let (text, markup) = repair_markup(text);
+    if markup.escaped_ampersands > 0 {
+        repairs.push(format!(
+            "Escaped {} unescaped '&' characters",
+            markup.escaped_ampersands
+        ));
+    }
+    if markup.escaped_brackets > 0 {
+        repairs.push(format!(
+            "Escaped {} unescaped '<' characters",
+            markup.escaped_brackets
+        ));
+    }
+    if markup.truncated || markup.closed_elements > 0 {
+        repairs.push(format!(
+            "Completed a truncated document (closed {} open elements)",
+            markup.closed_elements
+        ));
+    }
+
+    (!repairs.is_empty()).then_some(Decoded { text, repairs })
+}
+
+/// Characters allowed in an XML 1.0 document.
+fn is_xml_char(c: char) -> bool {
+    matches!(
+        c,
+        '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..
+    )
+}
+
+/// What [`repair_markup`] changed.
+#[derive(Debug, Default, PartialEq)]
+struct MarkupRepairs {
+    escaped_ampersands: usize,
+    escaped_brackets: usize,
+    /// The document ended inside a tag, comment or CDATA section.
+    truncated: bool,
+    closed_elements: usize,
+}
+
+/// Escape stray `&` and `<` in text and complete a truncated document,
+/// leaving comments, CDATA sections and processing instructions alone.
+fn repair_markup(text: &str) -> (String, MarkupRepairs) {
+    let mut out = String::with_capacity(text.len() + 64);
+    let mut repairs = MarkupRepairs::default();
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = text;
+
+    while let Some(i) = rest.find(['<', '&']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if rest.starts_with('&') {
+            if is_reference(rest) {
+                out.push('&');
+            } else {
+                out.push_str("&amp;");
+                repairs.escaped_ampersands += 1;
+            }
+            rest = &rest[1..];
+            continue;
+        }
+
+        let (opener, terminator) = if rest.starts_with("<![CDATA[") {
+            ("<![CDATA[", "]]>")
+        } else if rest.starts_with("<!--") {
+            ("<!--", "-->")
+        } else if rest.starts_with("<?") {
+            ("<?", "?>")
+        } else if rest[1..].starts_with(|c: char| c == '/' || c == '!' || is_name_start(c)) {
+            ("<", ">")
+        } else {
+            out.push_str("&lt;");
+            repairs.escaped_brackets += 1;
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = if terminator == ">" {
+            tag_end(rest)
+        } else {
+            rest[opener.len()..]
+                .find(terminator)
+                .map(|end| opener.len() + end + terminator.len())
+        };
+        let Some(end) = end else {
+            // The document stops part-way through this markup
+            repairs.truncated = true;
+            if terminator != ">" {
+                out.push_str(rest);
+                out.push_str(terminator);
+            }
+            rest = "";
+            break;
+        };
+
+        let markup = &rest[..end];
+        if opener == "<" {
+            track_element(markup, &mut open);
+        }
+        out.push_str(markup);
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+
+    repairs.closed_elements = open.len();
+    for name in open.iter().rev() {
+        out.push_str("</");
+        out.push_str(name);
+        out.push('>');
+    }
+
+    (out, repairs)
+}
+
+/// Whether `text` (starting at `&`) starts with an entity or character reference.
+fn is_reference(text: &str) -> bool {
+    let Some(end) = text.find(';') else {
+        return false;
+    };
+    let name = &text[1..end];
+
+    if let Some(hex) = name.strip_prefix("#x") {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        !decimal.is_empty() && decimal.chars().all(|c| c.is_ascii_digit())
+    } else {
+        name.starts_with(is_name_start) && name.chars().all(is_name_char)
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == ':'
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start(c) || c.is_numeric() || matches!(c, '-' | '.')
+}
+
+/// The length of the tag at the start of `text`, up to and including its `>`
+/// (which may not appear in quoted attribute values).
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Update the stack of open elements for a start, end or empty tag.
+fn track_element<'a>(tag: &'a str, open: &mut Vec<&'a str>) {
+    if tag.starts_with("<!") {
+        return;
+    }
+
+    if let Some(closing) = tag.strip_prefix("</") {
+        let name = element_name(closing);
+        // Close anything left open inside the element (if it is open at all)
+        if let Some(position) = open.iter().rposition(|open| *open == name) {
+            open.truncate(position);
+        }
+    } else if !tag.ends_with("/>") {
+        open.push(element_name(&tag[1..]));
+    }
+}
+
+fn element_name(tag: &str) -> &str {
+    let end = tag.find(|c: char| !is_name_char(c)).unwrap_or(tag.len());
+    &tag[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Result<feed_rs::model::Feed, feed_rs::parser::ParseFeedError> {
+        feed_rs::parser::parse(bytes)
+    }
+
+    fn rss(items: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0"><channel><title>Test</title>{}</channel></rss>"#,
+            items
+        )
+    }
+
+    #[test]
+    fn test_decodes_http_charset_and_declaration() {
+        let latin1 = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><rss version=\"2.0\"><channel><title>Caf\xe9</title></channel></rss>";
+
+        let decoded = decode(latin1, None);
+        assert!(decoded.text.contains("Café"));
+        assert!(decoded.text.contains(r#"encoding="UTF-8""#));
+        assert!(decoded.repairs.is_empty());
+        assert_eq!(parse(decoded.text.as_bytes()).unwrap().title.unwrap().content, "Café");
+
+        // The HTTP charset wins over the declaration
+        let koi8 = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><t>\xf0\xd2\xc9\xd7\xc5\xd4</t>";
+        let decoded = decode(koi8, Some("application/rss+xml; charset=\"KOI8-R\""));
+        assert!(decoded.text.contains("<t>Привет</t>"));
+    }
+
+    #[test]
+    fn test_bom_wins() {
+        let mut utf16 = vec![0xff, 0xfe];
+        for unit in "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><t>é</t>".encode_utf16() {
+            utf16.extend(unit.to_le_bytes());
+        }
+
+        let decoded = decode(&utf16, Some("text/xml; charset=iso-8859-1"));
+        assert_eq!(
+            decoded.text,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><t>é</t>"
+        );
+
+        let decoded = decode(b"\xef\xbb\xbf<t>\xc3\xa9</t>", Some("text/xml; charset=iso-8859-1"));
+        assert_eq!(decoded.text, "<t>é</t>");
+    }
+
+    #[test]
+    fn test_falls_back_to_windows_1252() {
+        let mislabeled = b"<?xml version=\"1.0\" encoding=\"utf-8\"?><t>\x93quoted\x94 caf\xe9</t>";
+
+        let decoded = decode(mislabeled, Some("text/xml; charset=utf-8"));
+        assert!(decoded.text.contains("<t>\u{201c}quoted\u{201d} café</t>"));
+        assert_eq!(
+            decoded.repairs,
+            vec!["Decoded as windows-1252: the document is not valid UTF-8"]
+        );
+
+        let decoded = decode(b"<t>ok</t>", Some("text/xml; charset=bogus"));
+        assert_eq!(decoded.text, "<t>ok</t>");
+        assert_eq!(decoded.repairs, vec!["Ignored unknown HTTP charset 'bogus'"]);
+    }
+
+    #[test]
+    fn test_repairs_unescaped_characters() {
+        let broken = rss(
+            "<item><title>Fish & Chips <3</title><link>https://example.com/?a=1&b=2</link>\
+             <description><![CDATA[R&D <b>bold</b>]]></description>\
+             <guid>a&amp;b &#169; &#x2014;</guid></item>",
+        );
+        assert!(parse(broken.as_bytes()).is_err());
+
+        let parsed = parse_lenient(broken.as_bytes(), None, parse).unwrap();
+        let entry = &parsed.feed.entries[0];
+        assert_eq!(entry.title.as_ref().unwrap().content, "Fish & Chips <3");
+        assert_eq!(entry.links[0].href, "https://example.com/?a=1&b=2");
+        assert_eq!(entry.summary.as_ref().unwrap().content, "R&D <b>bold</b>");
+        assert_eq!(
+            parsed.repairs,
+            vec![
+                "Escaped 2 unescaped '&' characters",
+                "Escaped 1 unescaped '<' characters",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removes_invalid_characters() {
+        let broken = rss("<item><title>Bell\u{7}\u{0}</title></item>");
+
+        let parsed = parse_lenient(broken.as_bytes(), None, parse).unwrap();
+        assert_eq!(parsed.feed.entries[0].title.as_ref().unwrap().content, "Bell");
+        assert_eq!(parsed.repairs, vec!["Removed 2 invalid characters"]);
+    }
+
+    #[test]
+    fn test_repairs_truncated_documents() {
+        let full = rss(
+            r#"<item><title>One</title><guid>1</guid></item><item><title>Two</title><link href="x>y">"#,
+        );
+        let truncated = &full[..full.find("Two").unwrap() + 3];
+        assert!(parse(truncated.as_bytes()).is_err());
+
+        let parsed = parse_lenient(truncated.as_bytes(), None, parse).unwrap();
+        assert_eq!(parsed.feed.entries.len(), 2);
+        assert_eq!(parsed.feed.entries[1].title.as_ref().unwrap().content, "Two");
+        assert_eq!(
+            parsed.repairs,
+            vec!["Completed a truncated document (closed 4 open elements)"]
+        );
+
+        // Cut inside a tag: the partial tag is dropped
+        let cut_in_tag = &full[..full.find("<link").unwrap() + 8];
+        let parsed = parse_lenient(cut_in_tag.as_bytes(), None, parse).unwrap();
+        assert_eq!(parsed.feed.entries.len(), 2);
+
+        // Cut inside a CDATA section: the section is closed
+        let cdata = rss("<item><title><![CDATA[Three & fou");
+        let cdata = &cdata[..cdata.find("fou").unwrap() + 3];
+        let parsed = parse_lenient(cdata.as_bytes(), None, parse).unwrap();
+        assert_eq!(
+            parsed.feed.entries[0].title.as_ref().unwrap().content,
+            "Three & fou"
+        );
+    }
+
+    #[test]
+    fn test_repair_leaves_valid_and_non_xml_documents_alone() {
+        assert_eq!(repair_xml(&rss("<item><title>A &amp; B</title></item>")), None);
+        assert_eq!(repair_xml(r#"{"version": "https://jsonfeed.org/version/1.1"}"#), None);
+
+        let parsed = parse_lenient(b"not a feed & never was", None, parse);
+        assert!(parsed.is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! Feed autodiscovery service.
+//!
+//! Users often paste a website's homepage instead of its feed URL. This module
+//! resolves such URLs to actual feeds by looking at `<link rel="alternate">`
+//! tags in the page and, failing that, probing a few common feed paths.
+
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::Client;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use url::Url;
+
+use crate::services::decode;
+use crate::services::http::{self, BodyError, RedirectError};
+use crate::services::url_guard::{BlockedUrl, UrlGuard};
+
+/// Link `type` values that identify a feed in `<link rel="alternate">` tags.
+const FEED_MEDIA_TYPES: &[&str] = &[
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/rdf+xml",
+];
+
+/// Largest page or probe response read during discovery.
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Paths probed (in order) when a page advertises no feeds.
+const COMMON_FEED_PATHS: &[&str] = &[
+    "/feed",
+    "/rss.xml",
+    "/feed.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/rss",
+];
+
+/// A feed advertised by (or probed on) a website.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedCandidate {
+    /// Absolute URL of the feed.
+    pub url: String,
+    /// Title from the `<link title="...">` attribute, if any.
+    pub title: Option<String>,
+    /// Declared media type, e.g. `application/rss+xml`.
+    pub media_type: Option<String>,
+}
+
+/// Outcome of discovering feeds for a URL.
+#[derive(Debug, PartialEq)]
+pub enum Discovery {
+    /// The URL resolved to exactly one feed.
+    Feed(String),
+    /// The page advertises several feeds; the user has to pick one.
+    Candidates(Vec<FeedCandidate>),
+}
+
+/// Errors that can occur during feed discovery.
+#[derive(Debug)]
+pub enum DiscoveryError {
+    /// The input is not a usable http(s) URL.
+    InvalidUrl(String),
+    /// HTTP request for the page failed.
+    HttpError(reqwest::Error),
+    /// The page redirected too many times.
+    TooManyRedirects,
+    /// The URL (or a redirect target) points somewhere we don't fetch from.
+    Blocked(BlockedUrl),
+    /// The page is larger than we are willing to download.
+    ResponseTooLarge,
+    /// The page is neither a feed nor links to one.
+    NoFeedFound,
+}
+
+impl std::fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscoveryError::InvalidUrl(url) => write!(f, "Invalid URL: {}", url),
+            DiscoveryError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            DiscoveryError::TooManyRedirects => write!(f, "Too many redirects"),
+            DiscoveryError::Blocked(e) => write!(f, "{}", e),
+            DiscoveryError::ResponseTooLarge => write!(f, "Page is too large"),
+            DiscoveryError::NoFeedFound => write!(f, "No feed found at this URL"),
+        }
+    }
+}
+
+impl std::error::Error for DiscoveryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DiscoveryError::HttpError(e) => Some(e),
+            DiscoveryError::Blocked(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for DiscoveryError {
+    fn from(err: reqwest::Error) -> Self {
+        DiscoveryError::HttpError(err)
+    }
+}
+
+impl From<RedirectError> for DiscoveryError {
+    fn from(err: RedirectError) -> Self {
+        match err {
+            RedirectError::HttpError(e) => DiscoveryError::HttpError(e),
+            RedirectError::InvalidUrl(url) => DiscoveryError::InvalidUrl(url),
+            RedirectError::TooManyRedirects => DiscoveryError::TooManyRedirects,
+            RedirectError::Blocked(e) => DiscoveryError::Blocked(e),
+        }
+    }
+}
+
+impl From<BodyError> for DiscoveryError {
+    fn from(err: BodyError) -> Self {
+        match err {
+            BodyError::HttpError(e) => DiscoveryError::HttpError(e),
+            BodyError::TooLarge(_) => DiscoveryError::ResponseTooLarge,
+        }
+    }
+}
+
+/// Resolve a user-supplied URL to a feed URL.
+///
+/// 1. If the URL itself serves a feed, it is returned as-is.
+/// 2. If it serves HTML, feeds advertised via `<link rel="alternate">` are collected.
+/// 3. If the page advertises none, common paths such as `/feed` are probed.
+///
+/// # Arguments
+/// * `client` - HTTP client used for the page and probe requests
+/// * `guard` - Checks the URL (resolved up front, for a clear error) and every redirect
+/// * `input` - The URL the user pasted (a missing scheme defaults to https)
+pub async fn discover_feed(
+    client: &Client,
+    guard: &UrlGuard,
+    input: &str,
+) -> Result<Discovery, DiscoveryError> {
+    let url = parse_input_url(input)?;
+    guard.check_url_resolved(&url).await.map_err(DiscoveryError::Blocked)?;
+
+    let followed =
+        http::get_following_redirects(client, guard, url.as_str(), &HeaderMap::new()).await?;
+    let final_url = followed.final_url;
+    let response = followed.response.error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let is_html = content_type.as_deref().is_some_and(|ct| ct.contains("html"));
+    let bytes = http::read_body_limited(response, MAX_PAGE_BYTES).await?;
+
+    let is_feed = !is_html
+        && decode::parse_lenient(&bytes, content_type.as_deref(), |bytes| {
+            feed_rs::parser::parse(bytes)
+        })
+        .is_ok();
+    if is_feed {
+        return Ok(Discovery::Feed(final_url.to_string()));
+    }
+
+    let html = String::from_utf8_lossy(&bytes);
+    let mut candidates = extract_feed_links(&html, &final_url);
+
+    if candidates.is_empty()
+        && let Some(candidate) = probe_common_paths(client, guard, &final_url).await
+    {
+        candidates.push(candidate);
+    }
+
+    match candidates.len() {
+        0 => Err(DiscoveryError::NoFeedFound),
+        1 => Ok(Discovery::Feed(candidates.remove(0).url)),
+        _ => Ok(Discovery::Candidates(candidates)),
+    }
+}
+
+/// Parse the user's input, defaulting to https when no scheme was given.
+fn parse_input_url(input: &str) -> Result<Url, DiscoveryError> {
+    let parsed = match Url::parse(input) {
+        Ok(url) => url,
+        Err(url::ParseError::RelativeUrlWithoutBase) => Url::parse(&format!("https://{}", input))
+            .map_err(|_| DiscoveryError::InvalidUrl(input.to_string()))?,
+        Err(_) => return Err(DiscoveryError::InvalidUrl(input.to_string())),
+    };
+
+    match parsed.scheme() {
+        "http" | "https" => Ok(parsed),
+        _ => Err(DiscoveryError::InvalidUrl(input.to_string())),
+    }
+}
+
+/// Collect feeds advertised by `<link rel="alternate" type="...">` tags.
+///
+/// Relative hrefs are resolved against `base`. Duplicate URLs are dropped,
+/// keeping the first occurrence.
+fn extract_feed_links(html: &str, base: &Url) -> Vec<FeedCandidate> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[rel][href][type]").expect("valid selector");
+
+    let mut candidates: Vec<FeedCandidate> = Vec::new();
+
+    for element in document.select(&selector) {
+        let attrs = element.value();
+
+        let is_alternate = attrs
+            .attr("rel")
+            .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("alternate")));
+        let media_type = attrs.attr("type").map(|t| t.trim().to_ascii_lowercase());
+        let is_feed_type = media_type
+            .as_deref()
+            .is_some_and(|t| FEED_MEDIA_TYPES.contains(&t));
+
+        if !is_alternate || !is_feed_type {
+            continue;
+        }
+
+        let Some(url) = attrs.attr("href").and_then(|href| base.join(href.trim()).ok()) else {
+            continue;
+        };
+        let url = url.to_string();
+
+        if candidates.iter().any(|c| c.url == url) {
+            continue;
+        }
+
+        candidates.push(FeedCandidate {
+            url,
+            title: attrs
+                .attr("title")
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty()),
+            media_type,
+        });
+    }
+
+    candidates
+}
+
+/// Probe well-known feed paths on the site's origin.
+///
+/// Returns the first path that serves a parseable feed (repairing it if needed,
+/// as fetches do), at the URL it was served from after redirects.
+async fn probe_common_paths(client: &Client, guard: &UrlGuard, base: &Url) -> Option<FeedCandidate> {
+    for path in COMMON_FEED_PATHS {
+        let Ok(url) = base.join(path) else {
+            continue;
+        };
+
+        let Ok(followed) =
+            http::get_following_redirects(client, guard, url.as_str(), &HeaderMap::new()).await
+        else {
+            continue;
+        };
+        let response = followed.response;
+        if !response.status().is_success() {
+            continue;
+        }
+        let content_type = http::header_string(response.headers(), CONTENT_TYPE);
+        let Ok(bytes) = http::read_body_limited(response, MAX_PAGE_BYTES).await else {
+            continue;
+        };
+
+        let is_feed = decode::parse_lenient(&bytes, content_type.as_deref(), |bytes| {
+            feed_rs::parser::parse(bytes)
+        })
+        .is_ok();
+        if is_feed {
+            return Some(FeedCandidate {
+                url: followed.final_url.to_string(),
+                title: None,
+                media_type: None,
+            });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, response::Redirect, routing::get, Router};
+    use tokio::net::TcpListener;
+
+    const RSS_BODY: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Fixture</title><link>http://example.com</link>
+<item><title>Hello</title><link>http://example.com/hello</link></item>
+</channel></rss>"#;
+
+    fn html(head: &str) -> String {
+        format!("<html><head>{}</head><body>Hi</body></html>", head)
+    }
+
+    /// The test servers listen on loopback, which is blocked by default.
+    fn loopback_guard() -> UrlGuard {
+        UrlGuard::from_allowlist("127.0.0.1").unwrap()
+    }
+
+    /// Serve `router` on an ephemeral local port and return its base URL.
+    async fn serve(router: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    fn html_response(body: String) -> ([(header::HeaderName, &'static str); 1], String) {
+        ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], body)
+    }
+
+    fn rss_response() -> ([(header::HeaderName, &'static str); 1], &'static str) {
+        ([(header::CONTENT_TYPE, "application/rss+xml")], RSS_BODY)
+    }
+
+    #[test]
+    fn test_extract_feed_links_resolves_relative_hrefs() {
+        let base = Url::parse("https://example.com/blog/").unwrap();
+        let page = html(
+            r#"<link rel="alternate" type="application/rss+xml" title="Posts" href="/feed.xml">
+               <link rel="alternate" type="application/atom+xml" href="atom.xml">
+               <link rel="alternate" type="application/feed+json" href="https://cdn.example.com/feed.json">
+               <link rel="stylesheet" type="text/css" href="/style.css">
+               <link rel="alternate" type="text/html" hreflang="fr" href="/fr/">"#,
+        );
+
+        let candidates = extract_feed_links(&page, &base);
+
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].url, "https://example.com/feed.xml");
+        assert_eq!(candidates[0].title.as_deref(), Some("Posts"));
+        assert_eq!(candidates[1].url, "https://example.com/blog/atom.xml");
+        assert_eq!(candidates[2].url, "https://cdn.example.com/feed.json");
+    }
+
+    #[test]
+    fn test_extract_feed_links_dedupes() {
+        let base = Url::parse("https://example.com/").unwrap();
+        let page = html(
+            r#"<link rel="alternate" type="application/rss+xml" href="/feed">
+               <link rel="Alternate" type="APPLICATION/RSS+XML" href="https://example.com/feed">"#,
+        );
+
+        assert_eq!(extract_feed_links(&page, &base).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_input_url() {
+        assert_eq!(
+            parse_input_url("arstechnica.com").unwrap().as_str(),
+            "https://arstechnica.com/"
+        );
+        assert!(parse_input_url("ftp://example.com/feed").is_err());
+        assert!(parse_input_url("http://").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_discover_direct_feed_url() {
+        let base = serve(Router::new().route("/rss", get(|| async { rss_response() }))).await;
+
+        let result = discover_feed(&Client::new(), &loopback_guard(), &format!("{}/rss", base)).await.unwrap();
+
+        assert_eq!(result, Discovery::Feed(format!("{}/rss", base)));
+    }
+
+    #[tokio::test]
+    async fn test_discover_single_link_candidate() {
+        let page = html(r#"<link rel="alternate" type="application/rss+xml" href="/posts.rss">"#);
+        let base = serve(Router::new().route("/", get(move || async move { html_response(page) }))).await;
+
+        let result = discover_feed(&Client::new(), &loopback_guard(), &base).await.unwrap();
+
+        assert_eq!(result, Discovery::Feed(format!("{}/posts.rss", base)));
+    }
+
+    #[tokio::test]
+    async fn test_discover_multiple_link_candidates() {
+        let page = html(
+            r#"<link rel="alternate" type="application/rss+xml" title="All" href="/all.rss">
+               <link rel="alternate" type="application/atom+xml" title="News" href="/news.atom">"#,
+        );
+        let base = serve(Router::new().route("/", get(move || async move { html_response(page) }))).await;
+
+        let Discovery::Candidates(candidates) = discover_feed(&Client::new(), &loopback_guard(), &base).await.unwrap()
+        else {
+            panic!("expected multiple candidates");
+        };
+
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[1].title.as_deref(), Some("News"));
+    }
+
+    #[tokio::test]
+    async fn test_discover_falls_back_to_common_paths() {
+        let router = Router::new()
+            .route("/", get(|| async { html_response(html("<title>No feeds here</title>")) }))
+            .route("/rss.xml", get(|| async { rss_response() }));
+        let base = serve(router).await;
+
+        let result = discover_feed(&Client::new(), &loopback_guard(), &base).await.unwrap();
+
+        assert_eq!(result, Discovery::Feed(format!("{}/rss.xml", base)));
+    }
+
+    #[tokio::test]
+    async fn test_common_path_probe_follows_redirects_and_repairs() {
+        let broken = RSS_BODY.replace("<title>Hello</title>", "<title>Fish & Chips</title>");
+        let router = Router::new()
+            .route("/", get(|| async { html_response(html("<title>No feeds here</title>")) }))
+            .route("/feed", get(|| async { Redirect::permanent("/feeds/main.xml") }))
+            .route(
+                "/feeds/main.xml",
+                get(move || async move { ([(header::CONTENT_TYPE, "application/rss+xml")], broken) }),
+            );
+        let base = serve(router).await;
+        // Like the fetcher's client, so redirects go through the guard
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+
+        let result = discover_feed(&client, &loopback_guard(), &base).await.unwrap();
+
+        assert_eq!(result, Discovery::Feed(format!("{}/feeds/main.xml", base)));
+    }
+
+    #[tokio::test]
+    async fn test_discover_no_feed_found() {
+        let base = serve(Router::new().route("/", get(|| async { html_response(html("")) }))).await;
+
+        let result = discover_feed(&Client::new(), &loopback_guard(), &base).await;
+
+        assert!(matches!(result, Err(DiscoveryError::NoFeedFound)));
+    }
+
+    #[tokio::test]
+    async fn test_discover_rejects_private_addresses() {
+        for url in ["http://169.254.169.254/", "http://localhost:5432/", "file:///etc/passwd"] {
+            let result = discover_feed(&Client::new(), &UrlGuard::default(), url).await;
+            assert!(
+                matches!(
+                    result,
+                    Err(DiscoveryError::Blocked(_) | DiscoveryError::InvalidUrl(_))
+                ),
+                "{} should be rejected",
+                url
+            );
+        }
+    }
+}
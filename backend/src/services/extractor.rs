@@ -0,0 +1,154 @@
+//! Main-content extraction for article pages.
+//!
+//! Many feeds only ship a one-line summary. For feeds with "fetch full content"
+//! enabled (and on demand from the article API) the article page is
+//! downloaded and its main content picked out readability-style: paragraphs
+//! score their parent (and half their grandparent) by length and commas, the
+//! score is discounted by link density, and the best-scoring container wins.
+
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashMap;
+use url::Url;
+
+use crate::services::sanitize;
+
+/// Paragraphs shorter than this don't count towards a container's score.
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// Extractions with less text than this are treated as failures (the page was
+/// probably a paywall, a consent wall or an index page).
+const MIN_EXTRACTED_CHARS: usize = 250;
+
+/// Main content extracted from an article page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extracted {
+    /// Sanitized HTML of the main content.
+    pub html: String,
+    /// Plain text of the main content, whitespace collapsed.
+    pub text: String,
+}
+
+/// Extract the main content of an HTML page.
+///
+/// # Arguments
+/// * `page` - The page's HTML
+/// * `page_url` - Where the page was served from (relative URLs resolve against it)
+///
+/// # Returns
+/// `None` if no container with enough text was found.
+pub fn extract(page: &str, page_url: &Url) -> Option<Extracted> {
+    let document = Html::parse_document(page);
+    let paragraphs = Selector::parse("p, pre, blockquote").expect("valid selector");
+
+    let mut scores: HashMap<_, f64> = HashMap::new();
+    for paragraph in document.select(&paragraphs) {
+        if has_ancestor(paragraph, &["nav", "header", "footer", "aside", "form"]) {
+            continue;
+        }
+
+        let text = normalize_whitespace(&paragraph.text().collect::<String>());
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+
+        let parent = paragraph.parent().and_then(ElementRef::wrap);
+        if let Some(parent) = parent {
+            *scores.entry(parent.id()).or_default() += score;
+            if let Some(grandparent) = parent.parent().and_then(ElementRef::wrap) {
+                *scores.entry(grandparent.id()).or_default() += score / 2.0;
+            }
+        }
+    }
+
+    let best = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = document.tree.get(id).and_then(ElementRef::wrap)?;
+            Some((element, score * (1.0 - link_density(element))))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)?;
+
+    let html = sanitize::sanitize_extracted_html(&best.inner_html(), page_url);
+    let fragment = Html::parse_fragment(&html);
+    let text = normalize_whitespace(&fragment.root_element().text().collect::<Vec<_>>().join(" "));
+
+    (text.chars().count() >= MIN_EXTRACTED_CHARS).then_some(Extracted { html, text })
+}
+
+/// Share of an element's text that sits inside links.
+fn link_density(element: ElementRef<'_>) -> f64 {
+    let links = Selector::parse("a").expect("valid selector");
+    let total = element.text().map(str::len).sum::<usize>();
+    if total == 0 {
+        return 1.0;
+    }
+
+    let linked = element
+        .select(&links)
+        .flat_map(|link| link.text())
+        .map(str::len)
+        .sum::<usize>();
+
+    linked as f64 / total as f64
+}
+
+fn has_ancestor(element: ElementRef<'_>, names: &[&str]) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| names.contains(&ancestor.value().name()))
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAGRAPH: &str = "The committee met on Tuesday to discuss the proposal, which would change how \
+        the city funds its transit system, according to people familiar with the talks.";
+
+    fn page(article: &str) -> String {
+        format!(
+            r#"<html><head><title>Story</title><script>track()</script></head><body>
+<nav><p>Home, News, Sports, Weather, Opinion, and a long list of other sections here</p></nav>
+<div class="sidebar"><a href="/a">A very long related headline link number one</a><a href="/b">Another related headline link</a></div>
+<div class="story">{}</div>
+<footer><p>Copyright 2024, Example News Corporation, all rights reserved worldwide.</p></footer>
+</body></html>"#,
+            article
+        )
+    }
+
+    fn url() -> Url {
+        Url::parse("https://news.example.com/2024/story").unwrap()
+    }
+
+    #[test]
+    fn test_extracts_main_content() {
+        let article = format!(
+            r#"<h1>Headline</h1><p>{p}</p><p>{p}</p><p><img src="/photo.jpg">{p}</p><p>Short.</p>"#,
+            p = PARAGRAPH
+        );
+
+        let extracted = extract(&page(&article), &url()).unwrap();
+
+        assert!(extracted.text.starts_with("Headline The committee met"));
+        assert!(!extracted.text.contains("Copyright"));
+        assert!(!extracted.text.contains("Sports"));
+        assert!(extracted.html.contains(r#"src="https://news.example.com/photo.jpg""#));
+        assert!(!extracted.html.contains("track()"));
+    }
+
+    #[test]
+    fn test_too_little_text_is_not_extracted() {
+        let article = format!("<p>{}</p>", PARAGRAPH);
+        assert_eq!(extract(&page(&article), &url()), None);
+    }
+}
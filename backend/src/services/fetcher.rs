@@ -1,35 +1,92 @@
 //! RSS/Atom feed fetcher service.
 //!
 //! This module provides functionality to fetch and parse RSS/Atom feeds,
-//! storing new articles in the database.
+//! storing new articles in the database. Where a feed's content comes from
+//! (HTTP, a scraped page, a file) is up to its source; see `services::source`.
 
-use feed_rs::parser;
+use chrono::Utc;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
 use reqwest::Client;
 use sqlx::PgPool;
-use std::time::Duration;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use url::Url;
 use uuid::Uuid;
 
-use crate::db::{articles, feeds};
+use crate::config::Config;
+use crate::db::articles::{self, NewArticle, UpsertStatus};
+use crate::db::feeds;
+use crate::db::fetch_log::{self, NewFetchLogEntry};
 use crate::models::feed::Feed;
+use crate::services::canonical;
+use crate::services::credentials::{CredentialCipher, CredentialError};
+use crate::services::decode;
+use crate::services::extractor::{self, Extracted};
+use crate::services::host_limiter::{host_key, HostLimiter};
+use crate::services::identity::{self, IdentityStrategy};
+use crate::services::language;
+use crate::services::media;
+use crate::services::reading_time::ReadingTime;
+use crate::services::http::{self, BodyError, RedirectError};
+use crate::services::sanitize;
+use crate::services::schedule;
+use crate::services::scrape::{self, ScrapeError, ScrapeRules, ScrapedPage};
+use crate::services::source::{
+    self, FeedKind, FeedSource, FetchAttempt, Fetched, FileSource, HttpSource, ScrapeSource,
+    SourceDocument, SourceEntry,
+};
+use crate::services::url_guard::{BlockedUrl, UrlGuard};
+use crate::services::websub;
 
 /// Result of fetching a single feed.
 #[derive(Debug, Clone)]
 pub struct FetchResult {
-    /// The ID of the feed that was fetched.
+    /// The ID of the feed that was fetched. Differs from the requested feed
+    /// when a permanent redirect merged it into a feed already stored at the
+    /// new URL.
     pub feed_id: Uuid,
-    /// Number of articles successfully fetched/created.
-    pub articles_fetched: usize,
-    /// Errors encountered while processing individual entries (non-fatal).
+    /// New URL when the feed permanently redirected (301/308).
+    pub moved_to: Option<String>,
+    /// Entries stored for the first time.
+    pub new_articles: usize,
+    /// Known entries whose content changed and were rewritten.
+    pub updated_articles: usize,
+    /// Known entries with unchanged content (left untouched).
+    pub unchanged_articles: usize,
+    /// True when the server answered `304 Not Modified` and the feed was not re-parsed.
+    pub not_modified: bool,
+    /// Errors encountered while processing individual entries (non-fatal),
+    /// and any repairs needed to parse a malformed document.
     pub errors: Vec<String>,
 }
 
 /// Errors that can occur during feed fetching.
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum FetchError {
     /// HTTP request failed.
     HttpError(reqwest::Error),
+    /// A redirect could not be followed (bad `Location`, too many hops).
+    RedirectError(RedirectError),
+    /// The server answered `410 Gone`; the feed has been marked gone.
+    Gone,
+    /// The URL (or a redirect target) points somewhere we don't fetch from.
+    Blocked(BlockedUrl),
+    /// The response body is larger than the configured limit (in bytes).
+    ResponseTooLarge(usize),
+    /// The response's `Content-Type` can't be a feed.
+    UnexpectedContentType(String),
+    /// A private feed's stored credentials can't be used.
+    Credentials(String),
+    /// A `file://` feed couldn't be read.
+    FileError(std::io::Error),
     /// Failed to parse the feed content.
     ParseError(feed_rs::parser::ParseFeedError),
+    /// A scraped source's page couldn't be read with its rules.
+    Scrape(ScrapeError),
     /// Database operation failed.
     DatabaseError(sqlx::Error),
 }
@@ -38,17 +95,59 @@ impl std::fmt::Display for FetchError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             FetchError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            FetchError::RedirectError(e) => write!(f, "Redirect error: {}", e),
+            FetchError::Gone => write!(f, "Feed is gone (410)"),
+            FetchError::Blocked(e) => write!(f, "Blocked URL: {}", e),
+            FetchError::ResponseTooLarge(limit) => {
+                write!(f, "Response larger than {} bytes", limit)
+            }
+            FetchError::UnexpectedContentType(content_type) => {
+                write!(f, "Unexpected content type: {}", content_type)
+            }
+            FetchError::Credentials(e) => write!(f, "Feed credentials: {}", e),
+            FetchError::FileError(e) => write!(f, "File error: {}", e),
             FetchError::ParseError(e) => write!(f, "Parse error: {}", e),
+            FetchError::Scrape(e) => write!(f, "Scrape error: {}", e),
             FetchError::DatabaseError(e) => write!(f, "Database error: {}", e),
         }
     }
 }
 
+impl FetchError {
+    /// Short name for the kind of failure, stored in the fetch log.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FetchError::HttpError(e) if e.is_timeout() => "timeout",
+            FetchError::HttpError(e) if e.is_connect() => "connection",
+            FetchError::HttpError(e) if e.is_status() => "http_status",
+            FetchError::HttpError(_) => "http",
+            FetchError::RedirectError(_) => "redirect",
+            FetchError::Gone => "gone",
+            FetchError::Blocked(_) => "blocked",
+            FetchError::ResponseTooLarge(_) => "too_large",
+            FetchError::UnexpectedContentType(_) => "content_type",
+            FetchError::Credentials(_) => "credentials",
+            FetchError::FileError(_) => "file",
+            FetchError::ParseError(_) => "parse",
+            FetchError::Scrape(_) => "scrape",
+            FetchError::DatabaseError(_) => "database",
+        }
+    }
+}
+
 impl std::error::Error for FetchError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             FetchError::HttpError(e) => Some(e),
+            FetchError::RedirectError(e) => Some(e),
+            FetchError::Gone => None,
+            FetchError::Blocked(e) => Some(e),
+            FetchError::ResponseTooLarge(_) => None,
+            FetchError::UnexpectedContentType(_) => None,
+            FetchError::Credentials(_) => None,
+            FetchError::FileError(e) => Some(e),
             FetchError::ParseError(e) => Some(e),
+            FetchError::Scrape(e) => Some(e),
             FetchError::DatabaseError(e) => Some(e),
         }
     }
@@ -60,22 +159,90 @@ impl From<reqwest::Error> for FetchError {
     }
 }
 
+impl From<RedirectError> for FetchError {
+    fn from(err: RedirectError) -> Self {
+        match err {
+            RedirectError::HttpError(e) => FetchError::HttpError(e),
+            RedirectError::Blocked(e) => FetchError::Blocked(e),
+            other => FetchError::RedirectError(other),
+        }
+    }
+}
+
+impl From<BodyError> for FetchError {
+    fn from(err: BodyError) -> Self {
+        match err {
+            BodyError::HttpError(e) => FetchError::HttpError(e),
+            BodyError::TooLarge(limit) => FetchError::ResponseTooLarge(limit),
+        }
+    }
+}
+
 impl From<feed_rs::parser::ParseFeedError> for FetchError {
     fn from(err: feed_rs::parser::ParseFeedError) -> Self {
         FetchError::ParseError(err)
     }
 }
 
+impl From<ScrapeError> for FetchError {
+    fn from(err: ScrapeError) -> Self {
+        FetchError::Scrape(err)
+    }
+}
+
 impl From<sqlx::Error> for FetchError {
     fn from(err: sqlx::Error) -> Self {
         FetchError::DatabaseError(err)
     }
 }
 
+/// Article pages downloaded (in the background) per feed fetch for full-content
+/// feeds; the rest are extracted on demand when read.
+const MAX_EXTRACTIONS_PER_FETCH: usize = 10;
+
+/// Feeds whose new articles are extracted in the background at the same time.
+const MAX_BACKGROUND_EXTRACTIONS: usize = 4;
+
 /// Service for fetching and parsing RSS/Atom feeds.
 pub struct FeedFetcher {
     client: Client,
     pool: PgPool,
+    min_interval_minutes: i64,
+    max_interval_minutes: i64,
+    failure_threshold: i32,
+    /// Base of the WebSub callback URL; push subscriptions are off without it.
+    public_base_url: Option<Url>,
+    /// Blocks requests to private networks (see `services::url_guard`).
+    guard: UrlGuard,
+    /// Largest feed or article page body read, in bytes.
+    max_body_bytes: usize,
+    /// How long fetch history is kept.
+    fetch_log_retention_days: i32,
+    /// Decrypts private feeds' credentials; `None` when no key is configured.
+    credential_cipher: Option<CredentialCipher>,
+    /// Canonical directory `file://` feeds are read from; `None` disables them.
+    file_root: Option<PathBuf>,
+    /// Bounds the background extraction jobs queued by feed fetches.
+    extraction_permits: Arc<Semaphore>,
+    /// Per-host limits, shared with the scheduler and on-demand refreshes.
+    host_limiter: Arc<HostLimiter>,
+    /// Feeds being fetched right now (see [`FeedFetcher::try_claim`]).
+    in_flight: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+/// A feed claimed for fetching; released on drop.
+pub struct FetchClaim {
+    feed_id: Uuid,
+    in_flight: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+impl Drop for FetchClaim {
+    fn drop(&mut self) {
+        self.in_flight
+            .lock()
+            .expect("in-flight feeds lock poisoned")
+            .remove(&self.feed_id);
+    }
 }
 
 impl FeedFetcher {
@@ -84,93 +251,630 @@ impl FeedFetcher {
     /// Configures the HTTP client with:
     /// - 30 second timeout
     /// - Custom User-Agent identifying the Herald RSS reader
-    pub fn new(pool: PgPool) -> Self {
+    /// - Redirects disabled; they are followed by [`http::get_following_redirects`]
+    ///   so permanent moves can be detected
+    /// - A DNS resolver that refuses private and reserved addresses (except
+    ///   those in the config's fetch allowlist)
+    ///
+    /// Per-feed fetch intervals are bounded by the config's min/max interval.
+    /// Article pages downloaded in the background take a slot from
+    /// `host_limiter` like feed fetches do.
+    pub fn new(pool: PgPool, host_limiter: Arc<HostLimiter>, config: &Config) -> Self {
+        let guard = UrlGuard::from_allowlist(&config.fetch_allowlist)
+            .expect("FETCH_ALLOWLIST must be a comma-separated list of hosts, IPs and CIDR ranges");
+        let credential_cipher = config.feed_credentials_key.as_deref().map(|key| {
+            CredentialCipher::from_base64_key(key)
+                .expect("FEED_CREDENTIALS_KEY must be a base64-encoded 32-byte key")
+        });
+        let file_root = config.feed_file_root.as_deref().map(|root| {
+            std::fs::canonicalize(root).expect("FEED_FILE_ROOT must be an existing directory")
+        });
+
         let client = Client::builder()
             .timeout(Duration::from_secs(30))
             .user_agent("Herald-RSS-Reader/1.0 (https://github.com/herald-rss)")
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(guard.resolver())
             .build()
             .expect("Failed to build HTTP client");
 
-        Self { client, pool }
+        Self {
+            client,
+            pool,
+            min_interval_minutes: config.feed_min_interval_minutes,
+            max_interval_minutes: config.feed_max_interval_minutes,
+            failure_threshold: config.feed_failure_threshold,
+            public_base_url: config
+                .public_base_url
+                .as_deref()
+                .and_then(|u| Url::parse(u).ok()),
+            guard,
+            max_body_bytes: config.fetch_max_response_bytes,
+            fetch_log_retention_days: config.fetch_log_retention_days,
+            credential_cipher,
+            file_root,
+            extraction_permits: Arc::new(Semaphore::new(MAX_BACKGROUND_EXTRACTIONS)),
+            host_limiter,
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// The HTTP client used for feed requests (shared with feed discovery).
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Encrypts credentials for new private feeds; `None` when private feeds
+    /// are not enabled.
+    pub fn credential_cipher(&self) -> Option<&CredentialCipher> {
+        self.credential_cipher.as_ref()
+    }
+
+    /// The guard outgoing URLs are checked against (shared with feed discovery).
+    pub fn url_guard(&self) -> &UrlGuard {
+        &self.guard
+    }
+
+    /// Claim a feed for fetching, unless it is being fetched already.
+    ///
+    /// The scheduler and on-demand refreshes both claim feeds first so the
+    /// same feed is never fetched twice at once. Hold the claim until the
+    /// fetch is done.
+    pub fn try_claim(&self, feed_id: Uuid) -> Option<FetchClaim> {
+        let mut in_flight = self.in_flight.lock().expect("in-flight feeds lock poisoned");
+        in_flight.insert(feed_id).then(|| FetchClaim {
+            feed_id,
+            in_flight: self.in_flight.clone(),
+        })
     }
 
     /// Fetch a single feed and store new articles.
     ///
+    /// Sends the feed's stored `ETag` / `Last-Modified` validators as a
+    /// conditional request. A `304 Not Modified` response skips parsing and
+    /// article upserts entirely; only the fetch timestamps are bumped.
+    ///
+    /// Every fetch also reschedules the feed: successful parses recompute its
+    /// adaptive interval, while a 304 keeps the current one. Failures are
+    /// recorded on the feed and retried with exponential backoff; after too many
+    /// in a row the feed is disabled.
+    ///
+    /// A permanent redirect (301/308) moves the feed to its new URL, merging it
+    /// into the feed already stored there if there is one. A `410 Gone` marks
+    /// the feed gone so it is never fetched again.
+    ///
+    /// Every attempt is recorded in the feed's fetch log.
+    ///
+    /// The feed is fetched from the source for its kind: an HTTP feed, a
+    /// scraped page or a `file://` feed.
+    ///
     /// # Arguments
-    /// * `feed_id` - The database ID of the feed
-    /// * `url` - The URL of the RSS/Atom feed
+    /// * `feed` - The feed to fetch (uses its URL and HTTP validators)
     ///
     /// # Returns
-    /// A `FetchResult` containing the count of new articles and any non-fatal errors.
-    pub async fn fetch_feed(&self, feed_id: Uuid, url: &str) -> Result<FetchResult, FetchError> {
-        // Fetch the feed content via HTTP
-        let response = self.client.get(url).send().await?;
-        let bytes = response.bytes().await?;
+    /// A `FetchResult` with counts of new, updated and unchanged entries and any
+    /// non-fatal errors.
+    pub async fn fetch_feed(&self, feed: &Feed) -> Result<FetchResult, FetchError> {
+        let started = Instant::now();
+        let mut attempt = FetchAttempt::new(feed.id);
+        let result = self.try_fetch_feed(feed, &mut attempt).await;
 
-        // Parse the feed using feed-rs
-        let feed = parser::parse(&bytes[..])?;
+        match &result {
+            Err(FetchError::Gone) => {
+                tracing::warn!(feed_id = %feed.id, url = %feed.url, "Feed is gone (410), no longer fetching");
+            }
+            Err(e) => self.record_failure(feed, e).await,
+            Ok(_) => {}
+        }
+        self.log_fetch(&attempt, started.elapsed(), &result).await;
 
-        let mut articles_fetched = 0;
-        let mut errors = Vec::new();
+        result
+    }
 
-        // Process each entry in the feed
-        for entry in feed.entries {
-            // Extract article fields from the entry
-            let title = entry
-                .title
-                .map(|t| t.content)
-                .unwrap_or_else(|| "Untitled".to_string());
-
-            let url = match entry.links.first() {
-                Some(link) => link.href.clone(),
-                None => {
-                    errors.push(format!(
-                        "Entry '{}' has no URL, skipping",
-                        entry.id
-                    ));
-                    continue;
-                }
-            };
+    /// Add a fetch attempt to the feed's fetch log.
+    async fn log_fetch(
+        &self,
+        attempt: &FetchAttempt,
+        duration: Duration,
+        result: &Result<FetchResult, FetchError>,
+    ) {
+        let (new_articles, updated_articles) = match result {
+            Ok(result) => (result.new_articles, result.updated_articles),
+            Err(_) => (0, 0),
+        };
+        let error = result.as_ref().err();
+        let error_message = error.map(|e| e.to_string());
+        // Failures like a 5xx carry their status even if it wasn't recorded
+        let http_status = attempt.http_status.or_else(|| match error {
+            Some(FetchError::HttpError(e)) => e.status(),
+            _ => None,
+        });
+
+        let entry = NewFetchLogEntry {
+            feed_id: attempt.feed_id,
+            http_status: http_status.map(|status| i32::from(status.as_u16())),
+            duration_ms: i32::try_from(duration.as_millis()).unwrap_or(i32::MAX),
+            bytes: attempt.bytes.and_then(|bytes| i64::try_from(bytes).ok()),
+            new_articles: i32::try_from(new_articles).unwrap_or(i32::MAX),
+            updated_articles: i32::try_from(updated_articles).unwrap_or(i32::MAX),
+            error_kind: error.map(FetchError::kind),
+            error_message: error_message.as_deref(),
+        };
+        if let Err(e) = fetch_log::record(&self.pool, &entry).await {
+            tracing::error!(feed_id = %attempt.feed_id, error = %e, "Failed to record feed fetch log");
+        }
+    }
+
+    /// Delete fetch history older than the configured retention.
+    ///
+    /// # Returns
+    /// The number of log rows deleted.
+    pub async fn prune_fetch_log(&self) -> Result<u64, sqlx::Error> {
+        fetch_log::prune(&self.pool, self.fetch_log_retention_days).await
+    }
+
+    /// Record a failed fetch on the feed and schedule its backed-off retry.
+    async fn record_failure(&self, feed: &Feed, error: &FetchError) {
+        let consecutive_failures = feed.consecutive_failures.saturating_add(1);
+        let base_interval = feed
+            .fetch_interval_minutes
+            .map(i64::from)
+            .unwrap_or(schedule::DEFAULT_INTERVAL_MINUTES);
+        let retry_in = schedule::backoff_minutes(
+            base_interval,
+            consecutive_failures,
+            self.max_interval_minutes,
+        );
+
+        match feeds::record_fetch_failure(
+            &self.pool,
+            feed.id,
+            &error.to_string(),
+            Utc::now() + chrono::Duration::minutes(retry_in),
+            self.failure_threshold,
+        )
+        .await
+        {
+            Ok(record) if record.disabled => tracing::warn!(
+                feed_id = %feed.id,
+                consecutive_failures = record.consecutive_failures,
+                "Feed disabled after repeated fetch failures"
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::error!(
+                feed_id = %feed.id,
+                error = %e,
+                "Failed to record feed fetch failure"
+            ),
+        }
+    }
+
+    /// Fetch and store a feed from the source for its kind; the body of
+    /// [`FeedFetcher::fetch_feed`].
+    async fn try_fetch_feed(
+        &self,
+        feed: &Feed,
+        attempt: &mut FetchAttempt,
+    ) -> Result<FetchResult, FetchError> {
+        match FeedKind::of(feed) {
+            FeedKind::Feed => self.fetch_from(&self.http_source(feed)?, feed, attempt).await,
+            FeedKind::Scrape => {
+                let source = ScrapeSource {
+                    http: self.http_source(feed)?,
+                    rules: ScrapeRules::of(feed)?,
+                };
+                self.fetch_from(&source, feed, attempt).await
+            }
+            FeedKind::File => self.fetch_from(&self.file_source()?, feed, attempt).await,
+        }
+    }
+
+    /// Fetch a feed from `source` and store its content.
+    async fn fetch_from<S: FeedSource>(
+        &self,
+        source: &S,
+        feed: &Feed,
+        attempt: &mut FetchAttempt,
+    ) -> Result<FetchResult, FetchError> {
+        let fetched = source.fetch(feed, attempt).await;
+        if let Err(FetchError::Gone) = fetched {
+            feeds::mark_feed_gone(&self.pool, feed.id).await?;
+        }
+
+        let moved_to = attempt.moved_to.clone();
+        if let Some(new_url) = &moved_to {
+            let feed_id = feeds::relocate_feed(&self.pool, feed.id, new_url.as_str()).await?;
+            attempt.feed_id = feed_id;
+            tracing::info!(
+                feed_id = %feed_id,
+                old_url = %feed.url,
+                new_url = %new_url,
+                merged = feed_id != feed.id,
+                "Feed moved permanently"
+            );
+        }
+        let feed_id = attempt.feed_id;
+        let moved_to = moved_to.map(|url| url.to_string());
 
-            let author = entry.authors.first().map(|a| a.name.clone());
-            let summary = entry.summary.map(|s| s.content);
-            let content = entry.content.and_then(|c| c.body);
-            let published_at = entry.published.or(entry.updated);
-            let guid = Some(entry.id);
+        let document = match fetched? {
+            Fetched::NotModified => {
+                feeds::record_fetch_success(&self.pool, feed_id).await?;
+                // Keep the interval, still avoiding the hours the publisher skips
+                let hints = feeds::get_skip_hints(&self.pool, feed_id).await?;
+                let interval = schedule::current_interval_minutes(feed);
+                feeds::update_next_fetch_at(
+                    &self.pool,
+                    feed_id,
+                    schedule::next_fetch_at(Utc::now(), interval, &hints),
+                )
+                .await?;
 
-            // Create the article in the database
-            match articles::create_article(
+                return Ok(FetchResult {
+                    feed_id,
+                    moved_to,
+                    new_articles: 0,
+                    updated_articles: 0,
+                    unchanged_articles: 0,
+                    not_modified: true,
+                    errors: Vec::new(),
+                });
+            }
+            Fetched::Document(document) => *document,
+        };
+
+        let validators = document.validators.clone();
+        let result = self.store(feed, feed_id, document).await?;
+
+        // Only remember the validators once the content has been stored, so a
+        // failed store is retried in full on the next fetch
+        if let Some(validators) = validators {
+            feeds::update_http_validators(
                 &self.pool,
                 feed_id,
-                &title,
-                &url,
-                author.as_deref(),
-                summary.as_deref(),
-                content.as_deref(),
-                published_at,
-                guid.as_deref(),
+                validators.etag.as_deref(),
+                validators.last_modified.as_deref(),
             )
-            .await
-            {
-                Ok(_) => {
-                    articles_fetched += 1;
+            .await?;
+        }
+
+        // Update the feed's fetch timestamps and reset its failure streak
+        feeds::record_fetch_success(&self.pool, feed_id).await?;
+
+        Ok(FetchResult { moved_to, ..result })
+    }
+
+    /// The HTTP source for a feed, with its credentials if it's private.
+    fn http_source(&self, feed: &Feed) -> Result<HttpSource<'_>, FetchError> {
+        Ok(HttpSource {
+            client: &self.client,
+            guard: &self.guard,
+            max_body_bytes: self.max_body_bytes,
+            credentials: self.credential_headers(feed)?,
+        })
+    }
+
+    /// The source for `file://` feeds.
+    ///
+    /// # Errors
+    /// When no `FEED_FILE_ROOT` is configured.
+    pub fn file_source(&self) -> Result<FileSource<'_>, FetchError> {
+        let root = self.file_root.as_deref().ok_or_else(|| {
+            FetchError::FileError(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "file:// feeds are not enabled (FEED_FILE_ROOT is not set)",
+            ))
+        })?;
+
+        Ok(FileSource {
+            root,
+            max_bytes: self.max_body_bytes,
+        })
+    }
+
+    /// The request headers for a private feed's credentials (none for shared feeds).
+    fn credential_headers(&self, feed: &Feed) -> Result<HeaderMap, FetchError> {
+        let Some(stored) = &feed.credentials else {
+            return Ok(HeaderMap::new());
+        };
+        let cipher = self
+            .credential_cipher
+            .as_ref()
+            .ok_or_else(|| FetchError::Credentials(CredentialError::NotConfigured.to_string()))?;
+
+        cipher
+            .decrypt(stored)
+            .map_err(|e| FetchError::Credentials(e.to_string()))?
+            .to_headers()
+            .map_err(FetchError::Credentials)
+    }
+
+    /// Store a feed document pushed by the feed's WebSub hub.
+    ///
+    /// Goes through the same parsing, scheduling and upserts as a polled fetch;
+    /// the feed's HTTP validators are left alone since the push didn't come
+    /// from its URL.
+    pub async fn ingest_push(
+        &self,
+        feed: &Feed,
+        content_type: Option<&str>,
+        bytes: &[u8],
+    ) -> Result<FetchResult, FetchError> {
+        let document = source::parse_feed_document(bytes, content_type, &feed.url)?;
+        let result = self.store(feed, feed.id, document).await?;
+        feeds::record_fetch_success(&self.pool, feed.id).await?;
+
+        Ok(result)
+    }
+
+    /// Request renewal of WebSub subscriptions whose lease is running out.
+    ///
+    /// # Returns
+    /// The number of subscriptions re-requested (0 when push is disabled).
+    pub async fn renew_websub_subscriptions(&self) -> Result<usize, sqlx::Error> {
+        match &self.public_base_url {
+            Some(base) => websub::renew_due(&self.client, &self.guard, &self.pool, base).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Fetch a page and read it with `rules` without storing anything, to
+    /// preview a scraped source.
+    pub async fn preview_scrape(
+        &self,
+        url: &str,
+        rules: &ScrapeRules,
+    ) -> Result<ScrapedPage, FetchError> {
+        let followed =
+            http::get_following_redirects(&self.client, &self.guard, url, &HeaderMap::new()).await?;
+        let page_url = followed.final_url;
+        let response = followed.response.error_for_status()?;
+        let content_type = http::header_string(response.headers(), CONTENT_TYPE);
+        let bytes = http::read_body_limited(response, self.max_body_bytes).await?;
+        let html = decode::decode(&bytes, content_type.as_deref()).text;
+
+        Ok(scrape::scrape(&html, &page_url, rules)?)
+    }
+
+    /// Store a feed's metadata, schedule and entries.
+    ///
+    /// Shared by every source and by WebSub pushes. The document's non-fatal
+    /// errors are passed on in the result, with any from storing the entries.
+    async fn store(
+        &self,
+        feed: &Feed,
+        feed_id: Uuid,
+        document: SourceDocument,
+    ) -> Result<FetchResult, FetchError> {
+        let SourceDocument {
+            metadata,
+            hints,
+            hub,
+            entries,
+            mut errors,
+            validators: _,
+        } = document;
+
+        // Keep the feed's title, site link, description etc. in sync
+        feeds::update_feed_metadata(
+            &self.pool,
+            feed_id,
+            metadata.title.as_deref(),
+            metadata.site_url.as_deref(),
+            metadata.description.as_deref(),
+            metadata.language.as_deref(),
+            metadata.icon_url.as_deref(),
+        )
+        .await?;
+
+        // Subscribe to the feed's hub so new entries are pushed to us (not for
+        // private feeds: the hub can't fetch them without the credentials)
+        if let Some(base) = &self.public_base_url
+            && feed.owner_user_id.is_none()
+            && let Some(links) = hub
+            && let Err(e) =
+                websub::ensure_subscription(&self.client, &self.guard, &self.pool, base, feed_id, &links)
+                    .await
+        {
+            errors.push(format!("WebSub subscription to {} failed: {}", links.hub, e));
+        }
+
+        // Schedule the next fetch from the feed's posting frequency and hints
+        let published: Vec<_> = entries.iter().filter_map(|entry| entry.published_at).collect();
+        let mut interval = schedule::compute_interval_minutes(
+            &published,
+            &hints,
+            self.min_interval_minutes,
+            self.max_interval_minutes,
+        );
+        // Pushed feeds are only polled as a fallback
+        if crate::db::websub::is_active(&self.pool, feed_id).await? {
+            interval = self.max_interval_minutes;
+        }
+        feeds::update_schedule(
+            &self.pool,
+            feed_id,
+            interval as i32,
+            schedule::next_fetch_at(Utc::now(), interval, &hints),
+            &hints,
+        )
+        .await?;
+
+        // Pick how entries are identified, switching `auto` feeds whose guids
+        // rotate over to link identity
+        let mut strategy = IdentityStrategy::of(feed);
+        if strategy == IdentityStrategy::Auto {
+            let identities: Vec<_> = entries
+                .iter()
+                .map(SourceEntry::identity)
+                .filter(|identity| identity.guid.is_some())
+                .collect();
+            let guids: Vec<String> = identities
+                .iter()
+                .filter_map(|identity| identity.guid.map(str::to_string))
+                .collect();
+            let links: Vec<String> = identities
+                .iter()
+                .map(|identity| identity.link_key().to_string())
+                .collect();
+
+            let rotated = articles::count_rotated_guids(&self.pool, feed_id, &guids, &links).await?;
+            if identity::guids_look_unstable(rotated as usize, &identities) {
+                let merged =
+                    identity::set_strategy(&self.pool, feed_id, IdentityStrategy::Link).await?;
+                tracing::warn!(
+                    feed_id = %feed_id,
+                    rotated_entries = rotated,
+                    merged_duplicates = merged,
+                    "Feed guids look unstable, switching to link identity"
+                );
+                strategy = IdentityStrategy::Link;
+            }
+        }
+
+        // Relative links and images resolve against the feed's site
+        let base_url = metadata
+            .site_url
+            .as_deref()
+            .or(feed.site_url.as_deref())
+            .unwrap_or(&feed.url);
+
+        // Articles too short to detect a language in get the feed's
+        let feed_language = metadata.language.as_deref().or(feed.language.as_deref());
+
+        let mut new_articles = 0;
+        let mut updated_articles = 0;
+        let mut unchanged_articles = 0;
+        let mut to_extract = Vec::new();
+
+        // Process each entry in the feed
+        for entry in &entries {
+            let guid = entry.identity().key(strategy);
+            let summary = sanitize::sanitize_field(entry.summary.as_deref(), Some(base_url));
+            let content = sanitize::sanitize_field(entry.content.as_deref(), Some(base_url));
+            let language = language::article_language(
+                &entry.title,
+                content.as_deref().or(summary.as_deref()),
+                feed_language,
+            );
+
+            let article = NewArticle {
+                feed_id,
+                title: &entry.title,
+                url: &entry.url,
+                author: entry.author.as_deref(),
+                summary: summary.as_deref(),
+                content: content.as_deref(),
+                raw_summary: entry.summary.as_deref(),
+                raw_content: entry.content.as_deref(),
+                published_at: entry.published_at,
+                guid: Some(&guid),
+                canonical_url: entry.canonical_url.as_deref(),
+                language: language.as_deref(),
+                reading_time: ReadingTime::of_entry(content.as_deref(), summary.as_deref()),
+            };
+
+            // Store the article, skipping entries whose content hasn't changed
+            let article_id = match articles::create_article(&self.pool, &article).await {
+                Ok(UpsertStatus::Inserted(id)) => {
+                    new_articles += 1;
+                    if feed.fetch_full_content && to_extract.len() < MAX_EXTRACTIONS_PER_FETCH {
+                        to_extract.push((id, entry.url.clone()));
+                    }
+                    id
+                }
+                Ok(UpsertStatus::Updated(id)) => {
+                    updated_articles += 1;
+                    id
+                }
+                Ok(UpsertStatus::Unchanged) => {
+                    unchanged_articles += 1;
+                    continue;
                 }
                 Err(e) => {
-                    errors.push(format!("Failed to create article '{}': {}", title, e));
+                    errors.push(format!("Failed to create article '{}': {}", entry.title, e));
+                    continue;
                 }
+            };
+
+            let media = media::with_inline_image(&entry.media, content.as_deref().or(summary.as_deref()));
+            if let Err(e) = articles::replace_media(&self.pool, article_id, &media).await {
+                errors.push(format!("Failed to store media for '{}': {}", entry.title, e));
             }
         }
 
-        // Update the feed's last_fetched_at timestamp
-        feeds::update_last_fetched(&self.pool, feed_id).await?;
+        // Pull full text for new articles of summary-only feeds
+        if !to_extract.is_empty() {
+            self.queue_extractions(feed_id, to_extract);
+        }
 
         Ok(FetchResult {
             feed_id,
-            articles_fetched,
+            moved_to: None,
+            new_articles,
+            updated_articles,
+            unchanged_articles,
+            not_modified: false,
             errors,
         })
     }
 
+    /// What article pages are downloaded with; cheap to move into a background task.
+    fn page_extractor(&self) -> PageExtractor {
+        PageExtractor {
+            client: self.client.clone(),
+            pool: self.pool.clone(),
+            guard: self.guard.clone(),
+            max_body_bytes: self.max_body_bytes,
+        }
+    }
+
+    /// Extract the full content of a feed's new articles in a background job.
+    ///
+    /// The job runs on its own task, so page downloads neither delay the feed
+    /// fetch nor hold its host slot; each download waits for a slot on the
+    /// page's own host. Failures are logged; the articles are extracted on
+    /// demand when read instead.
+    fn queue_extractions(&self, feed_id: Uuid, articles: Vec<(Uuid, String)>) {
+        let extractor = self.page_extractor();
+        let permits = self.extraction_permits.clone();
+        let host_limiter = self.host_limiter.clone();
+
+        tokio::spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("extraction semaphore is never closed");
+
+            for (article_id, url) in articles {
+                let _host_permit = host_limiter.acquire(&host_key(&url)).await;
+                if let Err(e) = extractor.extract(article_id, &url).await {
+                    tracing::warn!(
+                        feed_id = %feed_id,
+                        article_id = %article_id,
+                        url = %url,
+                        error = %e,
+                        "Failed to extract full content"
+                    );
+                }
+            }
+        });
+    }
+
+    /// Download an article's page, extract its main content and store it.
+    ///
+    /// A page where nothing could be extracted is recorded as attempted (with no
+    /// content) so it isn't downloaded again.
+    ///
+    /// # Returns
+    /// The extracted content, or `None` if the page had none.
+    pub async fn extract_article(
+        &self,
+        article_id: Uuid,
+        url: &str,
+    ) -> Result<Option<Extracted>, FetchError> {
+        self.page_extractor().extract(article_id, url).await
+    }
+
     /// Fetch all feeds that a user is subscribed to.
     ///
     /// If one feed fails, continues with the remaining feeds.
@@ -182,6 +886,7 @@ impl FeedFetcher {
     /// A vector of `FetchResult` for each feed that was successfully fetched.
     /// Feeds that failed completely will have their errors logged but won't
     /// prevent other feeds from being fetched.
+    #[allow(dead_code)]
     pub async fn fetch_all_user_feeds(
         &self,
         user_id: Uuid,
@@ -192,12 +897,13 @@ impl FeedFetcher {
         let mut results = Vec::new();
 
         for feed in user_feeds {
-            match self.fetch_feed(feed.id, &feed.url).await {
+            match self.fetch_feed(&feed).await {
                 Ok(result) => {
                     tracing::info!(
                         feed_id = %feed.id,
                         feed_title = %feed.title,
-                        articles_fetched = result.articles_fetched,
+                        new_articles = result.new_articles,
+                        updated_articles = result.updated_articles,
                         "Successfully fetched feed"
                     );
                     results.push(result);
@@ -213,7 +919,11 @@ impl FeedFetcher {
                     // Include a result with zero articles and the error
                     results.push(FetchResult {
                         feed_id: feed.id,
-                        articles_fetched: 0,
+                        moved_to: None,
+                        new_articles: 0,
+                        updated_articles: 0,
+                        unchanged_articles: 0,
+                        not_modified: false,
                         errors: vec![format!("Feed fetch failed: {}", e)],
                     });
                 }
@@ -224,6 +934,46 @@ impl FeedFetcher {
     }
 }
 
+/// Downloads article pages and stores their extracted content.
+struct PageExtractor {
+    client: Client,
+    pool: PgPool,
+    guard: UrlGuard,
+    max_body_bytes: usize,
+}
+
+impl PageExtractor {
+    /// See [`FeedFetcher::extract_article`].
+    async fn extract(&self, article_id: Uuid, url: &str) -> Result<Option<Extracted>, FetchError> {
+        let followed =
+            http::get_following_redirects(&self.client, &self.guard, url, &HeaderMap::new()).await?;
+        let page_url = followed.final_url;
+        let response = followed.response.error_for_status()?;
+        let is_html = http::header_string(response.headers(), CONTENT_TYPE)
+            .is_none_or(|content_type| content_type.contains("html"));
+        let body = http::read_body_limited(response, self.max_body_bytes).await?;
+
+        // Non-HTML pages (PDFs, images) are recorded as having nothing to extract
+        let page = is_html.then(|| String::from_utf8_lossy(&body));
+        let extracted = page
+            .as_deref()
+            .and_then(|page| extractor::extract(page, &page_url));
+        let canonical_url = page
+            .as_deref()
+            .and_then(|page| canonical::canonical_for_page(page, &page_url));
+        articles::store_extracted(
+            &self.pool,
+            article_id,
+            extracted.as_ref().map(|e| e.html.as_str()),
+            extracted.as_ref().map(|e| e.text.as_str()),
+            canonical_url.as_deref(),
+        )
+        .await?;
+
+        Ok(extracted)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,10 +982,22 @@ mod tests {
     fn test_fetch_error_display() {
         let http_err = FetchError::HttpError(
             reqwest::Client::new()
-                .get("invalid://url")
+                .get("not a url")
                 .build()
                 .unwrap_err(),
         );
         assert!(http_err.to_string().contains("HTTP error"));
     }
+
+    #[test]
+    fn test_fetch_error_kind() {
+        assert_eq!(FetchError::Gone.kind(), "gone");
+        assert_eq!(FetchError::ResponseTooLarge(1024).kind(), "too_large");
+        assert_eq!(
+            FetchError::Blocked(BlockedUrl::MissingHost).kind(),
+            "blocked"
+        );
+        let parse_error = source::parse_feed(b"not a feed").unwrap_err();
+        assert_eq!(FetchError::ParseError(parse_error).kind(), "parse");
+    }
 }
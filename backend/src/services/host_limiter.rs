@@ -0,0 +1,146 @@
+//! Per-host politeness limits for outgoing feed requests.
+//!
+//! Many feeds live on the same origin (e.g. several ESPN or NPR feeds). The
+//! limiter caps how many requests run against one host at a time and spaces
+//! out consecutive request starts so we never hammer a single origin.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{self, Instant};
+use url::Url;
+
+/// Concurrency and pacing state for a single host.
+struct HostSlot {
+    permits: Arc<Semaphore>,
+    /// Earliest time the next request to this host may start.
+    next_start: tokio::sync::Mutex<Instant>,
+}
+
+/// Guard returned by [`HostLimiter::acquire`]; the host slot is released on drop.
+pub struct HostPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Limits concurrent requests and request rate per host.
+pub struct HostLimiter {
+    per_host: usize,
+    min_delay: Duration,
+    hosts: Mutex<HashMap<String, Arc<HostSlot>>>,
+}
+
+impl HostLimiter {
+    /// Create a limiter.
+    ///
+    /// # Arguments
+    /// * `per_host` - Max concurrent requests to one host (at least 1)
+    /// * `min_delay` - Minimum gap between request starts to one host
+    pub fn new(per_host: usize, min_delay: Duration) -> Self {
+        Self {
+            per_host: per_host.max(1),
+            min_delay,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait until a request to `host` may start.
+    ///
+    /// Hold the returned permit for the duration of the request.
+    pub async fn acquire(&self, host: &str) -> HostPermit {
+        let slot = {
+            let mut hosts = self.hosts.lock().expect("host limiter lock poisoned");
+            hosts
+                .entry(host.to_ascii_lowercase())
+                .or_insert_with(|| {
+                    Arc::new(HostSlot {
+                        permits: Arc::new(Semaphore::new(self.per_host)),
+                        next_start: tokio::sync::Mutex::new(Instant::now()),
+                    })
+                })
+                .clone()
+        };
+
+        let permit = slot
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("host semaphore is never closed");
+
+        // Space out request starts; waiters queue on this lock in order
+        let mut next_start = slot.next_start.lock().await;
+        time::sleep_until(*next_start).await;
+        *next_start = Instant::now() + self.min_delay;
+
+        HostPermit { _permit: permit }
+    }
+}
+
+/// Key used to group feeds by origin for per-host limits.
+///
+/// Falls back to the raw URL so unparseable URLs are still limited (alone).
+pub fn host_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_key() {
+        assert_eq!(host_key("https://www.ESPN.com/espn/rss/news"), "www.espn.com");
+        assert_eq!(
+            host_key("https://www.espn.com/espn/rss/nfl/news"),
+            host_key("https://www.espn.com/espn/rss/nba/news")
+        );
+        assert_eq!(host_key("not a url"), "not a url");
+    }
+
+    #[tokio::test]
+    async fn test_same_host_requests_are_spaced() {
+        let limiter = HostLimiter::new(4, Duration::from_millis(50));
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            let _permit = limiter.acquire("example.com").await;
+        }
+
+        // First start is immediate, the next two wait one delay each
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_different_hosts_are_independent() {
+        let limiter = HostLimiter::new(1, Duration::from_millis(200));
+        let start = Instant::now();
+
+        let _a = limiter.acquire("a.example.com").await;
+        let _b = limiter.acquire("b.example.com").await;
+
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_per_host_concurrency_limit() {
+        let limiter = Arc::new(HostLimiter::new(1, Duration::ZERO));
+        let held = limiter.acquire("example.com").await;
+
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire("EXAMPLE.com").await;
+            })
+        };
+
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+
+        drop(held);
+        waiter.await.unwrap();
+    }
+}
@@ -0,0 +1,482 @@
+//! Shared HTTP helpers for outgoing feed requests.
+//!
+//! The feed client does not follow redirects on its own: we follow them here so
+//! callers can tell whether a feed moved permanently (301/308) and should have
+//! its stored URL updated, and so every hop passes the [`UrlGuard`].
+
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, IF_MODIFIED_SINCE, IF_NONE_MATCH, LOCATION,
+};
+use reqwest::{Client, Response, StatusCode};
+use url::Url;
+
+use crate::services::url_guard::{BlockedUrl, UrlGuard};
+
+/// Maximum number of redirects followed for a single request.
+const MAX_REDIRECTS: usize = 10;
+
+/// A response reached after following redirects.
+#[derive(Debug)]
+pub struct FollowedResponse {
+    /// The final (non-redirect) response.
+    pub response: Response,
+    /// URL the final response was served from.
+    pub final_url: Url,
+    /// Where the resource permanently lives, if the request was redirected by an
+    /// unbroken chain of permanent redirects starting at the requested URL.
+    /// A temporary redirect part-way keeps the last permanent target.
+    pub permanent_url: Option<Url>,
+}
+
+/// Errors that can occur while following redirects.
+#[derive(Debug)]
+pub enum RedirectError {
+    /// The request itself failed.
+    HttpError(reqwest::Error),
+    /// The starting URL or a `Location` header could not be parsed.
+    InvalidUrl(String),
+    /// More than `MAX_REDIRECTS` redirects.
+    TooManyRedirects,
+    /// The URL (or a redirect target) may not be requested.
+    Blocked(BlockedUrl),
+}
+
+impl std::fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RedirectError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            RedirectError::InvalidUrl(url) => write!(f, "Invalid redirect URL: {}", url),
+            RedirectError::TooManyRedirects => write!(f, "Too many redirects"),
+            RedirectError::Blocked(e) => write!(f, "Blocked URL: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RedirectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RedirectError::HttpError(e) => Some(e),
+            RedirectError::Blocked(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for RedirectError {
+    fn from(err: reqwest::Error) -> Self {
+        RedirectError::HttpError(err)
+    }
+}
+
+/// GET `url`, following redirects manually.
+///
+/// `headers` are sent with every hop. `304 Not Modified` is returned as the
+/// final response, not treated as a redirect. Each hop's URL is checked by
+/// `guard` before it is requested.
+pub async fn get_following_redirects(
+    client: &Client,
+    guard: &UrlGuard,
+    url: &str,
+    headers: &HeaderMap,
+) -> Result<FollowedResponse, RedirectError> {
+    get_following_redirects_with_credentials(client, guard, url, headers, &HeaderMap::new()).await
+}
+
+/// [`get_following_redirects`] for a URL that needs credentials.
+///
+/// `credentials` (e.g. `Authorization`) are only sent to hops on the same
+/// origin as `url`, so a redirect can't hand them to another site.
+pub async fn get_following_redirects_with_credentials(
+    client: &Client,
+    guard: &UrlGuard,
+    url: &str,
+    headers: &HeaderMap,
+    credentials: &HeaderMap,
+) -> Result<FollowedResponse, RedirectError> {
+    let mut current = Url::parse(url).map_err(|_| RedirectError::InvalidUrl(url.to_string()))?;
+    let origin = current.origin();
+    let mut permanent_url = None;
+    let mut all_permanent = true;
+
+    for _ in 0..=MAX_REDIRECTS {
+        guard.check_url(&current).map_err(RedirectError::Blocked)?;
+
+        let mut request = client.get(current.clone()).headers(headers.clone());
+        if current.origin() == origin {
+            request = request.headers(credentials.clone());
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        if !is_redirect(status) {
+            return Ok(FollowedResponse {
+                response,
+                final_url: current,
+                permanent_url,
+            });
+        }
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| RedirectError::InvalidUrl(format!("{} (missing Location)", current)))?;
+        let next = current
+            .join(location)
+            .map_err(|_| RedirectError::InvalidUrl(location.to_string()))?;
+
+        all_permanent &= matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        );
+        if all_permanent {
+            permanent_url = Some(next.clone());
+        }
+
+        current = next;
+    }
+
+    Err(RedirectError::TooManyRedirects)
+}
+
+/// Errors that can occur while reading a response body.
+#[derive(Debug)]
+pub enum BodyError {
+    /// Reading the body failed.
+    HttpError(reqwest::Error),
+    /// The body is larger than the limit (in bytes).
+    TooLarge(usize),
+}
+
+impl std::fmt::Display for BodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            BodyError::TooLarge(limit) => write!(f, "Response larger than {} bytes", limit),
+        }
+    }
+}
+
+impl std::error::Error for BodyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BodyError::HttpError(e) => Some(e),
+            BodyError::TooLarge(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for BodyError {
+    fn from(err: reqwest::Error) -> Self {
+        BodyError::HttpError(err)
+    }
+}
+
+/// Read a response body, giving up once it exceeds `max_bytes` (without
+/// downloading the rest).
+pub async fn read_body_limited(mut response: Response, max_bytes: usize) -> Result<Vec<u8>, BodyError> {
+    let declared = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > max_bytes as u64) {
+        return Err(BodyError::TooLarge(max_bytes));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(BodyError::TooLarge(max_bytes));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+/// Statuses that carry a `Location` to follow.
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+/// Build the conditional request headers for a feed's stored validators.
+pub fn conditional_headers(etag: Option<&str>, last_modified: Option<&str>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    if let Some(value) = etag.and_then(|v| HeaderValue::from_str(v).ok()) {
+        headers.insert(IF_NONE_MATCH, value);
+    }
+    if let Some(value) = last_modified.and_then(|v| HeaderValue::from_str(v).ok()) {
+        headers.insert(IF_MODIFIED_SINCE, value);
+    }
+
+    headers
+}
+
+/// Read a response header as an owned string, ignoring non-ASCII values.
+pub fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::AUTHORIZATION;
+    use axum::{
+        Router,
+        http::{StatusCode as AxumStatus, header},
+        routing::get,
+    };
+    use tokio::net::TcpListener;
+
+    async fn serve(router: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    fn redirect(
+        status: AxumStatus,
+        to: &'static str,
+    ) -> (AxumStatus, [(header::HeaderName, &'static str); 1]) {
+        (status, [(header::LOCATION, to)])
+    }
+
+    fn no_redirect_client() -> Client {
+        Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+    }
+
+    /// The test servers listen on loopback, which is blocked by default.
+    fn loopback_guard() -> UrlGuard {
+        UrlGuard::from_allowlist("127.0.0.1").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_permanent_redirect_chain() {
+        let router = Router::new()
+            .route(
+                "/old",
+                get(|| async { redirect(AxumStatus::MOVED_PERMANENTLY, "/older") }),
+            )
+            .route(
+                "/older",
+                get(|| async { redirect(AxumStatus::PERMANENT_REDIRECT, "/new") }),
+            )
+            .route("/new", get(|| async { "feed" }));
+        let base = serve(router).await;
+
+        let followed = get_following_redirects(
+            &no_redirect_client(),
+            &loopback_guard(),
+            &format!("{}/old", base),
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(followed.response.status(), StatusCode::OK);
+        assert_eq!(followed.final_url.as_str(), format!("{}/new", base));
+        assert_eq!(
+            followed.permanent_url.unwrap().as_str(),
+            format!("{}/new", base)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_temporary_redirect_is_not_permanent() {
+        let router = Router::new()
+            .route("/a", get(|| async { redirect(AxumStatus::FOUND, "/b") }))
+            .route("/b", get(|| async { "feed" }));
+        let base = serve(router).await;
+
+        let followed = get_following_redirects(
+            &no_redirect_client(),
+            &loopback_guard(),
+            &format!("{}/a", base),
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(followed.final_url.as_str(), format!("{}/b", base));
+        assert!(followed.permanent_url.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_temporary_hop_keeps_last_permanent_target() {
+        let router = Router::new()
+            .route(
+                "/a",
+                get(|| async { redirect(AxumStatus::MOVED_PERMANENTLY, "/b") }),
+            )
+            .route(
+                "/b",
+                get(|| async { redirect(AxumStatus::TEMPORARY_REDIRECT, "/c") }),
+            )
+            .route("/c", get(|| async { "feed" }));
+        let base = serve(router).await;
+
+        let followed = get_following_redirects(
+            &no_redirect_client(),
+            &loopback_guard(),
+            &format!("{}/a", base),
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(followed.final_url.as_str(), format!("{}/c", base));
+        assert_eq!(
+            followed.permanent_url.unwrap().as_str(),
+            format!("{}/b", base)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loop_errors() {
+        let router = Router::new().route(
+            "/loop",
+            get(|| async { redirect(AxumStatus::FOUND, "/loop") }),
+        );
+        let base = serve(router).await;
+
+        let result = get_following_redirects(
+            &no_redirect_client(),
+            &loopback_guard(),
+            &format!("{}/loop", base),
+            &HeaderMap::new(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(RedirectError::TooManyRedirects)));
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_private_address_is_blocked() {
+        let router = Router::new().route(
+            "/feed",
+            get(|| async { redirect(AxumStatus::FOUND, "http://169.254.169.254/latest/meta-data/") }),
+        );
+        let base = serve(router).await;
+
+        let result = get_following_redirects(
+            &no_redirect_client(),
+            &loopback_guard(),
+            &format!("{}/feed", base),
+            &HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(RedirectError::Blocked(BlockedUrl::PrivateAddress(_)))
+        ));
+
+        // Loopback itself is blocked without the allowlist entry
+        let result = get_following_redirects(
+            &no_redirect_client(),
+            &UrlGuard::default(),
+            &format!("{}/feed", base),
+            &HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(RedirectError::Blocked(_))));
+    }
+
+    #[tokio::test]
+    async fn test_credentials_stay_on_origin() {
+        async fn echo_auth(headers: axum::http::HeaderMap) -> String {
+            headers
+                .get(header::AUTHORIZATION)
+                .map(|v| v.to_str().unwrap().to_string())
+                .unwrap_or_default()
+        }
+
+        let other = serve(Router::new().route("/echo", get(echo_auth))).await;
+        let elsewhere = format!("{}/echo", other);
+        let router = Router::new()
+            .route("/echo", get(echo_auth))
+            .route("/same", get(|| async { redirect(AxumStatus::FOUND, "/echo") }))
+            .route(
+                "/cross",
+                get(move || async move { (AxumStatus::FOUND, [(header::LOCATION, elsewhere)]) }),
+            );
+        let base = serve(router).await;
+
+        let mut credentials = HeaderMap::new();
+        credentials.insert(AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let fetch = |path: &'static str| {
+            let url = format!("{}{}", base, path);
+            let credentials = credentials.clone();
+            async move {
+                let followed = get_following_redirects_with_credentials(
+                    &no_redirect_client(),
+                    &loopback_guard(),
+                    &url,
+                    &HeaderMap::new(),
+                    &credentials,
+                )
+                .await
+                .unwrap();
+                followed.response.text().await.unwrap()
+            }
+        };
+
+        assert_eq!(fetch("/same").await, "Bearer secret");
+        assert_eq!(fetch("/cross").await, "");
+    }
+
+    #[tokio::test]
+    async fn test_read_body_limited() {
+        let router = Router::new().route("/big", get(|| async { "x".repeat(1000) }));
+        let base = serve(router).await;
+        let fetch = || async {
+            no_redirect_client()
+                .get(format!("{}/big", base))
+                .send()
+                .await
+                .unwrap()
+        };
+
+        assert_eq!(read_body_limited(fetch().await, 1000).await.unwrap().len(), 1000);
+        assert!(matches!(
+            read_body_limited(fetch().await, 999).await,
+            Err(BodyError::TooLarge(999))
+        ));
+    }
+
+    #[test]
+    fn test_conditional_headers_with_validators() {
+        let headers = conditional_headers(
+            Some("\"abc123\""),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(headers.get(IF_NONE_MATCH).unwrap(), "\"abc123\"");
+        assert_eq!(
+            headers.get(IF_MODIFIED_SINCE).unwrap(),
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+    }
+
+    #[test]
+    fn test_conditional_headers_without_validators() {
+        let headers = conditional_headers(None, None);
+        assert!(headers.is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+//! Stable article identity.
+//!
+//! Articles are unique per `(feed_id, guid)`, but not every feed has usable
+//! guids: RSS items may have none (feed-rs would synthesize one from the link
+//! and title, which changes whenever the title is edited) and some publishers
+//! rotate guids on every edit. Each feed therefore has an identity strategy
+//! that decides what is stored in `articles.guid`:
+//!
+//! - `guid`: the publisher's guid (a hash of the entry when it has none)
+//! - `link`: the entry's canonical link
+//! - `hash`: a hash of link + title + publication time
+//! - `auto`: the guid when the publisher supplies one, the link otherwise;
+//!   switches the feed to `link` once guids are seen rotating
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use siphasher::sip128::{Hasher128, SipHasher};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::db::{articles, feeds, maintenance};
+use crate::models::feed::Feed;
+
+/// Entries seen under a new guid for a link already stored before a feed's
+/// guids are considered unstable.
+const MIN_ROTATED_ENTRIES: usize = 2;
+
+/// Maintenance task re-keying articles stored under feed-rs generated ids.
+const REKEY_TASK: &str = "rekey_generated_guids";
+
+/// SipHash keys feed-rs derives entry ids with.
+const FEED_RS_ID_KEYS: (u64, u64) = (0x5d78_4074_2887_2d60, 0x90ee_ca4c_90a5_e228);
+
+/// How a feed's articles are identified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentityStrategy {
+    Auto,
+    Guid,
+    Link,
+    Hash,
+}
+
+impl IdentityStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdentityStrategy::Auto => "auto",
+            IdentityStrategy::Guid => "guid",
+            IdentityStrategy::Link => "link",
+            IdentityStrategy::Hash => "hash",
+        }
+    }
+
+    /// The strategy stored on a feed (unknown values fall back to `auto`).
+    pub fn of(feed: &Feed) -> Self {
+        feed.identity_strategy.parse().unwrap_or(IdentityStrategy::Auto)
+    }
+}
+
+impl FromStr for IdentityStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(IdentityStrategy::Auto),
+            "guid" => Ok(IdentityStrategy::Guid),
+            "link" => Ok(IdentityStrategy::Link),
+            "hash" => Ok(IdentityStrategy::Hash),
+            other => Err(format!(
+                "Unknown identity strategy '{}' (expected auto, guid, link or hash)",
+                other
+            )),
+        }
+    }
+}
+
+/// The parts of an entry its identity can be derived from.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryIdentity<'a> {
+    /// The publisher's guid; `None` when the feed didn't provide one.
+    pub guid: Option<&'a str>,
+    pub link: &'a str,
+    pub canonical_url: Option<&'a str>,
+    pub title: &'a str,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+impl EntryIdentity<'_> {
+    /// The key stored in `articles.guid` under `strategy`.
+    pub fn key(&self, strategy: IdentityStrategy) -> String {
+        match strategy {
+            IdentityStrategy::Guid => self.guid.map(str::to_string).unwrap_or_else(|| self.hash_key()),
+            IdentityStrategy::Link => self.link_key().to_string(),
+            IdentityStrategy::Hash => self.hash_key(),
+            IdentityStrategy::Auto => self
+                .guid
+                .map(str::to_string)
+                .unwrap_or_else(|| self.link_key().to_string()),
+        }
+    }
+
+    /// The entry's link, canonicalized when possible.
+    pub fn link_key(&self) -> &str {
+        self.canonical_url.unwrap_or(self.link)
+    }
+
+    fn hash_key(&self) -> String {
+        let published_at = self.published_at.map(|p| p.to_rfc3339()).unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        for part in [self.link_key(), self.title, &published_at] {
+            hasher.update(part.as_bytes());
+            hasher.update([0u8]);
+        }
+
+        format!("sha256:{}", hex::encode(hasher.finalize()))
+    }
+}
+
+/// Whether a fetch shows the feed's guids rotating: several entries carry a
+/// guid we haven't stored for a link we have. Feeds that reuse one link for
+/// many entries (e.g. their homepage) can't be keyed by link, so they never
+/// count as rotating.
+pub fn guids_look_unstable(rotated_entries: usize, entries: &[EntryIdentity<'_>]) -> bool {
+    let mut links: Vec<&str> = entries.iter().map(|e| e.link_key()).collect();
+    links.sort_unstable();
+    links.dedup();
+    let links_unique = links.len() == entries.len();
+
+    links_unique && rotated_entries >= MIN_ROTATED_ENTRIES
+}
+
+/// Merge a feed's duplicate articles and re-key the survivors.
+///
+/// Articles that map to the same key under the feed's strategy are merged into
+/// the first stored copy; read/saved state from the copies is carried over.
+/// For `link` and `hash` feeds the survivor's guid is then rewritten to its key
+/// so future fetches match it. `auto` feeds only merge copies sharing both link
+/// and publication time (their stored guids are kept), and `guid` feeds cannot
+/// have duplicates.
+///
+/// # Returns
+/// The number of duplicate articles removed.
+pub async fn merge_duplicates(pool: &PgPool, feed: &Feed) -> Result<usize, sqlx::Error> {
+    let strategy = IdentityStrategy::of(feed);
+    if strategy == IdentityStrategy::Guid {
+        return Ok(0);
+    }
+
+    // Oldest first, so the first article seen per key is the one kept
+    let rows = articles::list_identity_rows(pool, feed.id).await?;
+    let mut groups: Vec<(String, Vec<&articles::IdentityRow>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for row in &rows {
+        let identity = EntryIdentity {
+            guid: None,
+            link: &row.url,
+            canonical_url: row.canonical_url.as_deref(),
+            title: &row.title,
+            published_at: row.published_at,
+        };
+        let key = match strategy {
+            IdentityStrategy::Auto => match row.published_at {
+                Some(published_at) => format!("{}\n{}", identity.link_key(), published_at.to_rfc3339()),
+                None => continue,
+            },
+            _ => identity.key(strategy),
+        };
+
+        match index.get(&key) {
+            Some(&i) => groups[i].1.push(row),
+            None => {
+                index.insert(key.clone(), groups.len());
+                groups.push((key, vec![row]));
+            }
+        }
+    }
+
+    let mut removed = 0;
+    for (key, group) in groups {
+        let (keep, duplicates) = group.split_first().expect("groups are never empty");
+        let new_guid = match strategy {
+            IdentityStrategy::Auto => None,
+            _ if keep.guid.as_deref() == Some(key.as_str()) => None,
+            _ => Some(key.as_str()),
+        };
+        if duplicates.is_empty() && new_guid.is_none() {
+            continue;
+        }
+
+        let duplicate_ids: Vec<Uuid> = duplicates.iter().map(|row| row.id).collect();
+        articles::merge_articles(pool, keep.id, &duplicate_ids, new_guid).await?;
+        removed += duplicate_ids.len();
+    }
+
+    Ok(removed)
+}
+
+/// Switch a feed to a new identity strategy and re-key its stored articles.
+///
+/// # Returns
+/// The number of duplicate articles removed while re-keying.
+pub async fn set_strategy(
+    pool: &PgPool,
+    feed_id: Uuid,
+    strategy: IdentityStrategy,
+) -> Result<usize, sqlx::Error> {
+    let feed = feeds::update_identity_strategy(pool, feed_id, strategy.as_str()).await?;
+    merge_duplicates(pool, &feed).await
+}
+
+/// Re-key articles stored under an id feed-rs made up, once.
+///
+/// Before identity strategies, an entry without a guid was stored under the id
+/// feed-rs derived from its link and title. Those entries are now keyed by
+/// their feed's strategy, so without re-keying the next fetch would store them
+/// again. Articles of `auto` and `guid` feeds whose guid is exactly that
+/// derived id get their new key; if the entry was already stored again under
+/// it, the two copies are merged into the older one. Runs at startup until it
+/// has completed once.
+///
+/// # Returns
+/// The number of articles re-keyed (`None` if it had already run).
+pub async fn rekey_generated_guids(pool: &PgPool) -> Result<Option<usize>, sqlx::Error> {
+    if !maintenance::is_pending(pool, REKEY_TASK).await? {
+        return Ok(None);
+    }
+
+    let mut rekeyed = 0;
+    for row in articles::list_generated_guid_candidates(pool).await? {
+        // feed-rs hashed the entry's title when it had one; "Untitled" is ours
+        let generated = feed_rs_generated_id(&row.url, Some(&row.title)) == row.guid
+            || (row.title == "Untitled" && feed_rs_generated_id(&row.url, None) == row.guid);
+        if !generated {
+            continue;
+        }
+
+        let identity = EntryIdentity {
+            guid: None,
+            link: &row.url,
+            canonical_url: row.canonical_url.as_deref(),
+            title: &row.title,
+            published_at: row.published_at,
+        };
+        let strategy = row.identity_strategy.parse().unwrap_or(IdentityStrategy::Auto);
+        let key = identity.key(strategy);
+
+        match articles::find_by_guid(pool, row.feed_id, &key).await? {
+            // Another copy, stored earlier, already has the key: keep that one
+            Some((existing, stored_at)) if stored_at <= row.created_at => {
+                articles::merge_articles(pool, existing, &[row.id], None).await?;
+            }
+            // Stored again under the key since the upgrade: keep this copy
+            Some((existing, _)) => {
+                articles::merge_articles(pool, row.id, &[existing], Some(&key)).await?;
+            }
+            None => articles::merge_articles(pool, row.id, &[], Some(&key)).await?,
+        }
+        rekeyed += 1;
+    }
+
+    maintenance::mark_completed(pool, REKEY_TASK).await?;
+    Ok(Some(rekeyed))
+}
+
+/// The id feed-rs gives an entry without one: a SipHash-1-3 (128 bit) of its
+/// first link and its title.
+fn feed_rs_generated_id(link: &str, title: Option<&str>) -> String {
+    let mut hasher = SipHasher::new_with_keys(FEED_RS_ID_KEYS.0, FEED_RS_ID_KEYS.1);
+    hasher.write(link.as_bytes());
+    if let Some(title) = title {
+        hasher.write(title.as_bytes());
+    }
+    let hash = hasher.finish128();
+
+    format!("{:x}{:x}", hash.h1, hash.h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+    use chrono::TimeZone;
+
+    fn entry<'a>(guid: Option<&'a str>, link: &'a str) -> EntryIdentity<'a> {
+        EntryIdentity {
+            guid,
+            link,
+            canonical_url: None,
+            title: "Title",
+            published_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_strategy_parsing() {
+        assert_eq!("LINK".parse(), Ok(IdentityStrategy::Link));
+        assert!("uuid".parse::<IdentityStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_keys_per_strategy() {
+        let with_guid = entry(Some("tag:example.com,2024:1"), "https://example.com/a");
+        let without_guid = entry(None, "https://example.com/a");
+
+        assert_eq!(with_guid.key(IdentityStrategy::Auto), "tag:example.com,2024:1");
+        assert_eq!(without_guid.key(IdentityStrategy::Auto), "https://example.com/a");
+        assert_eq!(with_guid.key(IdentityStrategy::Link), "https://example.com/a");
+        assert!(with_guid.key(IdentityStrategy::Hash).starts_with("sha256:"));
+        assert_eq!(
+            without_guid.key(IdentityStrategy::Guid),
+            without_guid.key(IdentityStrategy::Hash)
+        );
+    }
+
+    #[test]
+    fn test_link_key_prefers_canonical_url() {
+        let identity = EntryIdentity {
+            canonical_url: Some("https://example.com/a"),
+            ..entry(None, "https://example.com/a?utm_source=rss")
+        };
+        assert_eq!(identity.key(IdentityStrategy::Link), "https://example.com/a");
+    }
+
+    #[test]
+    fn test_hash_key_changes_with_title() {
+        let original = entry(None, "https://example.com/a");
+        let edited = EntryIdentity {
+            title: "Title (updated)",
+            ..original
+        };
+        assert_ne!(
+            original.key(IdentityStrategy::Hash),
+            edited.key(IdentityStrategy::Hash)
+        );
+    }
+
+    #[test]
+    fn test_guids_look_unstable() {
+        let entries = [
+            entry(Some("1"), "https://example.com/a"),
+            entry(Some("2"), "https://example.com/b"),
+            entry(Some("3"), "https://example.com/c"),
+        ];
+        assert!(guids_look_unstable(2, &entries));
+        assert!(!guids_look_unstable(1, &entries));
+
+        // Every entry links to the homepage: link identity would collapse them
+        let shared = [
+            entry(Some("1"), "https://example.com/"),
+            entry(Some("2"), "https://example.com/"),
+        ];
+        assert!(!guids_look_unstable(2, &shared));
+    }
+
+    #[test]
+    fn test_feed_rs_generated_id() {
+        let feed = feed_rs::parser::parse(
+            &br#"<rss version="2.0"><channel><title>T</title>
+              <item><title>No guid</title><link>https://example.com/a</link></item>
+              <item><link>https://example.com/b</link></item>
+            </channel></rss>"#[..],
+        )
+        .unwrap();
+
+        assert_eq!(feed.entries[0].id, feed_rs_generated_id("https://example.com/a", Some("No guid")));
+        assert_eq!(feed.entries[1].id, feed_rs_generated_id("https://example.com/b", None));
+    }
+
+    #[sqlx::test]
+    async fn test_rekey_generated_guids(pool: PgPool) {
+        let feed_id = test_support::insert_feed(&pool, "https://example.com/feed").await;
+        let insert = |guid: String, age: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, Uuid>(
+                    "INSERT INTO articles (feed_id, title, url, guid, created_at)
+                     VALUES ($1, 'No guid', 'https://example.com/a', $2, NOW() - $3::interval)
+                     RETURNING id",
+                )
+                .bind(feed_id)
+                .bind(guid)
+                .bind(age)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            }
+        };
+
+        // Stored before the upgrade, then again under the new key after it
+        let original = insert(feed_rs_generated_id("https://example.com/a", Some("No guid")), "2 days").await;
+        insert("https://example.com/a".to_string(), "1 hour").await;
+        // A publisher guid that happens to be hex is left alone
+        sqlx::query(
+            "INSERT INTO articles (feed_id, title, url, guid) VALUES ($1, 'Hex', 'https://example.com/h', 'abc123')",
+        )
+        .bind(feed_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(rekey_generated_guids(&pool).await.unwrap(), Some(1));
+        assert_eq!(rekey_generated_guids(&pool).await.unwrap(), None);
+
+        let rows: Vec<(Uuid, String)> = sqlx::query_as("SELECT id, guid FROM articles ORDER BY guid")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            [(rows[0].0, "abc123".to_string()), (original, "https://example.com/a".to_string())]
+        );
+    }
+}
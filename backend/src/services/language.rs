@@ -0,0 +1,268 @@
+//! Article language detection.
+//!
+//! Languages are stored as lowercase ISO 639-1 codes ("en", "de"). An
+//! article's language is detected offline from its title and text; when the
+//! text is too short or the detector isn't confident, the feed's declared
+//! language (`<language>en-us</language>`, `xml:lang`) is used instead.
+
+use scraper::Html;
+use sqlx::PgPool;
+use uuid::Uuid;
+use whatlang::Lang;
+
+use crate::db::articles;
+
+/// Less text than this is not enough to detect a language reliably.
+const MIN_DETECT_CHARS: usize = 40;
+
+/// Most text handed to the detector; more doesn't make it more accurate.
+const MAX_DETECT_CHARS: usize = 2000;
+
+/// Articles processed per batch by [`detect_missing`].
+const DETECT_BATCH_SIZE: i64 = 500;
+
+/// The language of an article.
+///
+/// # Arguments
+/// * `title` - The article's title
+/// * `html` - Its content, or summary if it has none (HTML)
+/// * `feed_language` - The language the feed declares, if any
+pub fn article_language(title: &str, html: Option<&str>, feed_language: Option<&str>) -> Option<String> {
+    let mut text = title.to_string();
+    if let Some(html) = html {
+        let fragment = Html::parse_fragment(html);
+        for chunk in fragment.root_element().text() {
+            text.push(' ');
+            text.push_str(chunk);
+        }
+    }
+
+    detect(&text)
+        .map(str::to_string)
+        .or_else(|| feed_language.and_then(normalize))
+}
+
+/// Detect the language of `text`; `None` if it is too short or the detector
+/// isn't confident.
+pub fn detect(text: &str) -> Option<&'static str> {
+    let text: String = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_DETECT_CHARS)
+        .collect();
+    if text.chars().count() < MIN_DETECT_CHARS {
+        return None;
+    }
+
+    whatlang::detect(&text)
+        .filter(|info| info.is_reliable())
+        .map(|info| iso_639_1(info.lang()))
+}
+
+/// Normalize a language tag ("en-US", "pt_BR", "DE", "deu") to its ISO
+/// 639-1 code ("en", "pt", "de", "de"); `None` if it isn't one.
+///
+/// Three-letter codes are only understood for languages the detector knows,
+/// since others have no 639-1 code to compare with.
+pub fn normalize(tag: &str) -> Option<String> {
+    let code = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+    if !code.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+
+    match code.len() {
+        2 => Some(code),
+        3 => from_three_letter_code(&code).map(|lang| iso_639_1(lang).to_string()),
+        _ => None,
+    }
+}
+
+/// Normalize language codes given by a user, dropping duplicates.
+///
+/// # Errors
+/// A message naming the first code that isn't a language code.
+pub fn normalize_all<'a>(codes: impl IntoIterator<Item = &'a str>) -> Result<Vec<String>, String> {
+    let mut languages = Vec::new();
+    for code in codes {
+        let language = normalize(code).ok_or_else(|| format!("Invalid language code: {}", code))?;
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+
+    Ok(languages)
+}
+
+/// Detect the language of stored articles that have none, e.g. those stored
+/// before language detection was added.
+///
+/// # Returns
+/// The number of articles whose language was found.
+pub async fn detect_missing(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut after: Option<Uuid> = None;
+    let mut updated = 0;
+
+    loop {
+        let batch = articles::list_missing_language(pool, after, DETECT_BATCH_SIZE).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some(last.id);
+
+        for row in &batch {
+            let html = row.content.as_deref().or(row.summary.as_deref());
+            if let Some(language) = article_language(&row.title, html, row.feed_language.as_deref()) {
+                articles::update_language(pool, row.id, &language).await?;
+                updated += 1;
+            }
+        }
+    }
+
+    Ok(updated)
+}
+
+/// A language by its ISO 639-3 or 639-2 code.
+fn from_three_letter_code(code: &str) -> Option<Lang> {
+    // 639-2 bibliographic codes and macrolanguages the detector knows by another code
+    let code = match code {
+        "chi" | "zho" => "cmn",
+        "per" | "fas" => "pes",
+        "arm" => "hye",
+        "bur" => "mya",
+        "cze" => "ces",
+        "dut" => "nld",
+        "fre" => "fra",
+        "geo" => "kat",
+        "ger" => "deu",
+        "gre" => "ell",
+        "mac" => "mkd",
+        "rum" => "ron",
+        "slo" => "slk",
+        other => other,
+    };
+    Lang::from_code(code)
+}
+
+/// The ISO 639-1 code of a detected language.
+fn iso_639_1(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "nb",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_article_language() {
+        let language = article_language(
+            "Die Regierung plant neue Gesetze",
+            Some("<p>Der Bundestag hat am Donnerstag über die Reform der Schuldenbremse beraten und wird nächste Woche abstimmen.</p>"),
+            Some("en-us"),
+        );
+        assert_eq!(language.as_deref(), Some("de"));
+
+        let language = article_language(
+            "The committee met on Tuesday",
+            Some("<p>Members of the committee discussed the budget for the coming year and agreed to meet again next month.</p>"),
+            None,
+        );
+        assert_eq!(language.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn test_short_text_falls_back_to_feed_language() {
+        assert_eq!(article_language("Breves", None, Some("pt-BR")).as_deref(), Some("pt"));
+        assert_eq!(article_language("Breves", None, None), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("en-US").as_deref(), Some("en"));
+        assert_eq!(normalize(" pt_BR ").as_deref(), Some("pt"));
+        assert_eq!(normalize("DE").as_deref(), Some("de"));
+        assert_eq!(normalize("deu").as_deref(), Some("de"));
+        assert_eq!(normalize("ger").as_deref(), Some("de"));
+        assert_eq!(normalize("ENG-us").as_deref(), Some("en"));
+        assert_eq!(normalize("zho").as_deref(), Some("zh"));
+        assert_eq!(normalize("xyz"), None);
+        assert_eq!(normalize("english"), None);
+        assert_eq!(normalize("e1"), None);
+        assert_eq!(normalize(""), None);
+
+        assert_eq!(normalize_all(["en", "EN-gb", "de"]), Ok(vec!["en".to_string(), "de".to_string()]));
+        assert_eq!(normalize_all(["en", "eng"]), Ok(vec!["en".to_string()]));
+        assert!(normalize_all(["en", "english"]).is_err());
+    }
+}
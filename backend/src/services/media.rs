@@ -0,0 +1,235 @@
+//! Article media: enclosures, thumbnails and images.
+//!
+//! Podcast and image-heavy feeds carry their payload as attachments rather
+//! than in the content. feed-rs exposes RSS `<enclosure>` and MediaRSS
+//! `media:content` / `media:thumbnail` as media objects, and Atom enclosures
+//! as `rel="enclosure"` links. Entries without any image attachment fall back
+//! to the first image in their content, so cards always have a thumbnail when
+//! one exists.
+//!
+//! Media URLs are served back to the frontend as image and link sources, so
+//! only absolute http(s) URLs are kept; relative ones are resolved against the
+//! entry's link.
+
+use feed_rs::model::Entry;
+use scraper::{Html, Selector};
+use url::Url;
+
+use crate::models::article::ArticleMedia;
+
+/// URLs longer than this are dropped (they are not real media links).
+const MAX_MEDIA_URL_CHARS: usize = 2000;
+
+/// What an [`ArticleMedia`] row is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    /// An attached file (RSS enclosure, Atom enclosure link, `media:content`).
+    Enclosure,
+    /// A representative image (`media:thumbnail`).
+    Thumbnail,
+    /// The first image of the article's content.
+    InlineImage,
+}
+
+impl MediaKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaKind::Enclosure => "enclosure",
+            MediaKind::Thumbnail => "thumbnail",
+            MediaKind::InlineImage => "inline_image",
+        }
+    }
+}
+
+/// Collect an entry's enclosures and thumbnails, in feed order.
+pub fn entry_media(entry: &Entry) -> Vec<ArticleMedia> {
+    let base = entry.links.first().and_then(|link| Url::parse(&link.href).ok());
+    let mut media = Vec::new();
+
+    for object in &entry.media {
+        for content in &object.content {
+            let Some(url) = &content.url else {
+                continue;
+            };
+            media.push(ArticleMedia {
+                mime_type: content.content_type.as_ref().map(|t| t.to_string()),
+                length_bytes: content.size.and_then(|s| i64::try_from(s).ok()),
+                duration_seconds: content
+                    .duration
+                    .or(object.duration)
+                    .and_then(|d| i32::try_from(d.as_secs()).ok()),
+                width: content.width.and_then(|w| i32::try_from(w).ok()),
+                height: content.height.and_then(|h| i32::try_from(h).ok()),
+                ..new_media(MediaKind::Enclosure, url.as_str())
+            });
+        }
+
+        for thumbnail in &object.thumbnails {
+            media.push(ArticleMedia {
+                width: thumbnail.image.width.and_then(|w| i32::try_from(w).ok()),
+                height: thumbnail.image.height.and_then(|h| i32::try_from(h).ok()),
+                ..new_media(MediaKind::Thumbnail, &thumbnail.image.uri)
+            });
+        }
+    }
+
+    for link in entry.links.iter().filter(|l| l.rel.as_deref() == Some("enclosure")) {
+        media.push(ArticleMedia {
+            mime_type: link.media_type.clone(),
+            length_bytes: link.length.and_then(|l| i64::try_from(l).ok()),
+            ..new_media(MediaKind::Enclosure, &link.href)
+        });
+    }
+
+    media.retain_mut(|m| match resolve_url(&m.url, base.as_ref()) {
+        Some(url) => {
+            m.url = url;
+            true
+        }
+        None => false,
+    });
+    media
+}
+
+/// Resolve a media URL against `base`, keeping it only if it is http(s) and
+/// not overlong.
+fn resolve_url(raw: &str, base: Option<&Url>) -> Option<String> {
+    let url = match base {
+        Some(base) => base.join(raw),
+        None => Url::parse(raw),
+    }
+    .ok()?;
+
+    (matches!(url.scheme(), "http" | "https") && url.as_str().len() <= MAX_MEDIA_URL_CHARS)
+        .then(|| url.to_string())
+}
+
+/// Add the first image of `html` when `media` has no image of its own.
+///
+/// `html` should already be sanitized, so its image URLs are absolute.
+pub fn with_inline_image(media: &[ArticleMedia], html: Option<&str>) -> Vec<ArticleMedia> {
+    let mut media = media.to_vec();
+
+    if !media.iter().any(is_image)
+        && let Some(url) = html.and_then(first_image)
+    {
+        media.push(new_media(MediaKind::InlineImage, &url));
+    }
+
+    media
+}
+
+fn is_image(media: &ArticleMedia) -> bool {
+    media.kind != MediaKind::Enclosure.as_str()
+        || media.mime_type.as_deref().is_some_and(|m| m.starts_with("image/"))
+}
+
+fn first_image(html: &str) -> Option<String> {
+    let selector = Selector::parse("img[src]").expect("valid selector");
+    Html::parse_fragment(html)
+        .select(&selector)
+        .filter_map(|img| img.value().attr("src"))
+        .map(str::trim)
+        .find(|src| src.starts_with("http") && src.len() <= MAX_MEDIA_URL_CHARS)
+        .map(str::to_string)
+}
+
+fn new_media(kind: MediaKind, url: &str) -> ArticleMedia {
+    ArticleMedia {
+        kind: kind.as_str().to_string(),
+        url: url.trim().to_string(),
+        mime_type: None,
+        length_bytes: None,
+        duration_seconds: None,
+        width: None,
+        height: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> Entry {
+        feed_rs::parser::parse(xml.as_bytes()).unwrap().entries.remove(0)
+    }
+
+    #[test]
+    fn test_collects_rss_enclosures_and_thumbnails() {
+        let entry = parse(
+            r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/">
+<channel><title>Podcast</title><link>https://example.com/</link>
+<item>
+  <title>Episode 1</title><link>https://example.com/1</link>
+  <enclosure url="https://cdn.example.com/1.mp3" length="12345" type="audio/mpeg"/>
+  <media:thumbnail url="https://cdn.example.com/1.jpg" width="640" height="360"/>
+</item>
+</channel></rss>"#,
+        );
+
+        let media = entry_media(&entry);
+        let enclosure = media.iter().find(|m| m.kind == "enclosure").unwrap();
+        assert_eq!(enclosure.url, "https://cdn.example.com/1.mp3");
+        assert_eq!(enclosure.mime_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(enclosure.length_bytes, Some(12345));
+
+        let thumbnail = media.iter().find(|m| m.kind == "thumbnail").unwrap();
+        assert_eq!(thumbnail.url, "https://cdn.example.com/1.jpg");
+        assert_eq!((thumbnail.width, thumbnail.height), (Some(640), Some(360)));
+    }
+
+    #[test]
+    fn test_collects_atom_enclosure_links() {
+        let entry = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Feed</title><id>urn:feed</id>
+<updated>2024-01-01T00:00:00Z</updated>
+<entry><title>Post</title><id>urn:1</id><updated>2024-01-01T00:00:00Z</updated>
+  <link href="https://example.com/1"/>
+  <link rel="enclosure" href="https://cdn.example.com/1.m4a" type="audio/mp4" length="999"/>
+</entry></feed>"#,
+        );
+
+        let media = entry_media(&entry);
+        assert_eq!(media.len(), 1);
+        assert_eq!(media[0].mime_type.as_deref(), Some("audio/mp4"));
+        assert_eq!(media[0].length_bytes, Some(999));
+    }
+
+    #[test]
+    fn test_media_urls_are_resolved_and_checked() {
+        let entry = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/">
+<title>Feed</title><id>urn:feed</id><updated>2024-01-01T00:00:00Z</updated>
+<entry><title>Post</title><id>urn:1</id><updated>2024-01-01T00:00:00Z</updated>
+  <link href="https://example.com/posts/1"/>
+  <link rel="enclosure" href="/audio/1.m4a" type="audio/mp4"/>
+  <link rel="enclosure" href="data:audio/mp4;base64,AAAA" type="audio/mp4"/>
+  <media:thumbnail url="javascript:alert(1)"/>
+</entry></feed>"#,
+        );
+
+        let media = entry_media(&entry);
+        assert_eq!(media.len(), 1);
+        assert_eq!(media[0].url, "https://example.com/audio/1.m4a");
+    }
+
+    #[test]
+    fn test_inline_image_is_a_fallback() {
+        let html = r#"<p>Text</p><img src="https://example.com/a.jpg"><img src="https://example.com/b.jpg">"#;
+
+        let media = with_inline_image(&[], Some(html));
+        assert_eq!(media, vec![new_media(MediaKind::InlineImage, "https://example.com/a.jpg")]);
+
+        let podcast = vec![ArticleMedia {
+            mime_type: Some("audio/mpeg".to_string()),
+            ..new_media(MediaKind::Enclosure, "https://cdn.example.com/1.mp3")
+        }];
+        assert_eq!(with_inline_image(&podcast, Some(html)).len(), 2);
+
+        let thumbnail = vec![new_media(MediaKind::Thumbnail, "https://cdn.example.com/1.jpg")];
+        assert_eq!(with_inline_image(&thumbnail, Some(html)), thumbnail);
+    }
+}
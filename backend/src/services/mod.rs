@@ -1,2 +1,21 @@
+pub mod canonical;
+pub mod credentials;
+pub mod decode;
+pub mod discovery;
+pub mod extractor;
 pub mod fetcher;
+pub mod host_limiter;
+pub mod http;
+pub mod identity;
+pub mod language;
+pub mod media;
+pub mod reading_time;
+pub mod refresh;
+pub mod retention;
+pub mod sanitize;
+pub mod schedule;
+pub mod scrape;
+pub mod source;
 pub mod scheduler;
+pub mod url_guard;
+pub mod websub;
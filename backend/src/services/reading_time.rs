@@ -0,0 +1,103 @@
+//! Article word counts and estimated reading times.
+//!
+//! Words are counted over an article's plain text: the extracted full text
+//! once there is one, otherwise the text of its content (or summary) HTML.
+
+use scraper::Html;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::articles;
+
+/// Average adult reading speed for online text.
+const WORDS_PER_MINUTE: usize = 230;
+
+/// Articles processed per batch by [`count_missing`].
+const COUNT_BATCH_SIZE: i64 = 500;
+
+/// How long an article is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadingTime {
+    pub word_count: i32,
+    /// Whole minutes, rounded up; 0 only for an article without text.
+    pub minutes: i32,
+}
+
+impl ReadingTime {
+    /// The reading time of plain text.
+    pub fn of_text(text: &str) -> Self {
+        let words = text.split_whitespace().count();
+
+        ReadingTime {
+            word_count: words.try_into().unwrap_or(i32::MAX),
+            minutes: words.div_ceil(WORDS_PER_MINUTE).try_into().unwrap_or(i32::MAX),
+        }
+    }
+
+    /// The reading time of an HTML fragment's text.
+    pub fn of_html(html: &str) -> Self {
+        let fragment = Html::parse_fragment(html);
+        Self::of_text(&fragment.root_element().text().collect::<Vec<_>>().join(" "))
+    }
+
+    /// The reading time of a feed entry: its content, or its summary if it
+    /// has no content.
+    pub fn of_entry(content: Option<&str>, summary: Option<&str>) -> Self {
+        content.or(summary).map(Self::of_html).unwrap_or(Self::of_text(""))
+    }
+}
+
+/// Count the words of stored articles that have no count, e.g. those stored
+/// before word counts were added.
+///
+/// # Returns
+/// The number of articles counted.
+pub async fn count_missing(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut after: Option<Uuid> = None;
+    let mut updated = 0;
+
+    loop {
+        let batch = articles::list_missing_word_count(pool, after, COUNT_BATCH_SIZE).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some(last.id);
+
+        for row in &batch {
+            let reading_time = match &row.extracted_text {
+                Some(text) => ReadingTime::of_text(text),
+                None => ReadingTime::of_entry(row.content.as_deref(), row.summary.as_deref()),
+            };
+            articles::update_reading_time(pool, row.id, reading_time).await?;
+            updated += 1;
+        }
+    }
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reading_time_rounds_up() {
+        assert_eq!(ReadingTime::of_text(""), ReadingTime { word_count: 0, minutes: 0 });
+        assert_eq!(ReadingTime::of_text(" one  two\nthree "), ReadingTime { word_count: 3, minutes: 1 });
+
+        let words = vec!["word"; 460].join(" ");
+        assert_eq!(ReadingTime::of_text(&words), ReadingTime { word_count: 460, minutes: 2 });
+        let words = vec!["word"; 461].join(" ");
+        assert_eq!(ReadingTime::of_text(&words).minutes, 3);
+    }
+
+    #[test]
+    fn test_reading_time_of_html() {
+        let reading_time = ReadingTime::of_html("<p>Hello <b>big</b> world.</p><p>Second paragraph</p>");
+        assert_eq!(reading_time.word_count, 5);
+
+        let reading_time = ReadingTime::of_entry(None, Some("<p>Only a summary</p>"));
+        assert_eq!(reading_time.word_count, 3);
+        assert_eq!(ReadingTime::of_entry(None, None).word_count, 0);
+    }
+}
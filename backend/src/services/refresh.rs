@@ -0,0 +1,209 @@
+//! On-demand feed fetches: right after subscribing, and manual refreshes.
+//!
+//! Requests wait for their fetches up to a deadline so they still return
+//! quickly; fetches that take longer keep running in the background and their
+//! articles show up once stored. Manual refreshes are throttled per user.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::{self, Instant};
+use uuid::Uuid;
+
+use crate::models::feed::Feed;
+use crate::services::fetcher::FeedFetcher;
+use crate::services::host_limiter::{host_key, HostLimiter};
+
+/// Feeds fetched this recently are not fetched again on request.
+const RECENT_FETCH: chrono::TimeDelta = chrono::TimeDelta::minutes(1);
+
+/// Max feeds fetched at once for one refresh request.
+const MAX_CONCURRENT_REFRESHES: usize = 4;
+
+/// What a manual refresh covers, for throttling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RefreshScope {
+    Feed(Uuid),
+    All,
+}
+
+/// Per-user cooldown between manual refreshes of the same scope.
+pub struct RefreshThrottle {
+    cooldown: Duration,
+    last_refresh: Mutex<HashMap<(Uuid, RefreshScope), Instant>>,
+}
+
+impl RefreshThrottle {
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            last_refresh: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a refresh by `user_id`, unless they already refreshed `scope`
+    /// within the cooldown.
+    ///
+    /// # Returns
+    /// Whether the refresh may go ahead.
+    pub fn try_acquire(&self, user_id: Uuid, scope: RefreshScope) -> bool {
+        let now = Instant::now();
+        let mut last_refresh = self.last_refresh.lock().expect("refresh throttle lock poisoned");
+        last_refresh.retain(|_, at| now.duration_since(*at) < self.cooldown);
+
+        if last_refresh.contains_key(&(user_id, scope)) {
+            return false;
+        }
+        last_refresh.insert((user_id, scope), now);
+        true
+    }
+}
+
+/// How an on-demand fetch went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshStatus {
+    /// Fetched and stored.
+    Fetched,
+    /// The fetch failed (see `error`).
+    Failed,
+    /// Still running after the request's deadline, or already being fetched
+    /// by the scheduler; continues in the background.
+    Pending,
+    /// Fetched within the last minute, so not fetched again.
+    Skipped,
+    /// The feed answered `410 Gone` before and is no longer fetched.
+    Gone,
+    /// The feed was disabled after too many failures and is no longer fetched.
+    Disabled,
+}
+
+/// Result of refreshing one feed.
+#[derive(Debug, Serialize)]
+pub struct RefreshOutcome {
+    /// The feed's id (after following a permanent redirect, if any).
+    pub feed_id: Uuid,
+    pub status: RefreshStatus,
+    pub new_articles: usize,
+    pub updated_articles: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RefreshOutcome {
+    fn without_fetch(feed_id: Uuid, status: RefreshStatus) -> Self {
+        Self {
+            feed_id,
+            status,
+            new_articles: 0,
+            updated_articles: 0,
+            error: None,
+        }
+    }
+}
+
+/// Fetch feeds now, waiting at most `wait` for the results.
+///
+/// Feeds that are gone, disabled or were fetched within the last minute are
+/// skipped, as are feeds the scheduler is fetching right now. Fetches share the
+/// scheduler's per-host limits. Outcomes are returned in the order of `feeds`.
+pub async fn refresh_feeds(
+    fetcher: Arc<FeedFetcher>,
+    host_limiter: Arc<HostLimiter>,
+    feeds: Vec<Feed>,
+    wait: Duration,
+) -> Vec<RefreshOutcome> {
+    let deadline = Instant::now() + wait;
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_REFRESHES));
+    let recent = chrono::Utc::now() - RECENT_FETCH;
+
+    // Spawned (not scoped to this request) so slow fetches finish anyway
+    let fetches: Vec<_> = feeds
+        .into_iter()
+        .map(|feed| {
+            if feed.gone_at.is_some() {
+                return Err(RefreshOutcome::without_fetch(feed.id, RefreshStatus::Gone));
+            }
+            if feed.disabled {
+                return Err(RefreshOutcome::without_fetch(feed.id, RefreshStatus::Disabled));
+            }
+            if feed.last_fetched_at.is_some_and(|at| at > recent) {
+                return Err(RefreshOutcome::without_fetch(feed.id, RefreshStatus::Skipped));
+            }
+            let Some(claim) = fetcher.try_claim(feed.id) else {
+                return Err(RefreshOutcome::without_fetch(feed.id, RefreshStatus::Pending));
+            };
+
+            let fetcher = fetcher.clone();
+            let host_limiter = host_limiter.clone();
+            let permits = permits.clone();
+            let feed_id = feed.id;
+            let task = tokio::spawn(async move {
+                let _claim = claim;
+                // Same order as the scheduler: host slot, then request permit
+                let _host_permit = host_limiter.acquire(&host_key(&feed.url)).await;
+                let _permit = permits
+                    .acquire_owned()
+                    .await
+                    .expect("refresh semaphore is never closed");
+                fetcher.fetch_feed(&feed).await
+            });
+            Ok((feed_id, task))
+        })
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(fetches.len());
+    for fetch in fetches {
+        let (feed_id, task) = match fetch {
+            Ok(spawned) => spawned,
+            Err(outcome) => {
+                outcomes.push(outcome);
+                continue;
+            }
+        };
+
+        outcomes.push(match time::timeout_at(deadline, task).await {
+            Ok(Ok(Ok(result))) => RefreshOutcome {
+                feed_id: result.feed_id,
+                status: RefreshStatus::Fetched,
+                new_articles: result.new_articles,
+                updated_articles: result.updated_articles,
+                error: None,
+            },
+            Ok(Ok(Err(e))) => RefreshOutcome {
+                error: Some(e.to_string()),
+                ..RefreshOutcome::without_fetch(feed_id, RefreshStatus::Failed)
+            },
+            Ok(Err(e)) => RefreshOutcome {
+                error: Some(format!("Fetch task failed: {}", e)),
+                ..RefreshOutcome::without_fetch(feed_id, RefreshStatus::Failed)
+            },
+            Err(_) => RefreshOutcome::without_fetch(feed_id, RefreshStatus::Pending),
+        });
+    }
+
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_throttle_per_user_and_scope() {
+        let throttle = RefreshThrottle::new(Duration::from_millis(50));
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let feed = RefreshScope::Feed(Uuid::new_v4());
+
+        assert!(throttle.try_acquire(alice, feed));
+        assert!(!throttle.try_acquire(alice, feed));
+        assert!(throttle.try_acquire(alice, RefreshScope::All));
+        assert!(throttle.try_acquire(bob, feed));
+
+        time::sleep(Duration::from_millis(60)).await;
+        assert!(throttle.try_acquire(alice, feed));
+        assert_eq!(throttle.last_refresh.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,166 @@
+//! Article retention.
+//!
+//! Articles older than `ARTICLE_RETENTION_DAYS` are deleted, except those any
+//! user has saved and those with an AI analysis or opposing-article links
+//! (which would be deleted with them), which are kept for good. An article is
+//! old once it was stored and (if dated) published before the cutoff, so a
+//! feed's backlog isn't deleted the moment it is first fetched. Deletes run in
+//! small batches with a pause in between, so pruning a large backlog never
+//! holds locks on `articles` for long.
+
+use chrono::{TimeDelta, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::db::articles;
+
+/// Pause between delete batches, letting other writers through.
+const BATCH_PAUSE: Duration = Duration::from_millis(100);
+
+/// What a pruning run did (or, on a dry run, would do).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PruneReport {
+    pub dry_run: bool,
+    /// Articles deleted (on a dry run: that would be deleted).
+    pub deleted: u64,
+    /// Expired articles kept because a user saved them or they were analysed.
+    pub kept: i64,
+}
+
+impl std::fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verb = if self.dry_run { "Would delete" } else { "Deleted" };
+        write!(
+            f,
+            "{} {} expired article(s), kept {} saved or analysed",
+            verb, self.deleted, self.kept
+        )
+    }
+}
+
+/// Delete articles past the retention window.
+///
+/// # Arguments
+/// * `retention_days` - Age (in days) after which unsaved articles are deleted
+/// * `batch_size` - Most articles deleted per statement
+/// * `dry_run` - Only count what would be deleted
+pub async fn prune_articles(
+    pool: &PgPool,
+    retention_days: i32,
+    batch_size: i64,
+    dry_run: bool,
+) -> Result<PruneReport, sqlx::Error> {
+    let cutoff = Utc::now() - TimeDelta::days(retention_days.into());
+    let expired = articles::count_expired(pool, cutoff).await?;
+
+    if dry_run {
+        return Ok(PruneReport {
+            dry_run,
+            deleted: expired.prunable.max(0) as u64,
+            kept: expired.kept,
+        });
+    }
+
+    let batch_size = batch_size.max(1);
+    let mut deleted = 0;
+    loop {
+        let batch = articles::delete_expired_batch(pool, cutoff, batch_size).await?;
+        deleted += batch;
+        if batch < batch_size as u64 {
+            break;
+        }
+        tokio::time::sleep(BATCH_PAUSE).await;
+    }
+
+    Ok(PruneReport {
+        dry_run,
+        deleted,
+        kept: expired.kept,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    #[test]
+    fn test_prune_report_display() {
+        let report = PruneReport {
+            dry_run: false,
+            deleted: 120,
+            kept: 3,
+        };
+        assert_eq!(report.to_string(), "Deleted 120 expired article(s), kept 3 saved or analysed");
+
+        let dry_run = PruneReport {
+            dry_run: true,
+            ..report
+        };
+        assert_eq!(dry_run.to_string(), "Would delete 120 expired article(s), kept 3 saved or analysed");
+    }
+
+    #[sqlx::test]
+    async fn test_prune_keeps_saved_and_analysed_articles(pool: PgPool) {
+        let user_id = test_support::insert_user(&pool, "a@example.com").await;
+        let feed_id = test_support::insert_feed(&pool, "https://example.com/feed").await;
+
+        let mut ids = Vec::new();
+        for guid in ["plain", "saved", "analysed", "opposed", "fresh"] {
+            let age = if guid == "fresh" { "1 day" } else { "30 days" };
+            let id: uuid::Uuid = sqlx::query_scalar(
+                "INSERT INTO articles (feed_id, title, url, guid, created_at)
+                 VALUES ($1, $2, 'https://example.com/' || $2, $2, NOW() - $3::interval)
+                 RETURNING id",
+            )
+            .bind(feed_id)
+            .bind(guid)
+            .bind(age)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+        let [_, saved, analysed, opposed, fresh] = ids[..] else { unreachable!() };
+
+        sqlx::query("INSERT INTO user_articles (user_id, article_id, is_saved) VALUES ($1, $2, TRUE)")
+            .bind(user_id)
+            .bind(saved)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO article_analysis (article_id, provider) VALUES ($1, 'ollama')")
+            .bind(analysed)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO opposing_articles (source_article_id, opposing_article_id, relevance_score)
+             VALUES ($1, $2, 0.9)",
+        )
+        .bind(fresh)
+        .bind(opposed)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let dry_run = prune_articles(&pool, 7, 10, true).await.unwrap();
+        assert_eq!((dry_run.deleted, dry_run.kept), (1, 3));
+
+        let report = prune_articles(&pool, 7, 10, false).await.unwrap();
+        assert_eq!((report.deleted, report.kept), (1, 3));
+
+        let mut remaining: Vec<String> = sqlx::query_scalar("SELECT guid FROM articles")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        remaining.sort();
+        assert_eq!(remaining, ["analysed", "fresh", "opposed", "saved"]);
+        let analyses: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM article_analysis")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(analyses, 1);
+    }
+}
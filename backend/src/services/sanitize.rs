@@ -0,0 +1,257 @@
+//! Article HTML sanitization.
+//!
+//! Summaries and content come straight from publishers and are rendered as
+//! HTML by the frontend, so they are cleaned at ingestion against an allowlist
+//! of tags and attributes. Scripts, event handlers, `javascript:` URLs and
+//! tracking pixels are removed, and relative links and images are made
+//! absolute against the feed's site. The raw HTML is stored next to the
+//! sanitized copy so the rules can be re-run later.
+
+use ammonia::{Builder, UrlRelative};
+use scraper::{Html, Selector};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use url::Url;
+use uuid::Uuid;
+
+use crate::db::{articles, maintenance};
+
+/// URL schemes allowed in `href` / `src` attributes.
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Hosts that only serve tracking pixels / beacons.
+const TRACKER_HOSTS: &[&str] = &[
+    "feeds.feedburner.com",
+    "feedproxy.google.com",
+    "pixel.wp.com",
+    "stats.wordpress.com",
+    "pixel.quantserve.com",
+    "www.google-analytics.com",
+    "google-analytics.com",
+    "ad.doubleclick.net",
+    "pixel.mathtag.com",
+    "sb.scorecardresearch.com",
+];
+
+/// Articles processed per batch by [`resanitize_all`].
+const RESANITIZE_BATCH_SIZE: i64 = 500;
+
+/// Maintenance task sanitizing articles stored before ingestion-time sanitization.
+const RESANITIZE_TASK: &str = "resanitize_stored_html";
+
+/// Sanitize publisher HTML for display.
+///
+/// # Arguments
+/// * `html` - Raw summary or content HTML
+/// * `base` - URL relative links and images are resolved against; without
+///   one, relative URLs are dropped
+pub fn sanitize_html(html: &str, base: Option<&Url>) -> String {
+    builder(base).clean(&remove_tracking_pixels(html)).to_string()
+}
+
+/// Sanitize main content extracted from an article page.
+///
+/// Like [`sanitize_html`], but page chrome that can be left inside the
+/// extracted container (navigation, headers, footers, asides, forms) is
+/// removed together with its content.
+pub fn sanitize_extracted_html(html: &str, page_url: &Url) -> String {
+    let chrome = ["nav", "header", "footer", "aside", "form"];
+
+    let mut builder = builder(Some(page_url));
+    builder.rm_tags(chrome).add_clean_content_tags(chrome);
+
+    builder.clean(&remove_tracking_pixels(html)).to_string()
+}
+
+/// The allowlist shared by all sanitization.
+fn builder(base: Option<&Url>) -> Builder<'static> {
+    let mut builder = Builder::default();
+    builder
+        .url_schemes(URL_SCHEMES.iter().copied().collect::<HashSet<_>>())
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .url_relative(match base {
+            Some(base) => UrlRelative::RewriteWithBase(base.clone()),
+            None => UrlRelative::Deny,
+        });
+
+    builder
+}
+
+/// Sanitize an optional field, resolving against the feed's site URL.
+pub fn sanitize_field(html: Option<&str>, base_url: Option<&str>) -> Option<String> {
+    let base = base_url.and_then(|u| Url::parse(u).ok());
+    html.map(|h| sanitize_html(h, base.as_ref()))
+}
+
+/// Drop `<img>` tags that are tracking pixels: 1x1 (or smaller) images and
+/// images served from known tracker hosts.
+fn remove_tracking_pixels(html: &str) -> String {
+    let selector = Selector::parse("img").expect("valid selector");
+    let mut fragment = Html::parse_fragment(html);
+
+    let pixels: Vec<_> = fragment
+        .select(&selector)
+        .filter(|img| {
+            let attrs = img.value();
+            let tiny = |name: &str| {
+                attrs
+                    .attr(name)
+                    .and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok())
+                    .is_some_and(|size| size <= 1)
+            };
+            let tracker = attrs
+                .attr("src")
+                .and_then(|src| Url::parse(src.trim()).ok())
+                .and_then(|src| src.host_str().map(str::to_ascii_lowercase))
+                .is_some_and(|host| TRACKER_HOSTS.contains(&host.as_str()));
+
+            (tiny("width") && tiny("height")) || tracker
+        })
+        .map(|img| img.id())
+        .collect();
+
+    if pixels.is_empty() {
+        return html.to_string();
+    }
+
+    for id in pixels {
+        if let Some(mut node) = fragment.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    fragment.root_element().inner_html()
+}
+
+/// Re-run sanitization over every stored article's raw HTML.
+///
+/// Used after the sanitization rules change. Articles are processed in
+/// batches; only rows whose sanitized output differs are written.
+///
+/// # Returns
+/// The number of articles updated.
+pub async fn resanitize_all(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut after: Option<Uuid> = None;
+    let mut updated = 0;
+
+    loop {
+        let batch = articles::list_raw_html(pool, after, RESANITIZE_BATCH_SIZE).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some(last.id);
+
+        for row in &batch {
+            let base_url = row.site_url.as_deref().or(Some(row.feed_url.as_str()));
+            let summary = sanitize_field(row.raw_summary.as_deref(), base_url);
+            let content = sanitize_field(row.raw_content.as_deref(), base_url);
+
+            if summary != row.summary || content != row.content {
+                articles::update_sanitized_html(pool, row.id, summary.as_deref(), content.as_deref())
+                    .await?;
+                updated += 1;
+            }
+        }
+    }
+
+    Ok(updated)
+}
+
+/// Sanitize articles stored before sanitization at ingestion, once.
+///
+/// Their raw HTML was copied from the unsanitized summary and content, so
+/// [`resanitize_all`] brings them in line with newer articles. Runs at startup
+/// until it has completed once.
+///
+/// # Returns
+/// The number of articles updated (`None` if it had already run).
+pub async fn resanitize_stored_html(pool: &PgPool) -> Result<Option<usize>, sqlx::Error> {
+    if !maintenance::is_pending(pool, RESANITIZE_TASK).await? {
+        return Ok(None);
+    }
+
+    let updated = resanitize_all(pool).await?;
+    maintenance::mark_completed(pool, RESANITIZE_TASK).await?;
+
+    Ok(Some(updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/blog/").unwrap()
+    }
+
+    #[test]
+    fn test_strips_scripts_and_event_handlers() {
+        let clean = sanitize_html(
+            r#"<p onclick="steal()">Hi<script>alert(1)</script></p><img src="/a.png" onerror="x()">"#,
+            Some(&base()),
+        );
+        assert!(!clean.contains("script"));
+        assert!(!clean.contains("onclick"));
+        assert!(!clean.contains("onerror"));
+        assert!(clean.contains("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn test_strips_javascript_urls() {
+        let clean = sanitize_html(r#"<a href="javascript:alert(1)">x</a>"#, Some(&base()));
+        assert!(!clean.contains("javascript"));
+    }
+
+    #[test]
+    fn test_resolves_relative_urls_against_base() {
+        let clean = sanitize_html(
+            r#"<a href="post/1">Post</a><img src="/img/cat.jpg" alt="cat">"#,
+            Some(&base()),
+        );
+        assert!(clean.contains(r#"href="https://example.com/blog/post/1""#));
+        assert!(clean.contains(r#"src="https://example.com/img/cat.jpg""#));
+        assert!(clean.contains(r#"rel="noopener noreferrer nofollow""#));
+    }
+
+    #[test]
+    fn test_drops_relative_urls_without_base() {
+        let clean = sanitize_html(r#"<a href="/post">Post</a>"#, None);
+        assert!(!clean.contains("href"));
+    }
+
+    #[test]
+    fn test_removes_tracking_pixels() {
+        let clean = sanitize_html(
+            r#"<p>Story</p><img src="https://example.com/p.gif" width="1" height="1"><img src="https://feeds.feedburner.com/~r/x/~4/abc"><img src="https://example.com/photo.jpg" width="600">"#,
+            Some(&base()),
+        );
+        assert!(!clean.contains("p.gif"));
+        assert!(!clean.contains("feedburner"));
+        assert!(clean.contains("photo.jpg"));
+    }
+
+    #[sqlx::test]
+    async fn test_resanitize_stored_html_runs_once(pool: PgPool) {
+        let feed_id = test_support::insert_feed(&pool, "https://example.com/feed").await;
+        let id = test_support::insert_article(&pool, feed_id, "a").await;
+        // Stored before sanitization: raw and display copies are the same
+        let html = r#"<p>Hi<script>alert(1)</script></p>"#;
+        sqlx::query("UPDATE articles SET content = $2, raw_content = $2 WHERE id = $1")
+            .bind(id)
+            .bind(html)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(resanitize_stored_html(&pool).await.unwrap(), Some(1));
+        assert_eq!(resanitize_stored_html(&pool).await.unwrap(), None);
+
+        let content: String = sqlx::query_scalar("SELECT content FROM articles WHERE id = $1")
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(content, "<p>Hi</p>");
+    }
+}
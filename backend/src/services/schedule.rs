@@ -0,0 +1,298 @@
+//! Adaptive per-feed fetch scheduling.
+//!
+//! Each feed gets its own fetch interval derived from how often it actually
+//! posts, nudged by the publisher's own hints (RSS `<ttl>`, `<skipHours>`,
+//! `<skipDays>` and the syndication module's `sy:updatePeriod` /
+//! `sy:updateFrequency`), and bounded by configurable min/max values.
+
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc, Weekday};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::models::feed::Feed;
+
+/// Interval used when a feed has too few dated entries to measure.
+pub const DEFAULT_INTERVAL_MINUTES: i64 = 15;
+
+/// How many of the most recent entries are used to measure posting frequency.
+const MAX_SAMPLE_ENTRIES: usize = 20;
+
+/// Publisher-supplied scheduling hints that feed-rs does not expose.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScheduleHints {
+    /// RSS `<ttl>`: minutes the feed may be cached.
+    pub ttl_minutes: Option<i64>,
+    /// RSS `<skipHours>`: hours of the day (0-23, GMT) not to fetch in.
+    pub skip_hours: Vec<u32>,
+    /// RSS `<skipDays>`: days of the week not to fetch on.
+    pub skip_days: Vec<Weekday>,
+    /// `sy:updatePeriod` divided by `sy:updateFrequency`, in minutes.
+    pub update_period_minutes: Option<i64>,
+}
+
+impl ScheduleHints {
+    /// Scan a raw feed document for scheduling hints.
+    ///
+    /// Unknown or malformed values are ignored; a document that fails to scan
+    /// part-way keeps whatever hints were read before the error.
+    pub fn from_xml(bytes: &[u8]) -> Self {
+        let mut reader = Reader::from_reader(bytes);
+        reader.config_mut().trim_text(true);
+
+        let mut hints = ScheduleHints::default();
+        let mut path: Vec<String> = Vec::new();
+        let mut update_period: Option<String> = None;
+        let mut update_frequency: Option<i64> = None;
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => {
+                    path.push(String::from_utf8_lossy(e.local_name().as_ref()).to_string());
+                }
+                Ok(Event::End(_)) => {
+                    path.pop();
+                }
+                Ok(Event::Text(e)) => {
+                    let Ok(text) = e.unescape() else {
+                        continue;
+                    };
+                    let text = text.trim();
+                    let parent = path.iter().rev().nth(1).map(String::as_str);
+
+                    match (parent, path.last().map(String::as_str)) {
+                        (Some("channel"), Some("ttl")) => {
+                            hints.ttl_minutes = text.parse().ok().filter(|m| *m > 0);
+                        }
+                        (Some("skipHours"), Some("hour")) => {
+                            if let Some(hour) = text.parse::<u32>().ok().map(|h| h % 24) {
+                                hints.skip_hours.push(hour);
+                            }
+                        }
+                        (Some("skipDays"), Some("day")) => {
+                            if let Ok(day) = text.parse::<Weekday>() {
+                                hints.skip_days.push(day);
+                            }
+                        }
+                        (_, Some("updatePeriod")) => update_period = Some(text.to_lowercase()),
+                        (_, Some("updateFrequency")) => {
+                            update_frequency = text.parse().ok().filter(|f| *f > 0);
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+        }
+
+        let period_minutes = match update_period.as_deref() {
+            Some("hourly") => Some(60),
+            Some("daily") => Some(60 * 24),
+            Some("weekly") => Some(60 * 24 * 7),
+            Some("monthly") => Some(60 * 24 * 30),
+            Some("yearly") => Some(60 * 24 * 365),
+            _ => None,
+        };
+        hints.update_period_minutes = period_minutes.map(|p| p / update_frequency.unwrap_or(1));
+
+        hints
+    }
+}
+
+/// Compute a feed's fetch interval in minutes.
+///
+/// The base interval is half the average gap between the most recent entries,
+/// so a new post is usually picked up well before the next one appears. The
+/// publisher's `ttl` and `sy:updatePeriod` act as lower bounds, and the result
+/// is clamped to `[min_minutes, max_minutes]`.
+///
+/// # Arguments
+/// * `published` - Publication times of the feed's entries (any order)
+/// * `hints` - Hints scanned from the feed document
+/// * `min_minutes` / `max_minutes` - Configured bounds
+pub fn compute_interval_minutes(
+    published: &[DateTime<Utc>],
+    hints: &ScheduleHints,
+    min_minutes: i64,
+    max_minutes: i64,
+) -> i64 {
+    let mut dates = published.to_vec();
+    dates.sort_unstable_by(|a, b| b.cmp(a));
+    dates.truncate(MAX_SAMPLE_ENTRIES);
+
+    let mut interval = match (dates.first(), dates.last()) {
+        (Some(newest), Some(oldest)) if dates.len() >= 2 => {
+            let average_gap = (*newest - *oldest).num_minutes() / (dates.len() as i64 - 1);
+            average_gap / 2
+        }
+        _ => DEFAULT_INTERVAL_MINUTES,
+    };
+
+    if let Some(ttl) = hints.ttl_minutes {
+        interval = interval.max(ttl);
+    }
+    if let Some(period) = hints.update_period_minutes {
+        interval = interval.max(period);
+    }
+
+    interval.clamp(min_minutes, max_minutes.max(min_minutes))
+}
+
+/// When the feed should next be fetched.
+///
+/// Starts at `now + interval_minutes` and moves forward to the start of the
+/// next allowed hour while the time falls in `skipHours` or `skipDays`.
+pub fn next_fetch_at(
+    now: DateTime<Utc>,
+    interval_minutes: i64,
+    hints: &ScheduleHints,
+) -> DateTime<Utc> {
+    let mut next = now + Duration::minutes(interval_minutes);
+
+    // A week of hours covers every combination; bail out if everything is skipped
+    for _ in 0..(24 * 7) {
+        let skipped = hints.skip_hours.contains(&next.hour())
+            || hints.skip_days.contains(&next.weekday());
+        if !skipped {
+            return next;
+        }
+
+        next = next
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(next)
+            + Duration::hours(1);
+    }
+
+    now + Duration::minutes(interval_minutes)
+}
+
+/// Delay before retrying a failing feed, in minutes.
+///
+/// Doubles the feed's normal interval for every consecutive failure after the
+/// first, capped at `max_minutes`.
+pub fn backoff_minutes(base_minutes: i64, consecutive_failures: i32, max_minutes: i64) -> i64 {
+    let exponent = consecutive_failures.saturating_sub(1).clamp(0, 20) as u32;
+    base_minutes
+        .max(1)
+        .saturating_mul(1_i64 << exponent)
+        .min(max_minutes.max(base_minutes))
+}
+
+/// A feed's current fetch interval, for when a fetch produced nothing new to
+/// adapt to (a 304 Not Modified).
+pub fn current_interval_minutes(feed: &Feed) -> i64 {
+    feed.fetch_interval_minutes
+        .map(i64::from)
+        .unwrap_or(DEFAULT_INTERVAL_MINUTES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_hints_from_rss() {
+        let xml = br#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:sy="http://purl.org/rss/1.0/modules/syndication/"><channel>
+  <title>Hints</title>
+  <ttl>60</ttl>
+  <sy:updatePeriod>daily</sy:updatePeriod>
+  <sy:updateFrequency>4</sy:updateFrequency>
+  <skipHours><hour>0</hour><hour>1</hour></skipHours>
+  <skipDays><day>Saturday</day><day>Sunday</day></skipDays>
+  <item><title>x</title><ttl>5</ttl></item>
+</channel></rss>"#;
+
+        let hints = ScheduleHints::from_xml(xml);
+
+        assert_eq!(hints.ttl_minutes, Some(60));
+        assert_eq!(hints.update_period_minutes, Some(360));
+        assert_eq!(hints.skip_hours, vec![0, 1]);
+        assert_eq!(hints.skip_days, vec![Weekday::Sat, Weekday::Sun]);
+    }
+
+    #[test]
+    fn test_hints_from_atom_are_empty() {
+        let xml = br#"<feed xmlns="http://www.w3.org/2005/Atom"><title>t</title></feed>"#;
+        assert_eq!(ScheduleHints::from_xml(xml), ScheduleHints::default());
+    }
+
+    #[test]
+    fn test_interval_from_posting_frequency() {
+        // One post per hour -> fetch every 30 minutes
+        let published: Vec<_> = (0..10).map(|h| at(2024, 1, 1, h, 0)).collect();
+        let interval = compute_interval_minutes(&published, &ScheduleHints::default(), 5, 1440);
+        assert_eq!(interval, 30);
+    }
+
+    #[test]
+    fn test_interval_is_clamped() {
+        // A post every minute is clamped up to the minimum
+        let busy: Vec<_> = (0..10).map(|m| at(2024, 1, 1, 12, m)).collect();
+        assert_eq!(compute_interval_minutes(&busy, &ScheduleHints::default(), 5, 1440), 5);
+
+        // One post a month is clamped down to the maximum
+        let quiet: Vec<_> = (1..6).map(|m| at(2024, m, 1, 0, 0)).collect();
+        assert_eq!(compute_interval_minutes(&quiet, &ScheduleHints::default(), 5, 1440), 1440);
+    }
+
+    #[test]
+    fn test_interval_honors_ttl_and_update_period() {
+        let published: Vec<_> = (0..10).map(|h| at(2024, 1, 1, h, 0)).collect();
+
+        let ttl = ScheduleHints {
+            ttl_minutes: Some(120),
+            ..Default::default()
+        };
+        assert_eq!(compute_interval_minutes(&published, &ttl, 5, 1440), 120);
+
+        let sy = ScheduleHints {
+            update_period_minutes: Some(60 * 24 * 7),
+            ..Default::default()
+        };
+        assert_eq!(compute_interval_minutes(&published, &sy, 5, 1440), 1440);
+    }
+
+    #[test]
+    fn test_interval_without_history_uses_default() {
+        let interval = compute_interval_minutes(&[], &ScheduleHints::default(), 5, 1440);
+        assert_eq!(interval, DEFAULT_INTERVAL_MINUTES);
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff_minutes(15, 1, 1440), 15);
+        assert_eq!(backoff_minutes(15, 2, 1440), 30);
+        assert_eq!(backoff_minutes(15, 4, 1440), 120);
+        assert_eq!(backoff_minutes(15, 10, 1440), 1440);
+        assert_eq!(backoff_minutes(15, i32::MAX, 1440), 1440);
+    }
+
+    #[test]
+    fn test_next_fetch_skips_hours_and_days() {
+        let hints = ScheduleHints {
+            skip_hours: vec![23, 0, 1],
+            skip_days: vec![Weekday::Sun],
+            ..Default::default()
+        };
+
+        // Friday 22:50 + 30 min lands in skipped hour 23 -> Saturday 02:00
+        let friday = at(2024, 1, 5, 22, 50);
+        assert_eq!(next_fetch_at(friday, 30, &hints), at(2024, 1, 6, 2, 0));
+
+        // Saturday 22:50 + 30 min -> skips the rest of Saturday night and all Sunday
+        let saturday = at(2024, 1, 6, 22, 50);
+        assert_eq!(next_fetch_at(saturday, 30, &hints), at(2024, 1, 8, 2, 0));
+
+        // Nothing skipped
+        let monday = at(2024, 1, 8, 12, 0);
+        assert_eq!(next_fetch_at(monday, 30, &hints), at(2024, 1, 8, 12, 30));
+    }
+}
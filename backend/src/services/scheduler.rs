@@ -1,107 +1,202 @@
 //! Background RSS feed scheduler service.
 //!
-//! This module provides a background task that periodically fetches all active
-//! RSS feeds and stores new articles in the database.
+//! This module provides a background task that periodically checks for active
+//! RSS feeds that are due (per their adaptive `next_fetch_at`), fetches them and
+//! stores new articles in the database.
 
 use sqlx::PgPool;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tokio::time;
 use tracing::{error, info};
 
+use crate::config::Config;
 use crate::db::feeds;
 use crate::services::fetcher::FeedFetcher;
+use crate::services::host_limiter::{host_key, HostLimiter};
+use crate::services::retention;
 
-/// Default fetch interval in minutes.
-const DEFAULT_INTERVAL_MINUTES: u64 = 15;
+/// Default interval between checks for due feeds, in seconds.
+const DEFAULT_TICK_SECONDS: u64 = 60;
+
+/// How often old fetch history and expired articles are pruned.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 /// Background scheduler for fetching RSS feeds.
 ///
-/// The scheduler periodically fetches all feeds that have at least one subscriber,
-/// storing new articles in the database.
+/// The scheduler periodically fetches feeds that have at least one subscriber and
+/// are due, storing new articles in the database. Feeds are fetched concurrently, bounded
+/// by a global limit and by per-host limits so one origin is never hammered.
 pub struct FeedScheduler {
     pool: PgPool,
-    fetcher: FeedFetcher,
+    fetcher: Arc<FeedFetcher>,
     interval: Duration,
+    concurrency: usize,
+    host_limiter: Arc<HostLimiter>,
+    article_retention_days: i32,
+    article_prune_batch_size: i64,
+    article_prune_dry_run: bool,
 }
 
 impl FeedScheduler {
-    /// Create a new FeedScheduler with the default interval (15 minutes).
-    pub fn new(pool: PgPool) -> Self {
-        Self::with_interval(pool, DEFAULT_INTERVAL_MINUTES)
+    /// Create a new FeedScheduler that checks for due feeds every minute.
+    pub fn new(
+        pool: PgPool,
+        fetcher: Arc<FeedFetcher>,
+        host_limiter: Arc<HostLimiter>,
+        config: &Config,
+    ) -> Self {
+        Self::with_interval(pool, fetcher, host_limiter, config, DEFAULT_TICK_SECONDS)
     }
 
-    /// Create a new FeedScheduler with a custom interval.
+    /// Create a new FeedScheduler with a custom check interval.
+    ///
+    /// How often each individual feed is fetched is decided per feed (see
+    /// `services::schedule`); this only controls how often due feeds are picked up.
     ///
     /// # Arguments
     /// * `pool` - Database connection pool
-    /// * `interval_minutes` - How often to fetch feeds, in minutes
-    pub fn with_interval(pool: PgPool, interval_minutes: u64) -> Self {
-        let fetcher = FeedFetcher::new(pool.clone());
+    /// * `fetcher` - Feed fetcher shared with the API routes
+    /// * `host_limiter` - Per-host limits shared with on-demand refreshes
+    /// * `config` - Application config (fetch concurrency and article retention)
+    /// * `interval_seconds` - How often to check for due feeds, in seconds
+    pub fn with_interval(
+        pool: PgPool,
+        fetcher: Arc<FeedFetcher>,
+        host_limiter: Arc<HostLimiter>,
+        config: &Config,
+        interval_seconds: u64,
+    ) -> Self {
         Self {
             pool,
             fetcher,
-            interval: Duration::from_secs(interval_minutes * 60),
+            interval: Duration::from_secs(interval_seconds),
+            concurrency: config.fetch_concurrency.max(1),
+            host_limiter,
+            article_retention_days: config.article_retention_days,
+            article_prune_batch_size: config.article_prune_batch_size,
+            article_prune_dry_run: config.article_prune_dry_run,
         }
     }
 
     /// Run the scheduler loop indefinitely.
     ///
     /// This method will:
-    /// 1. Immediately perform a fetch on startup
-    /// 2. Sleep for the configured interval
-    /// 3. Repeat
+    /// 1. Immediately fetch due feeds on startup
+    /// 2. Renew WebSub subscriptions whose lease is running out
+    /// 3. Prune old fetch history and expired articles (hourly)
+    /// 4. Sleep for the configured interval
+    /// 5. Repeat
     ///
     /// This method never returns under normal operation.
     pub async fn run(&self) {
         let mut interval = time::interval(self.interval);
+        // A slow cycle shouldn't trigger a burst of catch-up cycles
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
 
         info!(
-            interval_minutes = self.interval.as_secs() / 60,
+            interval_seconds = self.interval.as_secs(),
             "Starting feed scheduler"
         );
 
+        let mut last_prune: Option<time::Instant> = None;
+
         // Run immediately on start, then on interval
         loop {
             interval.tick().await;
             self.fetch_all_feeds().await;
+            self.renew_websub_subscriptions().await;
+
+            if last_prune.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                self.prune_fetch_log().await;
+                self.prune_articles().await;
+                last_prune = Some(time::Instant::now());
+            }
         }
     }
 
-    /// Fetch all active feeds once.
+    /// Fetch all due active feeds once.
     ///
     /// This is the main work function that:
-    /// 1. Gets all feeds with at least one subscriber
-    /// 2. Fetches each feed and stores new articles
-    /// 3. Logs success/failure for each feed
+    /// 1. Gets all feeds with at least one subscriber whose `next_fetch_at` has passed
+    /// 2. Fetches feeds concurrently (bounded globally and per host) and stores new articles
+    /// 3. Logs success/failure for each feed and the aggregated totals
     async fn fetch_all_feeds(&self) {
         info!("Starting scheduled feed fetch");
 
-        let active_feeds = match feeds::get_all_active_feeds(&self.pool).await {
+        let active_feeds = match feeds::get_due_feeds(&self.pool).await {
             Ok(f) => f,
             Err(e) => {
-                error!("Failed to get active feeds: {}", e);
+                error!("Failed to get due feeds: {}", e);
                 return;
             }
         };
 
         if active_feeds.is_empty() {
-            info!("No active feeds to fetch");
+            tracing::debug!("No feeds due for fetching");
             return;
         }
 
-        info!(feed_count = active_feeds.len(), "Fetching active feeds");
+        info!(
+            feed_count = active_feeds.len(),
+            concurrency = self.concurrency,
+            "Fetching due feeds"
+        );
+
+        let global_permits = Arc::new(Semaphore::new(self.concurrency));
+        let mut tasks = JoinSet::new();
+
+        for feed in active_feeds {
+            // An on-demand refresh of the feed may already be running
+            let Some(claim) = self.fetcher.try_claim(feed.id) else {
+                tracing::debug!(feed_id = %feed.id, "Feed is already being fetched");
+                continue;
+            };
+            let fetcher = self.fetcher.clone();
+            let host_limiter = self.host_limiter.clone();
+            let global_permits = global_permits.clone();
+
+            tasks.spawn(async move {
+                let _claim = claim;
+                // Take the host slot first so feeds waiting on a busy host
+                // don't hold global permits that other hosts could use
+                let _host_permit = host_limiter.acquire(&host_key(&feed.url)).await;
+                let _permit = global_permits
+                    .acquire_owned()
+                    .await
+                    .expect("fetch semaphore is never closed");
+
+                let result = fetcher.fetch_feed(&feed).await;
+                (feed, result)
+            });
+        }
 
         let mut success_count = 0;
         let mut failure_count = 0;
 
-        for feed in active_feeds {
-            match self.fetcher.fetch_feed(feed.id, &feed.url).await {
+        while let Some(joined) = tasks.join_next().await {
+            let (feed, result) = match joined {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    failure_count += 1;
+                    error!(error = %e, "Feed fetch task panicked");
+                    continue;
+                }
+            };
+
+            match result {
                 Ok(result) => {
                     success_count += 1;
                     info!(
-                        feed_id = %feed.id,
+                        feed_id = %result.feed_id,
                         feed_title = %feed.title,
-                        articles_fetched = result.articles_fetched,
+                        new_articles = result.new_articles,
+                        updated_articles = result.updated_articles,
+                        unchanged_articles = result.unchanged_articles,
+                        not_modified = result.not_modified,
+                        moved_to = ?result.moved_to,
                         errors = result.errors.len(),
                         "Feed fetch completed"
                     );
@@ -120,6 +215,7 @@ impl FeedScheduler {
                     error!(
                         feed_id = %feed.id,
                         feed_title = %feed.title,
+                        consecutive_failures = feed.consecutive_failures + 1,
                         error = %e,
                         "Failed to fetch feed"
                     );
@@ -133,6 +229,49 @@ impl FeedScheduler {
             "Completed scheduled feed fetch"
         );
     }
+
+    /// Renew WebSub leases that are about to expire.
+    async fn renew_websub_subscriptions(&self) {
+        match self.fetcher.renew_websub_subscriptions().await {
+            Ok(0) => {}
+            Ok(renewed) => info!(renewed, "Renewed WebSub subscriptions"),
+            Err(e) => error!("Failed to renew WebSub subscriptions: {}", e),
+        }
+    }
+
+    /// Delete fetch history past its retention.
+    async fn prune_fetch_log(&self) {
+        match self.fetcher.prune_fetch_log().await {
+            Ok(0) => {}
+            Ok(deleted) => info!(deleted, "Pruned feed fetch log"),
+            Err(e) => error!("Failed to prune feed fetch log: {}", e),
+        }
+    }
+
+    /// Delete unsaved articles past the retention window (unless retention is off).
+    async fn prune_articles(&self) {
+        if self.article_retention_days <= 0 {
+            return;
+        }
+
+        match retention::prune_articles(
+            &self.pool,
+            self.article_retention_days,
+            self.article_prune_batch_size,
+            self.article_prune_dry_run,
+        )
+        .await
+        {
+            Ok(report) => info!(
+                dry_run = report.dry_run,
+                deleted = report.deleted,
+                kept = report.kept,
+                "{}",
+                report
+            ),
+            Err(e) => error!("Failed to prune articles: {}", e),
+        }
+    }
 }
 
 #[cfg(test)]
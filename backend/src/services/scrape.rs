@@ -0,0 +1,333 @@
+//! Scraped sources: articles read from an HTML page for sites without a feed.
+//!
+//! A scraped source is a page URL plus CSS selectors. Every element matching
+//! the `item` selector is one article; the other selectors are matched inside
+//! it to find the article's title, link, date and summary. Scraped articles go
+//! through the same storage as feed entries (identity, sanitizing, updates).
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::models::feed::Feed;
+
+/// Most items read from one page.
+const MAX_ITEMS: usize = 100;
+
+/// Date formats tried (after RFC 3339 and RFC 2822) for a date with a time.
+const DATE_TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Date formats tried for a date without a time (taken as midnight UTC).
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%B %d, %Y",
+    "%b %d, %Y",
+    "%d %B %Y",
+    "%d %b %Y",
+    "%Y/%m/%d",
+];
+
+/// CSS selectors describing where a page's articles are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrapeRules {
+    /// Each element matching this is one article; the selectors below are
+    /// matched inside it.
+    pub item: String,
+    /// The article's title (the element's text).
+    pub title: String,
+    /// The article's link: the element's `href`, or that of the first link
+    /// inside it.
+    pub link: String,
+    /// The publication date: the element's `datetime` attribute, or its text.
+    pub date: Option<String>,
+    /// The article's summary (the element's text).
+    pub summary: Option<String>,
+}
+
+/// Parsed selectors of a [`ScrapeRules`].
+struct Selectors {
+    item: Selector,
+    title: Selector,
+    link: Selector,
+    date: Option<Selector>,
+    summary: Option<Selector>,
+}
+
+impl ScrapeRules {
+    /// The rules stored on a scraped source.
+    pub fn of(feed: &Feed) -> Result<Self, ScrapeError> {
+        feed.scrape_rules
+            .clone()
+            .and_then(|rules| serde_json::from_value(rules).ok())
+            .ok_or(ScrapeError::InvalidRules)
+    }
+
+    /// Check that every selector parses.
+    pub fn validate(&self) -> Result<(), ScrapeError> {
+        self.selectors().map(|_| ())
+    }
+
+    fn selectors(&self) -> Result<Selectors, ScrapeError> {
+        Ok(Selectors {
+            item: parse_selector("item", &self.item)?,
+            title: parse_selector("title", &self.title)?,
+            link: parse_selector("link", &self.link)?,
+            date: self.date.as_deref().map(|s| parse_selector("date", s)).transpose()?,
+            summary: self
+                .summary
+                .as_deref()
+                .map(|s| parse_selector("summary", s))
+                .transpose()?,
+        })
+    }
+}
+
+/// An article read from a page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScrapedItem {
+    pub title: String,
+    /// Absolute URL of the article.
+    pub url: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub summary: Option<String>,
+}
+
+/// Everything read from a page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScrapedPage {
+    /// The page's `<title>`.
+    pub title: Option<String>,
+    pub items: Vec<ScrapedItem>,
+    /// Items that were skipped, and why.
+    pub errors: Vec<String>,
+}
+
+/// Errors that can occur while scraping a page.
+#[derive(Debug)]
+pub enum ScrapeError {
+    /// A selector isn't valid CSS.
+    InvalidSelector {
+        field: &'static str,
+        selector: String,
+    },
+    /// The feed's stored rules are missing or unreadable.
+    InvalidRules,
+    /// The item selector matched nothing on the page.
+    NoItems,
+}
+
+impl std::fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScrapeError::InvalidSelector { field, selector } => {
+                write!(f, "Invalid {} selector '{}'", field, selector)
+            }
+            ScrapeError::InvalidRules => write!(f, "Scrape rules are missing or invalid"),
+            ScrapeError::NoItems => write!(f, "The item selector matched nothing on the page"),
+        }
+    }
+}
+
+impl std::error::Error for ScrapeError {}
+
+/// Read the articles on a page.
+///
+/// Items without a title or a link are skipped (and listed in `errors`).
+///
+/// # Arguments
+/// * `html` - The page's HTML
+/// * `page_url` - Where the page was served from (relative links resolve against it)
+/// * `rules` - The selectors to apply
+pub fn scrape(html: &str, page_url: &Url, rules: &ScrapeRules) -> Result<ScrapedPage, ScrapeError> {
+    let selectors = rules.selectors()?;
+    let document = Html::parse_document(html);
+    let title_selector = Selector::parse("title").expect("valid selector");
+
+    let mut matched = 0;
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    for (index, item) in document.select(&selectors.item).take(MAX_ITEMS).enumerate() {
+        matched += 1;
+        let position = index + 1;
+
+        let Some(title) = item.select(&selectors.title).next().map(element_text).filter(|t| !t.is_empty())
+        else {
+            errors.push(format!("Item {} has no title, skipping", position));
+            continue;
+        };
+        let Some(url) = item
+            .select(&selectors.link)
+            .next()
+            .and_then(link_href)
+            .and_then(|href| page_url.join(href.trim()).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+        else {
+            errors.push(format!("Item '{}' has no link, skipping", title));
+            continue;
+        };
+
+        let published_at = selectors
+            .date
+            .as_ref()
+            .and_then(|selector| item.select(selector).next())
+            .and_then(|element| match element.value().attr("datetime") {
+                Some(datetime) => parse_date(datetime),
+                None => parse_date(&element_text(element)),
+            });
+        let summary = selectors
+            .summary
+            .as_ref()
+            .and_then(|selector| item.select(selector).next())
+            .map(element_text)
+            .filter(|s| !s.is_empty());
+
+        items.push(ScrapedItem {
+            title,
+            url: url.to_string(),
+            published_at,
+            summary,
+        });
+    }
+
+    if matched == 0 {
+        return Err(ScrapeError::NoItems);
+    }
+
+    Ok(ScrapedPage {
+        title: document
+            .select(&title_selector)
+            .next()
+            .map(element_text)
+            .filter(|t| !t.is_empty()),
+        items,
+        errors,
+    })
+}
+
+fn parse_selector(field: &'static str, selector: &str) -> Result<Selector, ScrapeError> {
+    Selector::parse(selector).map_err(|_| ScrapeError::InvalidSelector {
+        field,
+        selector: selector.to_string(),
+    })
+}
+
+/// An element's text with whitespace collapsed.
+fn element_text(element: ElementRef) -> String {
+    element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The element's `href`, or that of the first link inside it.
+fn link_href<'a>(element: ElementRef<'a>) -> Option<&'a str> {
+    let links = Selector::parse("a[href]").expect("valid selector");
+    element
+        .value()
+        .attr("href")
+        .or_else(|| element.select(&links).next().and_then(|link| link.value().attr("href")))
+}
+
+/// Parse a date as written on a page; dates without a timezone are taken as UTC.
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(text).or_else(|_| DateTime::parse_from_rfc2822(text)) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Some(date) = DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    {
+        return Some(date.and_utc());
+    }
+
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head><title> Acme News </title></head><body>
+        <div class="post">
+          <h2><a href="/news/one">First   post</a></h2>
+          <time datetime="2024-03-01T10:00:00Z">March 1</time>
+          <p class="teaser">The <b>first</b> one.</p>
+        </div>
+        <div class="post">
+          <h2>Second post</h2>
+          <a class="more" href="https://other.example/two">Read more</a>
+          <span class="date">March 2, 2024</span>
+        </div>
+        <div class="post"><h2>No link here</h2></div>
+        <div class="post"><a href="/untitled"></a></div>
+      </body></html>"#;
+
+    fn rules() -> ScrapeRules {
+        ScrapeRules {
+            item: "div.post".to_string(),
+            title: "h2".to_string(),
+            link: "a".to_string(),
+            date: Some("time, .date".to_string()),
+            summary: Some(".teaser".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_scrape_items() {
+        let page_url = Url::parse("https://acme.example/news").unwrap();
+        let page = scrape(PAGE, &page_url, &rules()).unwrap();
+
+        assert_eq!(page.title.as_deref(), Some("Acme News"));
+        assert_eq!(
+            page.items,
+            vec![
+                ScrapedItem {
+                    title: "First post".to_string(),
+                    url: "https://acme.example/news/one".to_string(),
+                    published_at: Some("2024-03-01T10:00:00Z".parse().unwrap()),
+                    summary: Some("The first one.".to_string()),
+                },
+                ScrapedItem {
+                    title: "Second post".to_string(),
+                    url: "https://other.example/two".to_string(),
+                    published_at: Some("2024-03-02T00:00:00Z".parse().unwrap()),
+                    summary: None,
+                },
+            ]
+        );
+        assert_eq!(page.errors.len(), 2);
+    }
+
+    #[test]
+    fn test_scrape_errors() {
+        let page_url = Url::parse("https://acme.example/").unwrap();
+
+        let invalid = ScrapeRules {
+            title: "h2[".to_string(),
+            ..rules()
+        };
+        assert!(matches!(
+            invalid.validate(),
+            Err(ScrapeError::InvalidSelector { field: "title", .. })
+        ));
+
+        let unmatched = ScrapeRules {
+            item: "article".to_string(),
+            ..rules()
+        };
+        assert!(matches!(scrape(PAGE, &page_url, &unmatched), Err(ScrapeError::NoItems)));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let expected: DateTime<Utc> = "2024-03-02T00:00:00Z".parse().unwrap();
+        assert_eq!(parse_date("2024-03-02"), Some(expected));
+        assert_eq!(parse_date(" 2 March 2024 "), Some(expected));
+        assert_eq!(parse_date("Sat, 02 Mar 2024 00:00:00 +0000"), Some(expected));
+        assert_eq!(parse_date("yesterday"), None);
+    }
+}
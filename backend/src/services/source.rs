@@ -0,0 +1,766 @@
+//! Feed sources: where a feed's content comes from.
+//!
+//! A [`FeedSource`] fetches a feed and normalizes it into a [`SourceDocument`]
+//! (metadata, scheduling hints and entries) that the fetcher stores the same
+//! way whatever the source. Each feed kind has its source: feed documents over
+//! HTTP ([`HttpSource`]), scraped HTML pages ([`ScrapeSource`]) and feed files
+//! on this server ([`FileSource`]).
+
+use chrono::Utc;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
+use std::future::Future;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+use crate::models::article::ArticleMedia;
+use crate::models::feed::Feed;
+use crate::services::canonical;
+use crate::services::decode;
+use crate::services::fetcher::FetchError;
+use crate::services::http;
+use crate::services::identity::EntryIdentity;
+use crate::services::media;
+use crate::services::schedule::ScheduleHints;
+use crate::services::scrape::{self, ScrapeRules, ScrapedItem, ScrapedPage};
+use crate::services::url_guard::UrlGuard;
+use crate::services::websub::{self, HubLinks};
+
+/// Column limits on the `feeds` table that parsed metadata must respect.
+const MAX_TITLE_CHARS: usize = 500;
+const MAX_URL_CHARS: usize = 2000;
+const MAX_LANGUAGE_CHARS: usize = 50;
+
+/// How a feed's content is fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedKind {
+    /// An RSS, Atom or JSON feed document fetched over HTTP(S).
+    Feed,
+    /// An HTML page read with the feed's scrape rules.
+    Scrape,
+    /// A feed document read from a `file://` URL.
+    File,
+}
+
+impl FeedKind {
+    /// The kind stored on a feed (unknown values fall back to `feed`).
+    pub fn of(feed: &Feed) -> Self {
+        match feed.kind.as_str() {
+            "scrape" => FeedKind::Scrape,
+            "file" => FeedKind::File,
+            _ => FeedKind::Feed,
+        }
+    }
+}
+
+/// Fetches a feed's content.
+pub trait FeedSource {
+    /// Fetch the feed's current content.
+    ///
+    /// `attempt` records what was received along the way (even when the fetch
+    /// fails), for the fetch log and for following permanent moves.
+    fn fetch(
+        &self,
+        feed: &Feed,
+        attempt: &mut FetchAttempt,
+    ) -> impl Future<Output = Result<Fetched, FetchError>> + Send;
+}
+
+/// What a fetch attempt got from the server, for the fetch log.
+#[derive(Debug)]
+pub struct FetchAttempt {
+    /// The feed's id after following a permanent redirect (set by the fetcher).
+    pub feed_id: uuid::Uuid,
+    pub http_status: Option<StatusCode>,
+    pub bytes: Option<usize>,
+    /// Where the feed permanently moved, when the move should be followed.
+    pub moved_to: Option<Url>,
+}
+
+impl FetchAttempt {
+    pub fn new(feed_id: uuid::Uuid) -> Self {
+        Self {
+            feed_id,
+            http_status: None,
+            bytes: None,
+            moved_to: None,
+        }
+    }
+}
+
+/// Result of fetching from a source.
+#[derive(Debug)]
+pub enum Fetched {
+    /// The content hasn't changed since the last fetch.
+    NotModified,
+    Document(Box<SourceDocument>),
+}
+
+/// A feed's content, normalized for storage.
+#[derive(Debug)]
+pub struct SourceDocument {
+    pub metadata: SourceMetadata,
+    pub hints: ScheduleHints,
+    /// The feed's WebSub hub, if it advertises one.
+    pub hub: Option<HubLinks>,
+    pub entries: Vec<SourceEntry>,
+    /// Non-fatal problems: repairs to a malformed document, skipped entries.
+    pub errors: Vec<String>,
+    /// Cache validators to send on the next fetch; `None` leaves the stored
+    /// ones alone (e.g. for pushed content).
+    pub validators: Option<Validators>,
+}
+
+/// Cache validators for conditional fetches.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Descriptive metadata of a feed, written back to `feeds`.
+#[derive(Debug, Default, PartialEq)]
+pub struct SourceMetadata {
+    pub title: Option<String>,
+    pub site_url: Option<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub icon_url: Option<String>,
+}
+
+impl SourceMetadata {
+    /// Extract metadata from a parsed feed.
+    ///
+    /// The site URL is the first HTML `alternate` link (RSS `<link>` has no rel),
+    /// so Atom `self` links and links to other feed formats are skipped. The icon
+    /// prefers the feed's icon over its (usually larger) logo.
+    fn from_parsed(feed: &feed_rs::model::Feed) -> Self {
+        let site_url = feed
+            .links
+            .iter()
+            .find(|link| {
+                let is_alternate = link.rel.as_deref().is_none_or(|rel| rel == "alternate");
+                let is_html = link
+                    .media_type
+                    .as_deref()
+                    .is_none_or(|media_type| media_type.contains("html"));
+                is_alternate && is_html
+            })
+            .map(|link| link.href.trim().to_string());
+
+        let icon_url = feed
+            .icon
+            .as_ref()
+            .or(feed.logo.as_ref())
+            .map(|image| image.uri.trim().to_string());
+
+        Self {
+            title: feed
+                .title
+                .as_ref()
+                .map(|t| t.content.trim().chars().take(MAX_TITLE_CHARS).collect::<String>())
+                .filter(|t| !t.is_empty()),
+            site_url: site_url.filter(|u| !u.is_empty() && u.len() <= MAX_URL_CHARS),
+            description: feed
+                .description
+                .as_ref()
+                .map(|d| d.content.trim().to_string())
+                .filter(|d| !d.is_empty()),
+            language: feed
+                .language
+                .as_ref()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty() && l.len() <= MAX_LANGUAGE_CHARS),
+            icon_url: icon_url.filter(|u| !u.is_empty() && u.len() <= MAX_URL_CHARS),
+        }
+    }
+
+    /// Metadata of a scraped page: only its title.
+    fn from_scraped(page: &ScrapedPage) -> Self {
+        Self {
+            title: page
+                .title
+                .as_ref()
+                .map(|t| t.chars().take(MAX_TITLE_CHARS).collect()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Article fields of one feed entry.
+#[derive(Debug)]
+pub struct SourceEntry {
+    /// The publisher's guid; `None` when the entry had no id.
+    pub guid: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub canonical_url: Option<String>,
+    pub author: Option<String>,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    pub published_at: Option<chrono::DateTime<Utc>>,
+    /// Enclosures and thumbnails (the inline image is added once the content
+    /// is sanitized).
+    pub media: Vec<ArticleMedia>,
+}
+
+impl SourceEntry {
+    /// Extract an entry's fields; entries without a link can't be stored.
+    fn from_entry(entry: feed_rs::model::Entry) -> Result<Self, String> {
+        let media = media::entry_media(&entry);
+        let title = entry
+            .title
+            .map(|t| t.content)
+            .unwrap_or_else(|| "Untitled".to_string());
+
+        let url = match entry.links.first() {
+            Some(link) => link.href.clone(),
+            None => return Err(format!("Entry '{}' has no URL, skipping", entry.id)),
+        };
+
+        Ok(Self {
+            media,
+            guid: Some(entry.id).filter(|id| !id.is_empty()),
+            canonical_url: canonical::canonical_for_entry(&entry.links, &url)
+                .filter(|u| u.len() <= MAX_URL_CHARS),
+            title,
+            url,
+            author: entry.authors.first().map(|a| a.name.clone()),
+            summary: entry.summary.map(|s| s.content),
+            content: entry.content.and_then(|c| c.body),
+            published_at: entry.published.or(entry.updated),
+        })
+    }
+
+    /// An item read from a scraped page (identified by its link).
+    fn from_scraped(item: ScrapedItem) -> Self {
+        Self {
+            guid: None,
+            title: item.title,
+            url: item.url,
+            canonical_url: None,
+            author: None,
+            summary: item.summary,
+            content: None,
+            published_at: item.published_at,
+            media: Vec::new(),
+        }
+    }
+
+    pub fn identity(&self) -> EntryIdentity<'_> {
+        EntryIdentity {
+            guid: self.guid.as_deref(),
+            link: &self.url,
+            canonical_url: self.canonical_url.as_deref(),
+            title: &self.title,
+            published_at: self.published_at,
+        }
+    }
+}
+
+/// Parse a feed document (RSS, Atom or JSON Feed), repairing it if it's
+/// malformed.
+///
+/// # Arguments
+/// * `bytes` - The document as received
+/// * `content_type` - Its `Content-Type`, whose charset is used to decode it
+/// * `feed_url` - Where it was fetched from (the WebSub topic fallback)
+pub fn parse_feed_document(
+    bytes: &[u8],
+    content_type: Option<&str>,
+    feed_url: &str,
+) -> Result<SourceDocument, FetchError> {
+    let decode::Parsed {
+        feed: parsed,
+        document,
+        repairs,
+    } = decode::parse_lenient(bytes, content_type, parse_feed)?;
+    let mut errors = repairs;
+
+    let metadata = SourceMetadata::from_parsed(&parsed);
+    let hub = websub::hub_links(&parsed, feed_url);
+    let hints = ScheduleHints::from_xml(document.as_bytes());
+
+    let mut entries = Vec::with_capacity(parsed.entries.len());
+    for entry in parsed.entries {
+        match SourceEntry::from_entry(entry) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    Ok(SourceDocument {
+        metadata,
+        hints,
+        hub,
+        entries,
+        errors,
+        validators: None,
+    })
+}
+
+/// Parse a feed document.
+///
+/// Entries without an id of their own are left with an empty one instead of
+/// the id feed-rs would derive from their link and title, so article identity
+/// can tell publisher guids from missing ones.
+pub fn parse_feed(bytes: &[u8]) -> Result<feed_rs::model::Feed, feed_rs::parser::ParseFeedError> {
+    feed_rs::parser::Builder::new()
+        .id_generator(|_links, _title, _uri| String::new())
+        .build()
+        .parse(bytes)
+}
+
+/// A body downloaded over HTTP.
+struct Download {
+    /// URL the body was served from.
+    page_url: Url,
+    content_type: Option<String>,
+    bytes: Vec<u8>,
+    validators: Validators,
+}
+
+/// Feed documents fetched over HTTP(S).
+pub struct HttpSource<'a> {
+    pub client: &'a Client,
+    pub guard: &'a UrlGuard,
+    /// Largest body read, in bytes.
+    pub max_body_bytes: usize,
+    /// A private feed's credential headers (empty for shared feeds).
+    pub credentials: HeaderMap,
+}
+
+impl HttpSource<'_> {
+    /// Download the feed's URL, conditionally if it has validators.
+    ///
+    /// # Returns
+    /// `None` when the server answered `304 Not Modified`.
+    async fn download(
+        &self,
+        feed: &Feed,
+        attempt: &mut FetchAttempt,
+    ) -> Result<Option<Download>, FetchError> {
+        let followed = http::get_following_redirects_with_credentials(
+            self.client,
+            self.guard,
+            &feed.url,
+            &http::conditional_headers(feed.etag.as_deref(), feed.last_modified.as_deref()),
+            &self.credentials,
+        )
+        .await?;
+        let page_url = followed.final_url;
+        let response = followed.response;
+        attempt.http_status = Some(response.status());
+
+        if response.status() == StatusCode::GONE {
+            return Err(FetchError::Gone);
+        }
+
+        // Only follow the move once the new location actually answered. Private
+        // feeds don't move to another site: their credentials would go with them
+        if let Some(new_url) = followed.permanent_url
+            && (response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED)
+            && (self.credentials.is_empty()
+                || Url::parse(&feed.url).is_ok_and(|old| old.origin() == new_url.origin()))
+        {
+            attempt.moved_to = Some(new_url);
+        }
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        let response = response.error_for_status()?;
+        let content_type = http::header_string(response.headers(), CONTENT_TYPE);
+        if let Some(content_type) = content_type
+            .as_deref()
+            .filter(|content_type| !is_feed_content_type(content_type))
+        {
+            return Err(FetchError::UnexpectedContentType(content_type.to_string()));
+        }
+        let validators = Validators {
+            etag: http::header_string(response.headers(), ETAG),
+            last_modified: http::header_string(response.headers(), LAST_MODIFIED),
+        };
+        let bytes = http::read_body_limited(response, self.max_body_bytes).await?;
+        attempt.bytes = Some(bytes.len());
+
+        Ok(Some(Download {
+            page_url,
+            content_type,
+            bytes,
+            validators,
+        }))
+    }
+}
+
+impl FeedSource for HttpSource<'_> {
+    async fn fetch(&self, feed: &Feed, attempt: &mut FetchAttempt) -> Result<Fetched, FetchError> {
+        let Some(download) = self.download(feed, attempt).await? else {
+            return Ok(Fetched::NotModified);
+        };
+
+        let document =
+            parse_feed_document(&download.bytes, download.content_type.as_deref(), &feed.url)?;
+        Ok(Fetched::Document(Box::new(SourceDocument {
+            validators: Some(download.validators),
+            ..document
+        })))
+    }
+}
+
+/// HTML pages fetched over HTTP(S) and read with a scraped source's rules.
+pub struct ScrapeSource<'a> {
+    pub http: HttpSource<'a>,
+    pub rules: ScrapeRules,
+}
+
+impl FeedSource for ScrapeSource<'_> {
+    async fn fetch(&self, feed: &Feed, attempt: &mut FetchAttempt) -> Result<Fetched, FetchError> {
+        let Some(download) = self.http.download(feed, attempt).await? else {
+            return Ok(Fetched::NotModified);
+        };
+
+        let html = decode::decode(&download.bytes, download.content_type.as_deref()).text;
+        let page = scrape::scrape(&html, &download.page_url, &self.rules)?;
+
+        Ok(Fetched::Document(Box::new(SourceDocument {
+            metadata: SourceMetadata::from_scraped(&page),
+            hints: ScheduleHints::default(),
+            hub: None,
+            entries: page.items.into_iter().map(SourceEntry::from_scraped).collect(),
+            errors: page.errors,
+            validators: Some(download.validators),
+        })))
+    }
+}
+
+/// Feed documents read from `file://` URLs inside a root directory.
+pub struct FileSource<'a> {
+    /// Canonical path of the directory feeds may be read from.
+    pub root: &'a Path,
+    /// Largest file read, in bytes.
+    pub max_bytes: usize,
+}
+
+impl FileSource<'_> {
+    /// The path of a `file://` URL, if it is inside the root directory.
+    ///
+    /// The path is checked before the filesystem is touched, so nothing is
+    /// learned about files outside the root, and again once symlinks are
+    /// resolved.
+    pub async fn resolve(&self, url: &str) -> Result<PathBuf, FetchError> {
+        let path = Url::parse(url)
+            .ok()
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or_else(|| file_error(io::ErrorKind::InvalidInput, "Not a file:// URL"))?;
+        if !normalize_path(&path).starts_with(self.root) {
+            return Err(outside_root());
+        }
+
+        let path = tokio::fs::canonicalize(&path).await.map_err(FetchError::FileError)?;
+        if !path.starts_with(self.root) {
+            return Err(outside_root());
+        }
+        Ok(path)
+    }
+}
+
+/// `path` with `.` and `..` components resolved lexically.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn outside_root() -> FetchError {
+    file_error(io::ErrorKind::PermissionDenied, "File is outside FEED_FILE_ROOT")
+}
+
+impl FeedSource for FileSource<'_> {
+    async fn fetch(&self, feed: &Feed, attempt: &mut FetchAttempt) -> Result<Fetched, FetchError> {
+        let path = self.resolve(&feed.url).await?;
+        let file = tokio::fs::metadata(&path).await.map_err(FetchError::FileError)?;
+        if file.len() > self.max_bytes as u64 {
+            return Err(FetchError::ResponseTooLarge(self.max_bytes));
+        }
+
+        // The modification time stands in for Last-Modified
+        let last_modified = file
+            .modified()
+            .ok()
+            .map(|modified| chrono::DateTime::<Utc>::from(modified).to_rfc3339());
+        if last_modified.is_some() && last_modified == feed.last_modified {
+            return Ok(Fetched::NotModified);
+        }
+
+        let bytes = tokio::fs::read(&path).await.map_err(FetchError::FileError)?;
+        attempt.bytes = Some(bytes.len());
+
+        let document = parse_feed_document(&bytes, None, &feed.url)?;
+        Ok(Fetched::Document(Box::new(SourceDocument {
+            hub: None,
+            validators: Some(Validators {
+                etag: None,
+                last_modified,
+            }),
+            ..document
+        })))
+    }
+}
+
+fn file_error(kind: io::ErrorKind, message: &str) -> FetchError {
+    FetchError::FileError(io::Error::new(kind, message))
+}
+
+/// Whether a `Content-Type` could be a feed.
+///
+/// Feeds are served under many types (RSS/Atom/XML, JSON Feed, `text/plain`,
+/// even `text/html`), so only types that can't be one are refused: images,
+/// audio, video, fonts, PDFs, archives and the like.
+fn is_feed_content_type(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    essence.is_empty()
+        || essence.starts_with("text/")
+        || essence == "application/octet-stream"
+        || ["xml", "rss", "atom", "json"].iter().any(|t| essence.contains(t))
+}
+
+/// A feed document held in memory, for exercising entry mapping offline.
+#[cfg(test)]
+pub struct FixtureSource {
+    pub document: &'static str,
+    pub content_type: Option<&'static str>,
+}
+
+#[cfg(test)]
+impl FeedSource for FixtureSource {
+    async fn fetch(&self, feed: &Feed, attempt: &mut FetchAttempt) -> Result<Fetched, FetchError> {
+        attempt.bytes = Some(self.document.len());
+        parse_feed_document(self.document.as_bytes(), self.content_type, &feed.url)
+            .map(|document| Fetched::Document(Box::new(document)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(url: &str) -> Feed {
+        Feed {
+            id: uuid::Uuid::new_v4(),
+            title: url.to_string(),
+            url: url.to_string(),
+            site_url: None,
+            description: None,
+            topic_id: None,
+            is_curated: false,
+            language: None,
+            icon_url: None,
+            title_locked: false,
+            last_fetched_at: None,
+            etag: None,
+            last_modified: None,
+            fetch_interval_minutes: None,
+            next_fetch_at: None,
+            consecutive_failures: 0,
+            last_error: None,
+            last_error_at: None,
+            last_success_at: None,
+            disabled: false,
+            gone_at: None,
+            identity_strategy: "auto".to_string(),
+            fetch_full_content: false,
+            owner_user_id: None,
+            credentials: None,
+            kind: "feed".to_string(),
+            scrape_rules: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    async fn fetch_fixture(document: &'static str) -> SourceDocument {
+        let feed = feed("https://example.com/feed.xml");
+        let source = FixtureSource {
+            document,
+            content_type: Some("application/rss+xml"),
+        };
+        let mut attempt = FetchAttempt::new(feed.id);
+
+        match source.fetch(&feed, &mut attempt).await.unwrap() {
+            Fetched::Document(document) => *document,
+            Fetched::NotModified => panic!("fixtures always return a document"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_entry_mapping() {
+        let document = fetch_fixture(
+            r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/"><channel>
+  <title>Example</title>
+  <link>https://example.com/</link>
+  <item>
+    <title>With guid</title>
+    <link>https://example.com/a?utm_source=rss</link>
+    <guid isPermaLink="false">post-1</guid>
+    <dc:creator>Ada</dc:creator>
+    <description>&lt;p&gt;Summary&lt;/p&gt;</description>
+    <pubDate>Mon, 04 Mar 2024 10:00:00 GMT</pubDate>
+    <enclosure url="https://example.com/a.mp3" type="audio/mpeg" length="1024"/>
+  </item>
+  <item>
+    <link>https://example.com/b</link>
+  </item>
+  <item>
+    <title>No link</title>
+    <guid>post-3</guid>
+  </item>
+</channel></rss>"#,
+        )
+        .await;
+
+        assert_eq!(document.metadata.title.as_deref(), Some("Example"));
+        assert_eq!(document.entries.len(), 2);
+        assert_eq!(document.errors, vec!["Entry 'post-3' has no URL, skipping"]);
+
+        let first = &document.entries[0];
+        assert_eq!(first.guid.as_deref(), Some("post-1"));
+        assert_eq!(first.title, "With guid");
+        assert_eq!(first.url, "https://example.com/a?utm_source=rss");
+        assert_eq!(first.author.as_deref(), Some("Ada"));
+        assert_eq!(first.summary.as_deref(), Some("<p>Summary</p>"));
+        assert_eq!(first.published_at, Some("2024-03-04T10:00:00Z".parse().unwrap()));
+        assert_eq!(first.media.len(), 1);
+
+        let second = &document.entries[1];
+        assert_eq!(second.guid, None);
+        assert_eq!(second.title, "Untitled");
+        assert_eq!(second.published_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_file_source_stays_in_root() {
+        let root = std::env::temp_dir().join(format!("herald-feeds-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let root = std::fs::canonicalize(&root).unwrap();
+        let path = root.join("feed.xml");
+        std::fs::write(
+            &path,
+            r#"<rss version="2.0"><channel><title>Local</title>
+<item><title>A</title><link>https://example.com/a</link></item></channel></rss>"#,
+        )
+        .unwrap();
+        let source = FileSource {
+            root: &root,
+            max_bytes: 1024,
+        };
+
+        let mut feed = feed(Url::from_file_path(&path).unwrap().as_str());
+        let mut attempt = FetchAttempt::new(feed.id);
+        let Fetched::Document(document) = source.fetch(&feed, &mut attempt).await.unwrap() else {
+            panic!("first read returns the document");
+        };
+        assert_eq!(document.entries.len(), 1);
+        assert_eq!(attempt.bytes, Some(std::fs::metadata(&path).unwrap().len() as usize));
+
+        // Unchanged since the last read
+        feed.last_modified = document.validators.unwrap().last_modified;
+        assert!(matches!(
+            source.fetch(&feed, &mut attempt).await.unwrap(),
+            Fetched::NotModified
+        ));
+
+        // A file next to the root, not in it
+        let outside = root.with_extension("xml");
+        std::fs::write(&outside, "<rss/>").unwrap();
+        assert!(matches!(
+            source.resolve(Url::from_file_path(&outside).unwrap().as_str()).await,
+            Err(FetchError::FileError(_))
+        ));
+        assert!(source.resolve("https://example.com/feed.xml").await.is_err());
+
+        // Climbing out is refused without looking for the file
+        let climb = format!("{}/../missing.xml", Url::from_file_path(&root).unwrap());
+        let Err(FetchError::FileError(e)) = source.resolve(&climb).await else {
+            panic!("paths outside the root are refused");
+        };
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(normalize_path(Path::new("/a/./b/../c")), Path::new("/a/c"));
+
+        std::fs::remove_file(&outside).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_is_feed_content_type() {
+        assert!(is_feed_content_type("application/rss+xml; charset=utf-8"));
+        assert!(is_feed_content_type("application/atom+xml"));
+        assert!(is_feed_content_type("text/xml"));
+        assert!(is_feed_content_type("application/feed+json"));
+        assert!(is_feed_content_type("text/html"));
+        assert!(!is_feed_content_type("image/png"));
+        assert!(!is_feed_content_type("video/mp4"));
+        assert!(!is_feed_content_type("application/pdf"));
+    }
+
+    #[test]
+    fn test_metadata_from_rss() {
+        let xml = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+  <title> Ars Technica </title>
+  <atom:link href="https://feeds.arstechnica.com/arstechnica/index" rel="self" type="application/rss+xml"/>
+  <link>https://arstechnica.com</link>
+  <description>Serving the Technologist</description>
+  <language>en-us</language>
+  <image><url>https://arstechnica.com/logo.png</url><title>Ars</title><link>https://arstechnica.com</link></image>
+</channel></rss>"#;
+        let parsed = feed_rs::parser::parse(xml.as_bytes()).unwrap();
+
+        let metadata = SourceMetadata::from_parsed(&parsed);
+
+        assert_eq!(metadata.title.as_deref(), Some("Ars Technica"));
+        assert_eq!(metadata.site_url.as_deref(), Some("https://arstechnica.com/"));
+        assert_eq!(metadata.description.as_deref(), Some("Serving the Technologist"));
+        assert_eq!(metadata.language.as_deref(), Some("en-us"));
+        assert_eq!(metadata.icon_url.as_deref(), Some("https://arstechnica.com/logo.png"));
+    }
+
+    #[test]
+    fn test_metadata_from_atom_skips_self_link() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Example Blog</title>
+  <id>urn:example</id>
+  <updated>2024-01-01T00:00:00Z</updated>
+  <link rel="self" href="https://example.com/atom.xml"/>
+  <link rel="alternate" type="text/html" href="https://example.com/"/>
+  <icon>https://example.com/favicon.ico</icon>
+  <logo>https://example.com/logo.png</logo>
+</feed>"#;
+        let parsed = feed_rs::parser::parse(xml.as_bytes()).unwrap();
+
+        let metadata = SourceMetadata::from_parsed(&parsed);
+
+        assert_eq!(metadata.site_url.as_deref(), Some("https://example.com/"));
+        assert_eq!(metadata.icon_url.as_deref(), Some("https://example.com/favicon.ico"));
+        assert_eq!(metadata.description, None);
+    }
+}
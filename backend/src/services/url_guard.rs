@@ -0,0 +1,348 @@
+//! Outgoing request guard (SSRF protection).
+//!
+//! Feed URLs come from users, and feeds point at hubs and article pages we
+//! then request from inside our network. Every outgoing URL must be http(s),
+//! and every address it resolves to must be public: loopback, private,
+//! link-local (cloud metadata at 169.254.169.254), CGNAT, multicast and
+//! reserved ranges are refused. Addresses are checked when a connection is
+//! made (by the client's DNS resolver, so DNS rebinding doesn't get around it)
+//! and IP-literal URLs are checked before each request and redirect hop.
+//!
+//! Self-hosters reading intranet feeds can allowlist hosts, addresses and CIDR
+//! ranges with `FETCH_ALLOWLIST`.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use url::{Host, Url};
+
+/// Why a URL may not be requested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockedUrl {
+    /// Only http and https are fetched.
+    UnsupportedScheme(String),
+    /// The URL has no host.
+    MissingHost,
+    /// The host is (or resolves only to) a non-public address.
+    PrivateAddress(String),
+    /// The host name could not be resolved.
+    Unresolvable(String),
+}
+
+impl std::fmt::Display for BlockedUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockedUrl::UnsupportedScheme(scheme) => {
+                write!(f, "Unsupported URL scheme '{}' (only http and https)", scheme)
+            }
+            BlockedUrl::MissingHost => write!(f, "URL has no host"),
+            BlockedUrl::PrivateAddress(host) => {
+                write!(f, "{} is a private or reserved network address", host)
+            }
+            BlockedUrl::Unresolvable(host) => write!(f, "Could not resolve host {}", host),
+        }
+    }
+}
+
+impl std::error::Error for BlockedUrl {}
+
+/// Checks outgoing URLs and resolved addresses against the blocked ranges and
+/// the configured allowlist. Cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct UrlGuard {
+    allowlist: Arc<Allowlist>,
+}
+
+#[derive(Debug, Default)]
+struct Allowlist {
+    /// Lowercased host names allowed whatever they resolve to.
+    hosts: Vec<String>,
+    /// Address ranges (network, prefix length) allowed despite being private.
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl UrlGuard {
+    /// Build a guard from a comma-separated allowlist of host names, IP
+    /// addresses and CIDR ranges (e.g. `intranet.example,10.0.0.0/8`).
+    pub fn from_allowlist(spec: &str) -> Result<Self, String> {
+        let mut allowlist = Allowlist::default();
+
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            if let Some((network, prefix)) = entry.split_once('/') {
+                let network: IpAddr = network
+                    .parse()
+                    .map_err(|_| format!("Invalid allowlist network: {}", entry))?;
+                let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+                let prefix: u8 = prefix
+                    .parse()
+                    .ok()
+                    .filter(|p| *p <= max_prefix)
+                    .ok_or_else(|| format!("Invalid allowlist prefix: {}", entry))?;
+                allowlist.networks.push((network, prefix));
+            } else if let Ok(ip) = entry.trim_matches(['[', ']']).parse::<IpAddr>() {
+                allowlist.networks.push((ip, if ip.is_ipv4() { 32 } else { 128 }));
+            } else {
+                allowlist.hosts.push(entry.trim_end_matches('.').to_ascii_lowercase());
+            }
+        }
+
+        Ok(Self {
+            allowlist: Arc::new(allowlist),
+        })
+    }
+
+    /// Check a URL's scheme, and its address if the host is an IP literal.
+    /// Host names are checked when they are resolved (see [`UrlGuard::resolver`]).
+    pub fn check_url(&self, url: &Url) -> Result<(), BlockedUrl> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(BlockedUrl::UnsupportedScheme(url.scheme().to_string()));
+        }
+
+        let ip = match url.host() {
+            None => return Err(BlockedUrl::MissingHost),
+            Some(Host::Domain(_)) => return Ok(()),
+            Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        };
+
+        if self.allows(None, ip) {
+            Ok(())
+        } else {
+            Err(BlockedUrl::PrivateAddress(ip.to_string()))
+        }
+    }
+
+    /// [`UrlGuard::check_url`], plus resolving the host name now. Used to give
+    /// users a clear error up front; requests are still checked on connect.
+    pub async fn check_url_resolved(&self, url: &Url) -> Result<(), BlockedUrl> {
+        self.check_url(url)?;
+
+        let Some(Host::Domain(host)) = url.host() else {
+            return Ok(());
+        };
+        self.resolve_allowed(host).await.map(|_| ())
+    }
+
+    /// A DNS resolver for the HTTP client that only returns allowed addresses.
+    pub fn resolver(&self) -> Arc<GuardedResolver> {
+        Arc::new(GuardedResolver { guard: self.clone() })
+    }
+
+    /// Resolve `host`, keeping only the addresses that may be connected to.
+    async fn resolve_allowed(&self, host: &str) -> Result<Vec<SocketAddr>, BlockedUrl> {
+        let resolved: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|_| BlockedUrl::Unresolvable(host.to_string()))?
+            .collect();
+        if resolved.is_empty() {
+            return Err(BlockedUrl::Unresolvable(host.to_string()));
+        }
+
+        let allowed: Vec<SocketAddr> = resolved
+            .into_iter()
+            .filter(|addr| self.allows(Some(host), addr.ip()))
+            .collect();
+        if allowed.is_empty() {
+            return Err(BlockedUrl::PrivateAddress(host.to_string()));
+        }
+
+        Ok(allowed)
+    }
+
+    /// Whether `ip` (reached through `host`, if by name) may be connected to.
+    fn allows(&self, host: Option<&str>, ip: IpAddr) -> bool {
+        let host_allowed = host.is_some_and(|host| {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            self.allowlist.hosts.contains(&host)
+        });
+
+        host_allowed
+            || is_public(ip)
+            || self
+                .allowlist
+                .networks
+                .iter()
+                .any(|(network, prefix)| in_network(ip, *network, *prefix))
+    }
+}
+
+/// DNS resolver that drops blocked addresses (see [`UrlGuard::resolver`]).
+#[derive(Debug)]
+pub struct GuardedResolver {
+    guard: UrlGuard,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.guard.clone();
+        Box::pin(async move {
+            let addrs = guard.resolve_allowed(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether an address is on the public internet.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match embedded_v4(ip) {
+            Some(embedded) => is_public_v4(embedded),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+/// The IPv4 address an IPv6 address stands for, if it embeds one.
+///
+/// Traffic to these reaches the IPv4 address (through the host's stack, a
+/// NAT64 gateway or a 6to4 relay), so that is the address to check.
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let [.., a, b, c, d] = ip.octets();
+
+    match segments {
+        // IPv4-mapped, ::ffff:0:0/96, and IPv4-compatible, ::/96
+        [0, 0, 0, 0, 0, 0xffff | 0, ..] => ip.to_ipv4(),
+        // NAT64 well-known prefix, 64:ff9b::/96
+        [0x64, 0xff9b, 0, 0, 0, 0, ..] => Some(Ipv4Addr::new(a, b, c, d)),
+        // 6to4, 2002::/16 (the IPv4 address follows the prefix)
+        [0x2002, high, low, ..] => Some(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low))),
+        _ => None,
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // Shared address space (CGNAT), 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments, 192.0.0.0/24
+        || ip.octets()[..3] == [192, 0, 0]
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+        // Site-local (deprecated, still routed internally), fec0::/10
+        || (first & 0xffc0) == 0xfec0
+        // Local-use NAT64, 64:ff9b:1::/48
+        || (first == 0x64 && ip.segments()[1..3] == [0xff9b, 1])
+        // Documentation, 2001:db8::/32
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+fn in_network(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_blocks_non_http_schemes() {
+        let guard = UrlGuard::default();
+        assert_eq!(
+            guard.check_url(&url("file:///etc/passwd")),
+            Err(BlockedUrl::UnsupportedScheme("file".to_string()))
+        );
+        assert!(guard.check_url(&url("gopher://example.com/")).is_err());
+        assert!(guard.check_url(&url("https://example.com/feed")).is_ok());
+    }
+
+    #[test]
+    fn test_blocks_private_ip_literals() {
+        let guard = UrlGuard::default();
+        for blocked in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:5432/",
+            "http://10.1.2.3/",
+            "http://172.16.0.1/",
+            "http://192.168.1.1/",
+            "http://100.64.0.1/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[::127.0.0.1]/",
+            "http://[::a9fe:a9fe]/",
+            "http://[64:ff9b::a9fe:a9fe]/",
+            "http://[64:ff9b::10.0.0.1]/",
+            "http://[64:ff9b:1::a00:1]/",
+            "http://[2002:7f00:1::]/",
+            "http://[2002:c0a8:101::1]/",
+            "http://[fec0::1]/",
+        ] {
+            assert!(guard.check_url(&url(blocked)).is_err(), "{} should be blocked", blocked);
+        }
+        assert!(guard.check_url(&url("http://93.184.216.34/")).is_ok());
+        assert!(guard.check_url(&url("http://[2606:4700::1111]/")).is_ok());
+        assert!(guard.check_url(&url("http://[64:ff9b::5db8:d822]/")).is_ok());
+        assert!(guard.check_url(&url("http://[2002:5db8:d822::1]/")).is_ok());
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let guard = UrlGuard::from_allowlist("intranet.example, 10.0.0.0/8, 127.0.0.1").unwrap();
+
+        assert!(guard.check_url(&url("http://10.20.30.40/feed")).is_ok());
+        assert!(guard.check_url(&url("http://127.0.0.1:8080/feed")).is_ok());
+        assert!(guard.check_url(&url("http://127.0.0.2/feed")).is_err());
+        assert!(guard.check_url(&url("http://192.168.1.1/feed")).is_err());
+
+        let intranet = "10.0.0.1".parse().unwrap();
+        assert!(guard.allows(Some("INTRANET.example."), "192.168.0.5".parse().unwrap()));
+        assert!(guard.allows(Some("other.example"), intranet));
+        assert!(!guard.allows(Some("other.example"), "192.168.0.5".parse().unwrap()));
+
+        assert!(UrlGuard::from_allowlist("10.0.0.0/33").is_err());
+        assert!(UrlGuard::from_allowlist("nonsense/8").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolved_hosts_are_checked() {
+        let guard = UrlGuard::default();
+        assert_eq!(
+            guard.check_url_resolved(&url("http://localhost:5432/")).await,
+            Err(BlockedUrl::PrivateAddress("localhost".to_string()))
+        );
+
+        let allowed = UrlGuard::from_allowlist("localhost").unwrap();
+        assert!(allowed.check_url_resolved(&url("http://localhost:5432/")).await.is_ok());
+    }
+}
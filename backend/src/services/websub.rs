@@ -0,0 +1,441 @@
+//! WebSub (PubSubHubbub) push subscriptions.
+//!
+//! Feeds that advertise a `rel="hub"` link get subscribed to their hub with
+//! `/api/websub/:feed_id` as the callback. The hub confirms the subscription
+//! by echoing a challenge to the callback (intent verification), then POSTs
+//! the feed document whenever it changes, signed with the subscription's
+//! secret (`X-Hub-Signature`). Pushed documents are ingested exactly like
+//! polled ones, and feeds with an active subscription are only polled as a
+//! safety net. Leases are renewed by the scheduler ahead of expiry.
+//!
+//! Requires `PUBLIC_BASE_URL` (the address hubs can reach Herald on); without
+//! it no subscriptions are made.
+
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+use sqlx::PgPool;
+use url::Url;
+use uuid::Uuid;
+
+use crate::db::websub::{self, WebSubSubscription};
+use crate::services::url_guard::{BlockedUrl, UrlGuard};
+
+/// Lease requested from hubs (hubs may grant a different one).
+pub const REQUESTED_LEASE_SECONDS: i32 = 10 * 24 * 60 * 60;
+
+/// Subscriptions renewed per scheduler tick.
+const RENEWAL_BATCH_SIZE: i64 = 50;
+
+/// Errors from talking to a hub.
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum WebSubError {
+    /// HTTP request to the hub failed.
+    HttpError(reqwest::Error),
+    /// The hub refused the subscription request.
+    HubRejected(StatusCode),
+    /// The hub URL points somewhere we don't send requests to.
+    Blocked(BlockedUrl),
+    /// Database operation failed.
+    DatabaseError(sqlx::Error),
+}
+
+impl std::fmt::Display for WebSubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebSubError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            WebSubError::HubRejected(status) => write!(f, "Hub rejected the request ({})", status),
+            WebSubError::Blocked(e) => write!(f, "Blocked hub URL: {}", e),
+            WebSubError::DatabaseError(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WebSubError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WebSubError::HttpError(e) => Some(e),
+            WebSubError::HubRejected(_) => None,
+            WebSubError::Blocked(e) => Some(e),
+            WebSubError::DatabaseError(e) => Some(e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for WebSubError {
+    fn from(err: reqwest::Error) -> Self {
+        WebSubError::HttpError(err)
+    }
+}
+
+impl From<sqlx::Error> for WebSubError {
+    fn from(err: sqlx::Error) -> Self {
+        WebSubError::DatabaseError(err)
+    }
+}
+
+/// The hub a feed advertises and the topic URL to subscribe to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HubLinks {
+    pub hub: String,
+    /// The feed's `rel="self"` link; hubs key subscriptions by it.
+    pub topic: String,
+}
+
+/// Find a parsed feed's hub. The topic is its `rel="self"` link, falling back
+/// to the URL it was fetched from.
+pub fn hub_links(feed: &feed_rs::model::Feed, feed_url: &str) -> Option<HubLinks> {
+    let link = |rel: &str| {
+        feed.links
+            .iter()
+            .find(|l| l.rel.as_deref() == Some(rel))
+            .map(|l| l.href.trim().to_string())
+            .filter(|href| Url::parse(href).is_ok_and(|u| matches!(u.scheme(), "http" | "https")))
+    };
+
+    Some(HubLinks {
+        hub: link("hub")?,
+        topic: link("self").unwrap_or_else(|| feed_url.to_string()),
+    })
+}
+
+/// The callback URL hubs deliver a feed's pushes to.
+pub fn callback_url(public_base_url: &Url, feed_id: Uuid) -> String {
+    format!(
+        "{}/api/websub/{}",
+        public_base_url.as_str().trim_end_matches('/'),
+        feed_id
+    )
+}
+
+/// A fresh random subscription secret (64 hex characters).
+fn new_secret() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Subscribe a feed to `links.hub`, unless it already has a subscription for
+/// the same hub and topic (renewals are handled by [`renew_due`]).
+pub async fn ensure_subscription(
+    client: &Client,
+    guard: &UrlGuard,
+    pool: &PgPool,
+    public_base_url: &Url,
+    feed_id: Uuid,
+    links: &HubLinks,
+) -> Result<(), WebSubError> {
+    if let Some(existing) = websub::get_subscription(pool, feed_id).await?
+        && existing.hub_url == links.hub
+        && existing.topic_url == links.topic
+    {
+        return Ok(());
+    }
+
+    // Stored before asking: hubs may verify before answering the request
+    let secret = new_secret();
+    websub::create_pending(pool, feed_id, &links.hub, &links.topic, &secret).await?;
+
+    let callback = callback_url(public_base_url, feed_id);
+    request_subscription(client, guard, &links.hub, &links.topic, &callback, &secret).await?;
+
+    tracing::info!(feed_id = %feed_id, hub = %links.hub, "Requested WebSub subscription");
+    Ok(())
+}
+
+/// Re-request subscriptions whose lease is about to expire, that the hub
+/// never verified, or that it denied a while ago.
+///
+/// # Returns
+/// The number of subscriptions requested.
+pub async fn renew_due(
+    client: &Client,
+    guard: &UrlGuard,
+    pool: &PgPool,
+    public_base_url: &Url,
+) -> Result<usize, sqlx::Error> {
+    let due = websub::list_due_for_renewal(pool, RENEWAL_BATCH_SIZE).await?;
+    let mut renewed = 0;
+
+    for subscription in &due {
+        websub::mark_requested(pool, subscription.feed_id).await?;
+
+        let callback = callback_url(public_base_url, subscription.feed_id);
+        match request_subscription(
+            client,
+            guard,
+            &subscription.hub_url,
+            &subscription.topic_url,
+            &callback,
+            &subscription.secret,
+        )
+        .await
+        {
+            Ok(()) => renewed += 1,
+            Err(e) => tracing::warn!(
+                feed_id = %subscription.feed_id,
+                hub = %subscription.hub_url,
+                error = %e,
+                "WebSub renewal failed"
+            ),
+        }
+    }
+
+    Ok(renewed)
+}
+
+/// Send a subscription request to a hub. Hubs answer `202 Accepted` and
+/// verify the intent asynchronously.
+pub async fn request_subscription(
+    client: &Client,
+    guard: &UrlGuard,
+    hub: &str,
+    topic: &str,
+    callback: &str,
+    secret: &str,
+) -> Result<(), WebSubError> {
+    let hub = Url::parse(hub).map_err(|_| WebSubError::Blocked(BlockedUrl::MissingHost))?;
+    guard.check_url(&hub).map_err(WebSubError::Blocked)?;
+
+    let lease_seconds = REQUESTED_LEASE_SECONDS.to_string();
+    let response = client
+        .post(hub)
+        .form(&[
+            ("hub.mode", "subscribe"),
+            ("hub.topic", topic),
+            ("hub.callback", callback),
+            ("hub.secret", secret),
+            ("hub.lease_seconds", lease_seconds.as_str()),
+        ])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(WebSubError::HubRejected(response.status()));
+    }
+
+    Ok(())
+}
+
+/// Query parameters of a hub's intent verification (or denial) request.
+#[derive(Debug, Deserialize)]
+pub struct VerificationRequest {
+    #[serde(rename = "hub.mode")]
+    pub mode: String,
+    #[serde(rename = "hub.topic")]
+    pub topic: String,
+    #[serde(rename = "hub.challenge")]
+    pub challenge: Option<String>,
+    #[serde(rename = "hub.lease_seconds")]
+    pub lease_seconds: Option<i32>,
+}
+
+/// How to answer a verification request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verification {
+    /// Confirm a subscription we asked for: echo the challenge and store the lease.
+    Confirm { challenge: String, lease_seconds: i32 },
+    /// The hub refused our subscription.
+    Denied,
+    /// Not a subscription we asked for (or an unsubscribe we didn't request).
+    Reject,
+}
+
+/// Decide how to answer a hub's verification request for a feed.
+pub fn verify_intent(
+    subscription: Option<&WebSubSubscription>,
+    request: &VerificationRequest,
+) -> Verification {
+    let Some(subscription) = subscription.filter(|s| s.topic_url == request.topic) else {
+        return Verification::Reject;
+    };
+
+    match (request.mode.as_str(), &request.challenge) {
+        ("subscribe", Some(challenge)) if subscription.state != "denied" => Verification::Confirm {
+            challenge: challenge.clone(),
+            lease_seconds: request
+                .lease_seconds
+                .filter(|l| *l > 0)
+                .unwrap_or(REQUESTED_LEASE_SECONDS),
+        },
+        ("denied", _) => Verification::Denied,
+        _ => Verification::Reject,
+    }
+}
+
+/// Check a push's `X-Hub-Signature` (`sha1=<hex>`, `sha256=<hex>`, ...)
+/// against the subscription secret.
+pub fn verify_signature(secret: &str, header: Option<&str>, body: &[u8]) -> bool {
+    let Some((method, signature)) = header.and_then(|h| h.trim().split_once('=')) else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    fn check<M: Mac + hmac::digest::KeyInit>(secret: &str, body: &[u8], signature: &[u8]) -> bool {
+        let mut mac = <M as Mac>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(body);
+        mac.verify_slice(signature).is_ok()
+    }
+
+    match method.to_ascii_lowercase().as_str() {
+        "sha1" => check::<Hmac<Sha1>>(secret, body, &signature),
+        "sha256" => check::<Hmac<Sha256>>(secret, body, &signature),
+        "sha384" => check::<Hmac<Sha384>>(secret, body, &signature),
+        "sha512" => check::<Hmac<Sha512>>(secret, body, &signature),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Form, Router};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    fn subscription(state: &str) -> WebSubSubscription {
+        WebSubSubscription {
+            feed_id: Uuid::new_v4(),
+            hub_url: "https://hub.example.com/".to_string(),
+            topic_url: "https://example.com/feed.xml".to_string(),
+            secret: "s3cret".to_string(),
+            state: state.to_string(),
+        }
+    }
+
+    fn verification(mode: &str, topic: &str) -> VerificationRequest {
+        VerificationRequest {
+            mode: mode.to_string(),
+            topic: topic.to_string(),
+            challenge: Some("abc123".to_string()),
+            lease_seconds: Some(3600),
+        }
+    }
+
+    #[test]
+    fn test_hub_links() {
+        let feed = feed_rs::parser::parse(
+            br#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+<title>Blog</title><link>https://example.com/</link>
+<atom:link rel="hub" href="https://hub.example.com/"/>
+<atom:link rel="self" href="https://example.com/feed.xml"/>
+</channel></rss>"# as &[u8],
+        )
+        .unwrap();
+
+        assert_eq!(
+            hub_links(&feed, "http://example.com/feed"),
+            Some(HubLinks {
+                hub: "https://hub.example.com/".to_string(),
+                topic: "https://example.com/feed.xml".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_intent() {
+        let pending = subscription("pending");
+        let topic = pending.topic_url.clone();
+
+        assert_eq!(
+            verify_intent(Some(&pending), &verification("subscribe", &topic)),
+            Verification::Confirm {
+                challenge: "abc123".to_string(),
+                lease_seconds: 3600
+            }
+        );
+        assert_eq!(
+            verify_intent(Some(&pending), &verification("subscribe", "https://evil.example/")),
+            Verification::Reject
+        );
+        assert_eq!(
+            verify_intent(Some(&pending), &verification("unsubscribe", &topic)),
+            Verification::Reject
+        );
+        assert_eq!(
+            verify_intent(None, &verification("subscribe", &topic)),
+            Verification::Reject
+        );
+        assert_eq!(
+            verify_intent(Some(&pending), &verification("denied", &topic)),
+            Verification::Denied
+        );
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = b"<feed/>";
+        let mut mac = Hmac::<Sha1>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body);
+        let sha1 = format!("sha1={}", hex::encode(mac.finalize().into_bytes()));
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body);
+        let sha256 = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature("s3cret", Some(&sha1), body));
+        assert!(verify_signature("s3cret", Some(&sha256), body));
+        assert!(!verify_signature("other", Some(&sha1), body));
+        assert!(!verify_signature("s3cret", Some(&sha1), b"<feed>tampered</feed>"));
+        assert!(!verify_signature("s3cret", None, body));
+        assert!(!verify_signature("s3cret", Some("md5=abcd"), body));
+    }
+
+    #[tokio::test]
+    async fn test_request_subscription_against_stand_in_hub() {
+        type Received = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+        async fn hub(
+            State(received): State<Received>,
+            Form(form): Form<HashMap<String, String>>,
+        ) -> StatusCode {
+            received.lock().unwrap().push(form);
+            StatusCode::ACCEPTED
+        }
+
+        let received: Received = Arc::default();
+        let app = Router::new().route("/hub", post(hub)).with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // The stand-in hub listens on loopback, which is blocked by default
+        let guard = UrlGuard::from_allowlist("127.0.0.1").unwrap();
+        let base = Url::parse("https://herald.example.com/").unwrap();
+        let feed_id = Uuid::new_v4();
+        request_subscription(
+            &Client::new(),
+            &guard,
+            &format!("http://{}/hub", addr),
+            "https://example.com/feed.xml",
+            &callback_url(&base, feed_id),
+            "s3cret",
+        )
+        .await
+        .unwrap();
+
+        let form = received.lock().unwrap().remove(0);
+        assert_eq!(form["hub.mode"], "subscribe");
+        assert_eq!(form["hub.topic"], "https://example.com/feed.xml");
+        assert_eq!(
+            form["hub.callback"],
+            format!("https://herald.example.com/api/websub/{}", feed_id)
+        );
+        assert_eq!(form["hub.secret"], "s3cret");
+
+        let rejected = request_subscription(
+            &Client::new(),
+            &guard,
+            &format!("http://{}/missing", addr),
+            "https://example.com/feed.xml",
+            "https://herald.example.com/api/websub/x",
+            "s3cret",
+        )
+        .await;
+        assert!(matches!(rejected, Err(WebSubError::HubRejected(StatusCode::NOT_FOUND))));
+    }
+}
@@ -111,6 +111,7 @@ function ArticleCard({ article, onSelect, onToggleSave, isSelected = false }) {
     summary,
     published_at,
     feed_title,
+    thumbnail_url,
     is_read = false,
     is_saved = false,
   } = article;
@@ -193,6 +194,21 @@ function ArticleCard({ article, onSelect, onToggleSave, isSelected = false }) {
         </time>
       </div>
 
+      {/* Thumbnail - from the feed's media or the first inline image */}
+      {thumbnail_url && (
+        <img
+          src={thumbnail_url}
+          alt=""
+          loading="lazy"
+          referrerPolicy="no-referrer"
+          className={`
+            w-full aspect-video object-cover rounded mb-3
+            border border-herald-border
+            ${is_read ? 'opacity-70' : ''}
+          `}
+        />
+      )}
+
       {/* Title - the hero */}
       <h2
         className={`